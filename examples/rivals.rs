@@ -1,9 +1,4 @@
-use std::path::PathBuf;
-
-use bevy::{
-    prelude::*,
-    render::texture::{ImageLoaderSettings, ImageSampler},
-};
+use bevy::{prelude::*, render::texture::ImageSampler};
 use serde::{Deserialize, Serialize};
 use yabuil::{
     views::NodeEntityMut, ActiveLayout, LayoutApp, LayoutAttribute, LayoutBundle, LayoutPlugin,
@@ -122,45 +117,6 @@ fn update_menu_buttons(
         });
 }
 
-#[derive(Deserialize, Serialize, Reflect)]
-pub struct ReplaceImage {
-    id: String,
-    path: PathBuf,
-}
-
-#[derive(Deserialize, Serialize, Reflect)]
-pub struct ReplaceText {
-    id: String,
-    text: String,
-}
-
-impl LayoutAttribute for ReplaceImage {
-    const NAME: &'static str = "ReplaceImage";
-    fn apply(&self, mut world: NodeEntityMut) {
-        let mut child = world.child(self.id.as_str());
-
-        let handle = child
-            .world()
-            .resource::<AssetServer>()
-            .load_with_settings::<_, ImageLoaderSettings>(self.path.clone(), |settings| {
-                settings.sampler = ImageSampler::nearest();
-            });
-
-        child.image().set_image(handle);
-    }
-}
-
-impl LayoutAttribute for ReplaceText {
-    const NAME: &'static str = "ReplaceText";
-
-    fn apply(&self, mut world: NodeEntityMut) {
-        world
-            .child(self.id.as_str())
-            .text()
-            .set_text(self.text.clone());
-    }
-}
-
 #[derive(Deserialize, Serialize, Reflect)]
 pub struct NearestNeighbor {}
 
@@ -225,8 +181,6 @@ pub fn main() {
         .add_systems(Startup, spawn_layout)
         .add_systems(Update, update_menu_buttons)
         .register_layout_attribute::<NearestNeighbor>()
-        .register_layout_attribute::<ReplaceImage>()
-        .register_layout_attribute::<ReplaceText>()
         .register_layout_attribute::<MainMenuButton>()
         .init_resource::<MenuMoveSfx>()
         .run();
@@ -1,19 +1,21 @@
-use std::any::TypeId;
+use std::{any::TypeId, fmt, hash::Hash, time::Duration};
 
 use bevy::{prelude::*, utils::hashbrown::HashMap, ecs::world::unsafe_world_cell::UnsafeWorldCell};
 use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::IndexMap;
 
 use crate::{node::LayoutHandle, LayoutNodeId, views::NodeMut};
 
 use serde::{Deserialize, Serialize};
 
+#[derive(Copy, Clone)]
 pub(crate) struct StaticTypeInfo {
     pub name: &'static str,
     pub type_path: &'static str,
     pub type_id: TypeId,
 }
 
-#[derive(Default, Deserialize, Serialize, Copy, Clone)]
+#[derive(Default, Deserialize, Serialize, Copy, Clone, PartialEq)]
 pub enum TimeBezierCurve {
     #[default]
     Linear,
@@ -46,6 +48,16 @@ pub struct DynamicAnimationTarget {
     // SAFETY: The caller must ensure that the type of data being passed into BOTH parameters
     //          is the same type that created this animation node.
     interpolate: unsafe fn(*const (), Option<*const ()>, NodeMut, ResourceRestrictedWorld, f32),
+    // SAFETY: The caller must ensure that the type of data being passed into BOTH parameters
+    //          is the same type that created this animation node.
+    sample: unsafe fn(*const (), Option<*const ()>, f32) -> Option<*mut ()>,
+    // SAFETY: The caller must ensure that the data provided to this function via pointer
+    //          is the same type as what was used to create the function
+    clone: Option<unsafe fn(*const ()) -> *mut ()>,
+    // SAFETY: The caller must ensure that the data provided to this function via pointer
+    //          is the same type as what was used to create the function, and that the data is
+    //          not used again afterwards
+    drop: unsafe fn(*mut ()),
 }
 
 unsafe impl Send for DynamicAnimationTarget {}
@@ -59,7 +71,7 @@ impl DynamicAnimationTarget {
                 type_path: T::short_type_path(),
                 type_id: TypeId::of::<T>(),
             },
-            data: (Box::leak(Box::new(data)) as *mut T).cast::<()>(),
+            data: Box::into_raw(Box::new(data)).cast::<()>(),
             // We cannot create an unsafe closure, but this is good enough for our purposes since
             // we are coallescing it into an unsafe function pointer
             interpolate: |current, prev, node, world, progress| unsafe {
@@ -67,9 +79,28 @@ impl DynamicAnimationTarget {
                 let prev = prev.map(|prev| &*prev.cast::<T>());
                 current.interpolate(prev, node, world, progress);
             },
+            sample: |current, prev, progress| unsafe {
+                let current = &*current.cast::<T>();
+                let prev = prev.map(|prev| &*prev.cast::<T>());
+                current
+                    .sample(prev, progress)
+                    .map(|sample| Box::into_raw(Box::new(sample)).cast::<()>())
+            },
+            clone: None,
+            drop: |data| unsafe { drop(Box::from_raw(data.cast::<T>())) },
         }
     }
 
+    /// Creates a target that can be copied with [`try_clone`](Self::try_clone)
+    pub(crate) fn new_cloneable<T: LayoutAnimationTarget + Clone>(data: T) -> Self {
+        let mut target = Self::new(data);
+        target.clone = Some(|data| unsafe {
+            let data = &*data.cast::<T>();
+            Box::into_raw(Box::new(data.clone())).cast()
+        });
+        target
+    }
+
     pub fn name(&self) -> &str {
         self.type_info.name.as_ref()
     }
@@ -86,6 +117,33 @@ impl DynamicAnimationTarget {
         self.type_info.type_id == TypeId::of::<T>()
     }
 
+    /// Gets a reference to the target's data, if it is of type `T`
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        // SAFETY: The type ids match, so the data was created from a `T`
+        self.is_type::<T>().then(|| unsafe { &*self.data.cast::<T>() })
+    }
+
+    /// Whether the target's type was registered as cloneable, see
+    /// [`LayoutRegistry::register_cloneable_animation`](crate::LayoutRegistry::register_cloneable_animation)
+    pub fn is_cloneable(&self) -> bool {
+        self.clone.is_some()
+    }
+
+    /// Copies this target, if its type was registered as cloneable
+    pub fn try_clone(&self) -> Option<Self> {
+        let clone = self.clone?;
+        Some(Self {
+            type_info: self.type_info,
+            // SAFETY: we are providing the owned pointer that we created on type construction, it
+            // is going to be the same type
+            data: unsafe { clone(self.data) },
+            interpolate: self.interpolate,
+            sample: self.sample,
+            clone: self.clone,
+            drop: self.drop,
+        })
+    }
+
     pub fn interpolate_from_start(&self, node: NodeMut, world: ResourceRestrictedWorld, progress: f32) {
         // SAFETY: we are providing the owned pointer that we created ont ype construction, it is
         // going to be the same type
@@ -115,6 +173,38 @@ impl DynamicAnimationTarget {
             (self.interpolate)(self.data, Some(previous.data), node, world, progress);
         }
     }
+
+    /// Computes the target that interpolating from `previous` would apply, if the target type
+    /// supports it, see [`LayoutAnimationTarget::sample`]
+    pub fn sample(&self, previous: Option<&DynamicAnimationTarget>, progress: f32) -> Option<Self> {
+        if let Some(previous) = previous {
+            assert_eq!(
+                self.type_info.type_id, previous.type_info.type_id,
+                "Attempting to sample incorrect type. Expected type {}, got type {}",
+                self.type_info.type_path, previous.type_info.type_path
+            );
+        }
+
+        let previous = previous.map(|previous| previous.data.cast_const());
+        // SAFETY: we have ensured that both targets are the type that created this target
+        let data = unsafe { (self.sample)(self.data, previous, progress)? };
+        Some(Self {
+            type_info: self.type_info,
+            data,
+            interpolate: self.interpolate,
+            sample: self.sample,
+            clone: self.clone,
+            drop: self.drop,
+        })
+    }
+}
+
+impl Drop for DynamicAnimationTarget {
+    fn drop(&mut self) {
+        // SAFETY: we own the data that we created on type construction, and nothing can use it
+        // once we are dropped
+        unsafe { (self.drop)(self.data) }
+    }
 }
 
 pub struct RawKeyframe {
@@ -140,6 +230,11 @@ pub struct Keyframes {
 }
 
 impl Keyframes {
+    /// The keyframes of this node, split into one channel per target type
+    pub fn channels(&self) -> &[KeyframeChannel] {
+        &self.channels
+    }
+
     /// Flattens a list of keyframes into individual channels based off of their type id
     ///
     /// This can be used to more efficiently animate each target during the animation systems
@@ -188,16 +283,51 @@ impl Keyframes {
     }
 }
 
+/// An animation as it is read from a layout file, before its keyframes are flattened
+#[derive(Default)]
+pub(crate) struct RawLayoutAnimation {
+    pub(crate) loop_mode: AnimationLoopMode,
+    pub(crate) priority: i32,
+    pub(crate) markers: Vec<AnimationMarker>,
+    pub(crate) nodes: HashMap<String, Vec<RawKeyframe>>,
+}
+
 #[derive(Default)]
-pub(crate) struct RawLayoutAnimations(
-    pub(crate) HashMap<String, HashMap<String, Vec<RawKeyframe>>>,
-);
+pub(crate) struct RawLayoutAnimations(pub(crate) HashMap<String, RawLayoutAnimation>);
 
 /// An asset type for a layout animation
 ///
-/// Layout animations are loaded as labeled assets on an animation.
+/// Layout animations are loaded as labeled assets on an animation. This dereferences to the
+/// keyframes of every node that the animation targets, keyed by their path.
 #[derive(Asset, Deref, DerefMut, TypePath)]
-pub struct LayoutAnimation(pub(crate) HashMap<Utf8PathBuf, Keyframes>);
+pub struct LayoutAnimation {
+    #[deref]
+    pub(crate) nodes: HashMap<Utf8PathBuf, Keyframes>,
+    pub(crate) loop_mode: AnimationLoopMode,
+    pub(crate) priority: i32,
+    /// Sorted by their timestamp
+    pub(crate) markers: Vec<AnimationMarker>,
+}
+
+impl LayoutAnimation {
+    /// How the animation repeats when it is played without choosing a loop mode
+    pub fn loop_mode(&self) -> AnimationLoopMode {
+        self.loop_mode
+    }
+
+    /// The priority of the animation when it is played without choosing one
+    ///
+    /// Animations with a higher priority are applied over the ones with a lower priority when
+    /// they animate the same target on the same node.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// The markers of the animation, in the order of their timestamps
+    pub fn markers(&self) -> &[AnimationMarker] {
+        &self.markers
+    }
+}
 
 pub struct ResourceRestrictedWorld<'w>(UnsafeWorldCell<'w>);
 
@@ -229,13 +359,465 @@ pub trait LayoutAnimationTarget: TypePath + Send + Sync + 'static {
     const NAME: &'static str;
 
     fn interpolate(&self, previous: Option<&Self>, node: NodeMut, world: ResourceRestrictedWorld<'_>, progress: f32);
+
+    /// Computes the target that [`interpolate`](Self::interpolate) would apply, without applying
+    /// it
+    ///
+    /// This is what lets an animation crossfade into another one. Targets that return [`None`],
+    /// which is the default, switch over to the new animation at once instead.
+    fn sample(&self, previous: Option<&Self>, progress: f32) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = (previous, progress);
+        None
+    }
+}
+
+/// What an animation does once it reaches the end of its keyframes (or the start, when it is
+/// playing in reverse)
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+pub enum AnimationLoopMode {
+    /// Stops the animation
+    #[default]
+    Once,
+    /// Starts the animation over from the other end
+    Loop,
+    /// Plays the animation back in the other direction
+    PingPong,
+    /// Starts the animation over until it has been played this many times, then stops it
+    Repeat(u32),
+}
+
+/// A named point in an animation, which sends a [`LayoutAnimationEvent`] whenever playback
+/// crosses it
+///
+/// Markers are declared in the `events` list of an animation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AnimationMarker {
+    pub timestamp_ms: usize,
+    pub name: String,
+}
+
+/// Sent by the animation system when something happens to an animation that is playing
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct LayoutAnimationEvent {
+    /// The layout node that is playing the animation
+    pub entity: Entity,
+    /// The name of the animation
+    pub animation: String,
+    pub kind: LayoutAnimationEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutAnimationEventKind {
+    /// The animation was updated for the first time since it was played
+    Started,
+    /// The animation reached its end and stopped
+    Finished,
+    /// The animation reached its start while playing in reverse and stopped
+    ReversedToStart,
+    /// The animation reached either of its ends and kept playing, see [`AnimationLoopMode`]
+    Looped,
+    /// Playback crossed the marker with this name, in either direction
+    Marker(String),
+    /// The animation was still playing or paused when its layout node was despawned, or when a
+    /// hot reload removed the animation (or its node) from the layout
+    Interrupted,
+}
+
+/// Sends [`LayoutAnimationEventKind::Interrupted`] for each of the `animations` of the layout
+/// node `entity`
+pub(crate) fn send_interrupted(
+    world: &mut World,
+    entity: Entity,
+    animations: impl IntoIterator<Item = String>,
+) {
+    let events: Vec<LayoutAnimationEvent> = animations
+        .into_iter()
+        .map(|animation| LayoutAnimationEvent {
+            entity,
+            animation,
+            kind: LayoutAnimationEventKind::Interrupted,
+        })
+        .collect();
+
+    if !events.is_empty() {
+        world.send_event_batch(events);
+    }
+}
+
+/// The time that drives layout animations
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AnimationClock {
+    /// [`Time<Virtual>`], which stops while the game is paused
+    #[default]
+    Virtual,
+
+    /// [`Time<Real>`], which keeps going while the game is paused
+    Real,
+
+    /// A [`Time<T>`] resource that the app provides, see [`AnimationClock::custom`]
+    Custom(CustomClock),
+
+    /// Only advances when stepped with [`LayoutAnimationPlaybackState::step`]
+    ///
+    /// This is useful to drive animations deterministically in tests
+    Manual,
+}
+
+impl AnimationClock {
+    /// Drives animations with the [`Time<T>`] resource
+    ///
+    /// Animations don't advance while the resource is missing.
+    pub fn custom<T: Default + Send + Sync + 'static>() -> Self {
+        /// SAFETY: The caller has to make sure that nothing is mutably accessing `Time<T>`
+        unsafe fn delta<T: Default + Send + Sync + 'static>(world: UnsafeWorldCell) -> Duration {
+            world
+                .get_resource::<Time<T>>()
+                .map_or(Duration::ZERO, Time::delta)
+        }
+
+        Self::Custom(CustomClock {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            delta: delta::<T>,
+        })
+    }
+
+    /// Gets how much time passed since the last update on this clock
+    ///
+    /// SAFETY: The caller has to make sure that nothing is mutably accessing the time resources
+    unsafe fn delta(self, world: UnsafeWorldCell, manual_step: Duration) -> Duration {
+        match self {
+            Self::Virtual => world
+                .get_resource::<Time<Virtual>>()
+                .map_or(Duration::ZERO, Time::delta),
+            Self::Real => world
+                .get_resource::<Time<Real>>()
+                .map_or(Duration::ZERO, Time::delta),
+            Self::Custom(clock) => (clock.delta)(world),
+            Self::Manual => manual_step,
+        }
+    }
+}
+
+/// A [`Time<T>`] resource used as an [`AnimationClock`]
+#[derive(Copy, Clone)]
+pub struct CustomClock {
+    type_id: TypeId,
+    type_name: &'static str,
+    delta: unsafe fn(UnsafeWorldCell) -> Duration,
+}
+
+impl fmt::Debug for CustomClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Time<{}>", self.type_name)
+    }
+}
+
+impl PartialEq for CustomClock {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id
+    }
+}
+
+impl Eq for CustomClock {}
+
+impl Hash for CustomClock {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.type_id.hash(state);
+    }
+}
+
+/// Options for playing a single animation, see [`LayoutAnimationPlaybackState::play_animation_with`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnimationPlayOptions {
+    /// How the animation repeats
+    ///
+    /// If [`None`], the loop mode that the animation declares in its layout is used
+    pub loop_mode: Option<AnimationLoopMode>,
+
+    /// How fast the animation plays, where `1.0` is its normal speed
+    pub speed: f32,
+
+    /// The time that drives the animation
+    ///
+    /// If [`None`], the clock of the layout node that plays the animation is used
+    pub clock: Option<AnimationClock>,
+
+    /// Where the animation is layered when it animates the same target as another animation
+    ///
+    /// If [`None`], the priority that the animation declares in its layout is used
+    pub priority: Option<i32>,
+
+    /// How long the animation takes to fade in over the animations that it is layered over
+    ///
+    /// Other animations with the same priority that animate the same targets are stopped once
+    /// the crossfade is over.
+    pub crossfade: Option<Duration>,
+}
+
+impl Default for AnimationPlayOptions {
+    fn default() -> Self {
+        Self {
+            loop_mode: None,
+            speed: 1.0,
+            clock: None,
+            priority: None,
+            crossfade: None,
+        }
+    }
+}
+
+impl AnimationPlayOptions {
+    pub fn with_loop_mode(mut self, loop_mode: AnimationLoopMode) -> Self {
+        self.loop_mode = Some(loop_mode);
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: AnimationClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn with_crossfade(mut self, duration: Duration) -> Self {
+        self.crossfade = Some(duration);
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ActivePlayback {
+    progress: Duration,
+    /// Moves the playback to this fraction of the animation on its next update, for when the
+    /// length of the animation is not known yet
+    seek_fraction: Option<f32>,
+    is_reverse: bool,
+    loop_mode: Option<AnimationLoopMode>,
+    speed: f32,
+    clock: Option<AnimationClock>,
+    priority: Option<i32>,
+    /// When the animation was played relative to the other animations of its layout node, which
+    /// orders animations that have the same priority
+    order: u64,
+    /// How far the animation is into fading in over the animations layered below it
+    crossfade: Option<Crossfade>,
+    /// The order of the animation whose crossfade replaces this one, this animation is stopped
+    /// once that crossfade is over (or keeps playing if that animation is stopped first)
+    replaced_by: Option<u64>,
+    /// Whether the animation reached its end while it was being replaced, in which case it keeps
+    /// applying its last keyframes until it is stopped
+    held: bool,
+    /// How many times the animation has reached either of its ends
+    loops: u32,
+    /// Whether the animation has been updated since it was played
+    started: bool,
+    /// Whether the animation has to be applied to its nodes even if it is paused, because it was
+    /// moved to another point
+    needs_apply: bool,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Crossfade {
+    elapsed: Duration,
+    duration: Duration,
+}
+
+impl Crossfade {
+    /// How much of the animation is applied over the animations below it
+    fn weight(&self) -> f32 {
+        if self.duration.is_zero() {
+            1.0
+        } else {
+            (self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+        }
+    }
+}
+
+/// A stretch of an animation that playback went over during a single update
+struct PlaybackSpan {
+    from: Duration,
+    to: Duration,
+    /// Whether markers at `from` were not already crossed by the previous update
+    includes_from: bool,
+}
+
+impl PlaybackSpan {
+    fn is_reverse(&self) -> bool {
+        self.to < self.from
+    }
+
+    fn contains(&self, timestamp_ms: usize) -> bool {
+        let timestamp = Duration::from_millis(timestamp_ms as u64);
+        let (start, end) = if self.is_reverse() {
+            (self.to, self.from)
+        } else {
+            (self.from, self.to)
+        };
+
+        (start..=end).contains(&timestamp) && (timestamp != self.from || self.includes_from)
+    }
+}
+
+/// What happened to an animation during a single update
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PlaybackStep {
+    Continued,
+    Looped,
+    Finished,
+}
+
+impl ActivePlayback {
+    fn new(
+        seek_fraction: f32,
+        is_reverse: bool,
+        options: AnimationPlayOptions,
+        order: u64,
+    ) -> Self {
+        Self {
+            progress: Duration::ZERO,
+            seek_fraction: Some(seek_fraction),
+            is_reverse,
+            loop_mode: options.loop_mode,
+            speed: sanitize_speed(options.speed),
+            clock: options.clock,
+            priority: options.priority,
+            order,
+            crossfade: options.crossfade.map(|duration| Crossfade {
+                elapsed: Duration::ZERO,
+                duration,
+            }),
+            replaced_by: None,
+            held: false,
+            loops: 0,
+            started: false,
+            needs_apply: false,
+        }
+    }
+
+    fn seek(&mut self, progress: Duration) {
+        self.progress = progress;
+        self.seek_fraction = None;
+        self.needs_apply = true;
+    }
+
+    fn seek_fraction(&mut self, fraction: f32) {
+        let fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, 1.0)
+        };
+        self.seek_fraction = Some(fraction);
+        self.needs_apply = true;
+    }
+
+    /// Moves the playback `delta` further into an animation that is `length` long, and records
+    /// the parts of the animation that it went over in `spans`
+    ///
+    /// When `delta` is longer than the animation, every loop that it went over is recorded as a
+    /// span of its own. Going over more than [`MAX_LOOPS_PER_UPDATE`] loops drops the rest of
+    /// `delta`.
+    fn advance(
+        &mut self,
+        delta: Duration,
+        length: Duration,
+        loop_mode: AnimationLoopMode,
+        spans: &mut Vec<PlaybackSpan>,
+    ) -> PlaybackStep {
+        let mut remaining = delta.min(length.saturating_mul(MAX_LOOPS_PER_UPDATE));
+        let mut includes_from = !std::mem::replace(&mut self.started, true);
+        let mut step = PlaybackStep::Continued;
+        loop {
+            let from = self.progress;
+            let overshoot = if self.is_reverse {
+                let overshoot = remaining.saturating_sub(self.progress);
+                self.progress = self.progress.saturating_sub(remaining);
+                overshoot
+            } else {
+                self.progress = self.progress.saturating_add(remaining);
+                let overshoot = self.progress.saturating_sub(length);
+                self.progress = self.progress.min(length);
+                overshoot
+            };
+
+            spans.push(PlaybackSpan {
+                from,
+                to: self.progress,
+                includes_from,
+            });
+
+            let reached_end = if self.is_reverse {
+                self.progress.is_zero()
+            } else {
+                self.progress == length
+            };
+            if !reached_end {
+                return step;
+            }
+
+            self.loops = self.loops.saturating_add(1);
+            let repeats = match loop_mode {
+                AnimationLoopMode::Once => false,
+                AnimationLoopMode::Loop | AnimationLoopMode::PingPong => true,
+                AnimationLoopMode::Repeat(count) => self.loops < count,
+            };
+
+            // Animations without any length would loop forever without ever getting anywhere
+            if !repeats || length.is_zero() {
+                return PlaybackStep::Finished;
+            }
+
+            // Ping-pong turns around at the end that was reached, anything else starts over from
+            // the other end
+            let is_ping_pong = loop_mode == AnimationLoopMode::PingPong;
+            if is_ping_pong {
+                self.is_reverse = !self.is_reverse;
+            }
+
+            self.progress = if self.is_reverse {
+                length
+            } else {
+                Duration::ZERO
+            };
+
+            // Time that went past the end carries over into the next loop, which may go past
+            // the end again
+            remaining = overshoot;
+            includes_from = !is_ping_pong;
+            step = PlaybackStep::Looped;
+        }
+    }
+}
+
+/// How many loops of an animation a single update goes over at most, so that animations played
+/// at an absurd speed don't stall the app
+const MAX_LOOPS_PER_UPDATE: u32 = 1000;
+
+/// Keeps playback speeds non-negative and finite, turning NaN into `0.0`
+fn sanitize_speed(speed: f32) -> f32 {
+    if speed.is_nan() {
+        0.0
+    } else {
+        speed.clamp(0.0, f32::MAX)
+    }
 }
 
 #[derive(Debug)]
 enum InternalPlaybackState {
     Stopped,
-    Paused { progress: usize, is_reverse: bool },
-    Playing { progress: usize, is_reverse: bool },
+    Paused(ActivePlayback),
+    Playing(ActivePlayback),
 }
 
 pub enum PlaybackState {
@@ -248,14 +830,24 @@ impl PlaybackState {
     fn from_internal(internal: &InternalPlaybackState) -> Self {
         match internal {
             InternalPlaybackState::Stopped => Self::Stopped,
-            InternalPlaybackState::Paused { .. } => Self::Paused,
-            InternalPlaybackState::Playing { .. } => Self::Playing,
+            InternalPlaybackState::Paused(_) => Self::Paused,
+            InternalPlaybackState::Playing(_) => Self::Playing,
         }
     }
 }
 
 #[derive(Component, Default)]
-pub struct LayoutAnimationPlaybackState(HashMap<String, InternalPlaybackState>);
+pub struct LayoutAnimationPlaybackState {
+    animations: HashMap<String, InternalPlaybackState>,
+    clock: AnimationClock,
+    /// Time that animations on the [`AnimationClock::Manual`] clock advance by on the next update
+    manual_step: Duration,
+    /// How many animations have been played, which is the order of the next one
+    play_count: u64,
+    /// Where animations that finished left the channels of their nodes, which crossfades fade
+    /// from until another animation applies those channels on its own
+    resting: HashMap<Utf8PathBuf, HashMap<TypeId, DynamicAnimationTarget>>,
+}
 
 impl LayoutAnimationPlaybackState {
     pub(crate) fn new(
@@ -271,25 +863,83 @@ impl LayoutAnimationPlaybackState {
             map.insert(label.to_string(), InternalPlaybackState::Stopped);
         }
 
-        Self(map)
+        Self {
+            animations: map,
+            ..Default::default()
+        }
+    }
+
+    /// Carries over the state of every animation from `previous` that still exists in this
+    /// playback state
+    ///
+    /// This is used to keep animations going when a layout is reloaded. Returns the names of the
+    /// animations that were playing or paused in `previous`, but no longer exist.
+    pub(crate) fn restore_from(&mut self, previous: Self) -> Vec<String> {
+        self.clock = previous.clock;
+        self.manual_step = previous.manual_step;
+        self.play_count = previous.play_count;
+
+        let mut removed = vec![];
+        for (name, state) in previous.animations {
+            match self.animations.get_mut(&name) {
+                Some(current) => *current = state,
+                None if !matches!(state, InternalPlaybackState::Stopped) => removed.push(name),
+                None => {}
+            }
+        }
+
+        removed
+    }
+
+    /// The names of the animations that are playing or paused
+    pub(crate) fn active_animations(&self) -> impl Iterator<Item = &str> + '_ {
+        self.animations
+            .iter()
+            .filter(|(_, state)| !matches!(state, InternalPlaybackState::Stopped))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// The time that drives the animations of this layout node, unless an animation was
+    /// played with its own clock
+    pub fn clock(&self) -> AnimationClock {
+        self.clock
+    }
+
+    pub fn set_clock(&mut self, clock: AnimationClock) {
+        self.clock = clock;
+    }
+
+    /// Advances animations on the [`AnimationClock::Manual`] clock by `delta` on the next update
+    ///
+    /// Steps add up until the animations are updated.
+    pub fn step(&mut self, delta: Duration) {
+        self.manual_step = self.manual_step.saturating_add(delta);
     }
 
     pub fn is_playing_any(&self) -> bool {
-        self.0
+        self.animations
             .values()
-            .any(|state| matches!(state, InternalPlaybackState::Playing { .. }))
+            .any(|state| matches!(state, InternalPlaybackState::Playing(_)))
     }
 
     pub fn playback_state(&self, name: &str) -> Option<PlaybackState> {
-        self.0.get(name).map(PlaybackState::from_internal)
+        self.animations.get(name).map(PlaybackState::from_internal)
     }
 
     pub fn play_animation(&mut self, name: &str) -> bool {
-        if let Some(state) = self.0.get_mut(name) {
-            *state = InternalPlaybackState::Playing {
-                progress: 0,
-                is_reverse: false,
-            };
+        self.play_animation_with(name, AnimationPlayOptions::default())
+    }
+
+    /// Plays the animation from the start, with options that only apply to this playthrough
+    pub fn play_animation_with(&mut self, name: &str, options: AnimationPlayOptions) -> bool {
+        if let Some(state) = self.animations.get_mut(name) {
+            *state = InternalPlaybackState::Playing(ActivePlayback::new(
+                0.0,
+                false,
+                options,
+                self.play_count,
+            ));
+            self.play_count += 1;
             true
         } else {
             false
@@ -297,7 +947,7 @@ impl LayoutAnimationPlaybackState {
     }
 
     pub fn stop_animation(&mut self, name: &str) -> bool {
-        if let Some(state) = self.0.get_mut(name) {
+        if let Some(state) = self.animations.get_mut(name) {
             *state = InternalPlaybackState::Stopped;
             true
         } else {
@@ -306,16 +956,10 @@ impl LayoutAnimationPlaybackState {
     }
 
     pub fn pause_animation(&mut self, name: &str) -> bool {
-        if let Some(state) = self.0.get_mut(name) {
+        if let Some(state) = self.animations.get_mut(name) {
             match state {
-                InternalPlaybackState::Playing {
-                    progress,
-                    is_reverse,
-                } => {
-                    *state = InternalPlaybackState::Paused {
-                        progress: *progress,
-                        is_reverse: *is_reverse,
-                    }
+                InternalPlaybackState::Playing(playback) => {
+                    *state = InternalPlaybackState::Paused(*playback);
                 }
                 _ => {}
             }
@@ -327,16 +971,10 @@ impl LayoutAnimationPlaybackState {
     }
 
     pub fn pause_all_animations(&mut self) {
-        for state in self.0.values_mut() {
+        for state in self.animations.values_mut() {
             match state {
-                InternalPlaybackState::Playing {
-                    progress,
-                    is_reverse,
-                } => {
-                    *state = InternalPlaybackState::Paused {
-                        progress: *progress,
-                        is_reverse: *is_reverse,
-                    }
+                InternalPlaybackState::Playing(playback) => {
+                    *state = InternalPlaybackState::Paused(*playback);
                 }
                 _ => {}
             }
@@ -344,16 +982,10 @@ impl LayoutAnimationPlaybackState {
     }
 
     pub fn resume_animation(&mut self, name: &str) -> bool {
-        if let Some(state) = self.0.get_mut(name) {
+        if let Some(state) = self.animations.get_mut(name) {
             match state {
-                InternalPlaybackState::Paused {
-                    progress,
-                    is_reverse,
-                } => {
-                    *state = InternalPlaybackState::Playing {
-                        progress: *progress,
-                        is_reverse: *is_reverse,
-                    }
+                InternalPlaybackState::Paused(playback) => {
+                    *state = InternalPlaybackState::Playing(*playback);
                 }
                 _ => {}
             }
@@ -365,16 +997,10 @@ impl LayoutAnimationPlaybackState {
     }
 
     pub fn resume_all_animations(&mut self) {
-        for state in self.0.values_mut() {
+        for state in self.animations.values_mut() {
             match state {
-                InternalPlaybackState::Paused {
-                    progress,
-                    is_reverse,
-                } => {
-                    *state = InternalPlaybackState::Playing {
-                        progress: *progress,
-                        is_reverse: *is_reverse,
-                    }
+                InternalPlaybackState::Paused(playback) => {
+                    *state = InternalPlaybackState::Playing(*playback);
                 }
                 _ => {}
             }
@@ -382,11 +1008,11 @@ impl LayoutAnimationPlaybackState {
     }
 
     pub fn reverse_animation(&mut self, name: &str) -> bool {
-        if let Some(state) = self.0.get_mut(name) {
+        if let Some(state) = self.animations.get_mut(name) {
             match state {
-                InternalPlaybackState::Paused { is_reverse, .. }
-                | InternalPlaybackState::Playing { is_reverse, .. } => {
-                    *is_reverse = true;
+                InternalPlaybackState::Paused(playback)
+                | InternalPlaybackState::Playing(playback) => {
+                    playback.is_reverse = true;
                 }
                 _ => {}
             }
@@ -397,17 +1023,20 @@ impl LayoutAnimationPlaybackState {
     }
 
     pub fn play_or_reverse_animation(&mut self, name: &str) -> bool {
-        if let Some(state) = self.0.get_mut(name) {
+        if let Some(state) = self.animations.get_mut(name) {
             match state {
-                InternalPlaybackState::Paused { is_reverse, .. }
-                | InternalPlaybackState::Playing { is_reverse, .. } => {
-                    *is_reverse = !*is_reverse;
+                InternalPlaybackState::Paused(playback)
+                | InternalPlaybackState::Playing(playback) => {
+                    playback.is_reverse = !playback.is_reverse;
                 }
                 InternalPlaybackState::Stopped => {
-                    *state = InternalPlaybackState::Playing {
-                        progress: usize::MAX,
-                        is_reverse: true,
-                    };
+                    *state = InternalPlaybackState::Playing(ActivePlayback::new(
+                        1.0,
+                        true,
+                        AnimationPlayOptions::default(),
+                        self.play_count,
+                    ));
+                    self.play_count += 1;
                 }
             }
             true
@@ -415,6 +1044,67 @@ impl LayoutAnimationPlaybackState {
             false
         }
     }
+
+    /// Moves the animation to `ms` milliseconds into it
+    ///
+    /// A stopped animation is paused at that point, so that it can be resumed from there. Seeking
+    /// past the end of the animation moves it to its end.
+    pub fn seek(&mut self, name: &str, ms: f32) -> bool {
+        let progress = Duration::try_from_secs_f32(ms.max(0.0) / 1000.0).unwrap_or(Duration::MAX);
+        self.with_active_playback(name, |playback| playback.seek(progress))
+    }
+
+    /// Moves the animation to a fraction of its length, where `0.0` is its start and `1.0` its
+    /// end
+    ///
+    /// A stopped animation is paused at that point, so that it can be resumed from there.
+    pub fn set_progress(&mut self, name: &str, fraction: f32) -> bool {
+        self.with_active_playback(name, |playback| playback.seek_fraction(fraction))
+    }
+
+    /// Changes how fast the animation plays, where `1.0` is its normal speed
+    ///
+    /// This lasts until the animation is played again, so stopped animations are left as they
+    /// are. Use [`AnimationPlayOptions::with_speed`] to play an animation at another speed.
+    pub fn set_speed(&mut self, name: &str, speed: f32) -> bool {
+        let Some(state) = self.animations.get_mut(name) else {
+            return false;
+        };
+
+        if let InternalPlaybackState::Paused(playback) | InternalPlaybackState::Playing(playback) =
+            state
+        {
+            playback.speed = sanitize_speed(speed);
+        }
+
+        true
+    }
+
+    /// Runs `f` on the playback of the animation, pausing the animation at its start first if it
+    /// is stopped
+    fn with_active_playback(&mut self, name: &str, f: impl FnOnce(&mut ActivePlayback)) -> bool {
+        let Some(state) = self.animations.get_mut(name) else {
+            return false;
+        };
+
+        if let InternalPlaybackState::Stopped = state {
+            *state = InternalPlaybackState::Paused(ActivePlayback::new(
+                0.0,
+                false,
+                AnimationPlayOptions::default(),
+                self.play_count,
+            ));
+            self.play_count += 1;
+        }
+
+        if let InternalPlaybackState::Paused(playback) | InternalPlaybackState::Playing(playback) =
+            state
+        {
+            f(playback);
+        }
+
+        true
+    }
 }
 
 type IsLayoutNodeFilter = (With<LayoutAnimationPlaybackState>, With<LayoutHandle>);
@@ -463,11 +1153,158 @@ fn try_get_descendant_id(world: &World, entity: EntityRef<'_>, id: &Utf8Path) ->
     }
 }
 
+/// An animation of a layout node that is being played, in the order in which animations are
+/// applied
+struct AnimationLayer<'a> {
+    name: String,
+    animation: &'a LayoutAnimation,
+    priority: i32,
+    order: u64,
+    /// Whether the animation starts to crossfade over the animations below it on this update
+    starts_crossfade: bool,
+}
+
+impl AnimationLayer<'_> {
+    /// Whether both animations animate the same target on the same node
+    fn shares_target(&self, other: &Self) -> bool {
+        self.animation.iter().any(|(node_id, keyframes)| {
+            other.animation.get(node_id).is_some_and(|other| {
+                keyframes
+                    .channels
+                    .iter()
+                    .any(|channel| other.channels.iter().any(|c| c.type_id == channel.type_id))
+            })
+        })
+    }
+}
+
+/// The keyframes of a single channel at some point into its animation
+struct ChannelPose<'a> {
+    target: &'a DynamicAnimationTarget,
+    previous: Option<&'a DynamicAnimationTarget>,
+    progress: f32,
+}
+
+impl<'a> ChannelPose<'a> {
+    /// Finds the keyframes of `channel` at `progress` milliseconds into the animation
+    fn new(channel: &'a KeyframeChannel, progress: f32) -> Self {
+        let index = if let Some(index) = channel
+            .keyframes
+            .iter()
+            .position(|kf| progress < kf.timestamp_ms as f32)
+        {
+            index
+        } else {
+            channel.keyframes.len() - 1 // we can safely subtract 1 since channels are never
+                                        // created without keyframes
+        };
+
+        let kf = &channel.keyframes[index];
+        // we are at the start of the animation, no prev keyframe to interpolate frame
+        if index == 0 {
+            let progress = if kf.timestamp_ms == 0 {
+                1.0
+            } else {
+                progress / kf.timestamp_ms as f32
+            };
+
+            Self {
+                target: &kf.target,
+                previous: None,
+                progress: kf.time_scale.map(progress.clamp(0.0, 1.0)),
+            }
+        } else {
+            let prev_kf = &channel.keyframes[index - 1];
+            // doing a non saturating sub here is safe since we sort the keyframe list upon
+            // construction
+            let delta_kf = kf.timestamp_ms - prev_kf.timestamp_ms;
+            let progress = if delta_kf == 0 {
+                1.0
+            } else {
+                (progress - prev_kf.timestamp_ms as f32) / delta_kf as f32
+            };
+
+            Self {
+                target: &kf.target,
+                previous: Some(&prev_kf.target),
+                progress: kf.time_scale.map(progress.clamp(0.0, 1.0)),
+            }
+        }
+    }
+
+    fn sample(&self) -> Option<DynamicAnimationTarget> {
+        self.target.sample(self.previous, self.progress)
+    }
+
+    fn apply(&self, node: NodeMut, world: ResourceRestrictedWorld) {
+        log::trace!("Animating target {}", self.target.name());
+        match self.previous {
+            Some(previous) => {
+                self.target
+                    .interpolate_with_previous(previous, node, world, self.progress)
+            }
+            None => self.target.interpolate_from_start(node, world, self.progress),
+        }
+    }
+}
+
+/// What a single channel applies once the animations layered on it are blended together
+enum BlendedPose<'a> {
+    Pose(ChannelPose<'a>),
+    Sampled(DynamicAnimationTarget),
+}
+
+impl<'a> BlendedPose<'a> {
+    /// Layers `pose` over this one, where a `weight` of `1.0` only keeps `pose`
+    ///
+    /// Targets that can't be sampled switch over to `pose` at once.
+    fn blend(self, pose: ChannelPose<'a>, weight: f32) -> Self {
+        if weight >= 1.0 {
+            return Self::Pose(pose);
+        }
+
+        match self {
+            Self::Pose(below) => match below.sample() {
+                Some(below) => Self::blend_over(&below, pose, weight),
+                None => Self::Pose(pose),
+            },
+            Self::Sampled(below) => Self::blend_over(&below, pose, weight),
+        }
+    }
+
+    /// Layers `pose` over a target that was already sampled, see [`blend`](Self::blend)
+    fn blend_over(below: &DynamicAnimationTarget, pose: ChannelPose<'a>, weight: f32) -> Self {
+        if weight >= 1.0 {
+            return Self::Pose(pose);
+        }
+
+        pose.sample()
+            .and_then(|sampled| sampled.sample(Some(below), weight))
+            .map_or(Self::Pose(pose), Self::Sampled)
+    }
+
+    /// The target that [`apply`](Self::apply) applies, if it can be sampled
+    fn into_target(self) -> Option<DynamicAnimationTarget> {
+        match self {
+            Self::Pose(pose) => pose.sample(),
+            Self::Sampled(target) => Some(target),
+        }
+    }
+
+    fn apply(&self, node: NodeMut, world: ResourceRestrictedWorld) {
+        match self {
+            Self::Pose(pose) => pose.apply(node, world),
+            Self::Sampled(target) => target.interpolate_from_start(node, world, 1.0),
+        }
+    }
+}
+
 pub(crate) fn update_animations(world: &mut World) {
-    let delta_ms = world.resource::<Time>().delta().as_millis();
     let asset_server = world.resource::<AssetServer>().clone();
 
-    world.resource_scope::<Assets<LayoutAnimation>, _>(move |world, animations| {
+    let events = world.resource_scope::<Assets<LayoutAnimation>, _>(move |world, animations| {
+        let mut events = vec![];
+        let mut spans = vec![];
         let mut query = world.query_filtered::<EntityMut, IsLayoutNodeFilter>();
 
         let world = world.as_unsafe_world_cell();
@@ -499,12 +1336,32 @@ pub(crate) fn update_animations(world: &mut World) {
                 },
             );
 
-            let mut changed = false;
-            for (name, state) in state.0.iter_mut() {
-                let InternalPlaybackState::Playing {
-                    progress,
-                    is_reverse,
-                } = state
+            // Animations are no longer replaced once the animation that replaced them is stopped
+            // (or played over) before its crossfade is over
+            let active: Vec<u64> = state
+                .animations
+                .values()
+                .filter_map(|state| match state {
+                    InternalPlaybackState::Playing(playback)
+                    | InternalPlaybackState::Paused(playback) => Some(playback.order),
+                    InternalPlaybackState::Stopped => None,
+                })
+                .collect();
+
+            for state in state.animations.values_mut() {
+                if let InternalPlaybackState::Playing(playback)
+                | InternalPlaybackState::Paused(playback) = state
+                {
+                    if playback.replaced_by.is_some_and(|order| !active.contains(&order)) {
+                        playback.replaced_by = None;
+                    }
+                }
+            }
+
+            let mut layers = vec![];
+            for (name, state) in state.animations.iter() {
+                let (InternalPlaybackState::Playing(playback)
+                | InternalPlaybackState::Paused(playback)) = state
                 else {
                     continue;
                 };
@@ -521,22 +1378,147 @@ pub(crate) fn update_animations(world: &mut World) {
                     continue;
                 };
 
+                layers.push(AnimationLayer {
+                    name: name.clone(),
+                    animation,
+                    priority: playback.priority.unwrap_or(animation.priority),
+                    order: playback.order,
+                    starts_crossfade: matches!(state, InternalPlaybackState::Playing(_))
+                        && playback.crossfade.is_some()
+                        && !playback.started,
+                });
+            }
+
+            // Animations are applied from the lowest priority to the highest, and in the order
+            // they were played when their priorities are the same, so that the last one wins
+            layers.sort_by(|a, b| {
+                (a.priority, a.order, &a.name).cmp(&(b.priority, b.order, &b.name))
+            });
+
+            // An animation that starts with a crossfade replaces the animations it fades over
+            for layer in layers.iter().filter(|layer| layer.starts_crossfade) {
+                for other in layers.iter() {
+                    if other.priority != layer.priority
+                        || other.order >= layer.order
+                        || !layer.shares_target(other)
+                    {
+                        continue;
+                    }
+
+                    if let Some(
+                        InternalPlaybackState::Playing(playback)
+                        | InternalPlaybackState::Paused(playback),
+                    ) = state.animations.get_mut(&other.name)
+                    {
+                        playback.replaced_by.get_or_insert(layer.order);
+                    }
+                }
+            }
+
+            let clock = state.clock;
+            let manual_step = std::mem::take(&mut state.manual_step);
+            let mut changed = false;
+            let mut poses = IndexMap::new();
+            let mut finished = vec![];
+            let resting = &mut state.resting;
+            for layer in layers.iter() {
+                let Some(state) = state.animations.get_mut(&layer.name) else {
+                    continue;
+                };
+
+                let (playback, is_playing) = match state {
+                    InternalPlaybackState::Playing(playback) => (playback, true),
+                    InternalPlaybackState::Paused(playback) if playback.needs_apply => {
+                        (playback, false)
+                    }
+                    _ => continue,
+                };
+
+                let animation = layer.animation;
                 changed |= true;
 
-                if *progress == usize::MAX {
-                    *progress = animation.values().map(|kf| kf.max_length).max().unwrap_or_default();
+                let length = animation.values().map(|kf| kf.max_length).max().unwrap_or_default();
+                let length = Duration::from_millis(length as u64);
+                if let Some(fraction) = playback.seek_fraction.take() {
+                    playback.progress = length.mul_f32(fraction);
                 }
+                playback.progress = playback.progress.min(length);
+                playback.needs_apply = false;
+
+                let send = |events: &mut Vec<LayoutAnimationEvent>, kind| {
+                    events.push(LayoutAnimationEvent {
+                        entity: entity.id(),
+                        animation: layer.name.clone(),
+                        kind,
+                    })
+                };
+
+                // Paused animations that were moved to another point are applied without
+                // advancing them, and so are animations that are held at their end
+                let step = if is_playing && !playback.held {
+                    if !playback.started {
+                        send(&mut events, LayoutAnimationEventKind::Started);
+                    }
+
+                    // SAFETY: Time resources are only read here, while the query only accesses
+                    // components
+                    let delta = unsafe {
+                        playback
+                            .clock
+                            .unwrap_or(clock)
+                            .delta(world, manual_step)
+                    };
+
+                    if let Some(crossfade) = playback.crossfade.as_mut() {
+                        crossfade.elapsed = crossfade.elapsed.saturating_add(delta);
+                    }
 
-                if *is_reverse {
-                    // Performing an "as" conversion here is fine, if your game takes over
-                    // usize::MAX milliseconds you probably have other concerns than your
-                    // layouts animating
-                    *progress = progress.saturating_sub(delta_ms as usize);
+                    // Speeds are finite, but can still be too fast to fit in a duration
+                    let delta = Duration::try_from_secs_f32(delta.as_secs_f32() * playback.speed)
+                        .unwrap_or(Duration::MAX);
+                    playback.advance(
+                        delta,
+                        length,
+                        playback.loop_mode.unwrap_or(animation.loop_mode),
+                        &mut spans,
+                    )
                 } else {
-                    *progress = progress.saturating_add(delta_ms as usize);
+                    PlaybackStep::Continued
+                };
+                let progress = playback.progress.as_secs_f32() * 1000.0;
+
+                for (index, span) in spans.drain(..).enumerate() {
+                    // Playback only goes over more than one span when the animation loops
+                    if index > 0 {
+                        send(&mut events, LayoutAnimationEventKind::Looped);
+                    }
+
+                    let mut crossed: Vec<_> = animation
+                        .markers
+                        .iter()
+                        .filter(|marker| span.contains(marker.timestamp_ms))
+                        .collect();
+                    if span.is_reverse() {
+                        crossed.reverse();
+                    }
+
+                    for marker in crossed {
+                        send(&mut events, LayoutAnimationEventKind::Marker(marker.name.clone()));
+                    }
                 }
 
-                let mut are_keyframes_finished = true;
+                match step {
+                    PlaybackStep::Continued | PlaybackStep::Looped => {}
+                    PlaybackStep::Finished if playback.is_reverse => {
+                        send(&mut events, LayoutAnimationEventKind::ReversedToStart)
+                    }
+                    PlaybackStep::Finished => send(&mut events, LayoutAnimationEventKind::Finished),
+                }
+
+                let weight = playback.crossfade.map_or(1.0, |crossfade| crossfade.weight());
+                if weight >= 1.0 {
+                    playback.crossfade = None;
+                }
 
                 for (node_id, keyframes) in animation.iter() {
                     if keyframes.channels.is_empty() {
@@ -544,70 +1526,101 @@ pub(crate) fn update_animations(world: &mut World) {
                         continue;
                     }
 
-                    let readonly = entity.as_readonly();
-                    // SAFETY: This is safe since we remove the only other active mutable reference
-                    // into the world by making it readonly (we will use it as mutable again later
-                    // but for all intents and purposes this is safe)
-                    let mut node = match try_get_descendant_id(unsafe { world.world() }, readonly, node_id) {
-                        DescendantId::None => continue, // We don't log anything because that's done in
-                                                        // the function
-                        // SAFETY: We are repurposing the EntityMut that we had earlier, it is
-                        // still the only exclusive reference
-                        DescendantId::This => unsafe { NodeMut::try_new(world, readonly.id()).unwrap() },
-                        // SAFETY: This is safe since we have confirmed that it is not the same
-                        // entity (therefore no double mutable reference) and we are not iterating
-                        // in parallel so we have exclusive access to this entity
-                        DescendantId::Other(id) => unsafe { NodeMut::try_new(world, id).unwrap() }
-                    };
-
                     for channel in keyframes.channels.iter() {
-                        let index = if let Some(index) = channel.keyframes.iter().position(|kf| {
-                            *progress < kf.timestamp_ms
-                        }) {
-                            are_keyframes_finished = false;
-                            index
-                        } else {
-                            channel.keyframes.len() - 1 // we can safely subtract 1
-                                                        // since we check if it is empty
-                                                        // above
+                        let key = (node_id, channel.type_id);
+                        let pose = ChannelPose::new(channel, progress);
+                        let blended = match poses.shift_remove(&key) {
+                            Some(below) => BlendedPose::blend(below, pose, weight),
+                            // Channels that no animation below applies fade from where the
+                            // animations that finished left them
+                            None => match resting
+                                .get(node_id)
+                                .and_then(|channels| channels.get(&channel.type_id))
+                            {
+                                Some(below) => BlendedPose::blend_over(below, pose, weight),
+                                None => BlendedPose::Pose(pose),
+                            },
                         };
+                        poses.insert(key, blended);
+                    }
+                }
 
-                        let kf = &channel.keyframes[index];
-                        log::trace!("Animating target {}", kf.target.name());
-                        // we are at the start of the animation, no prev keyframe
-                        // to interpolate frame
-                        if index == 0 {
-                            let progress = if kf.timestamp_ms == 0 {
-                                1.0
-                            } else {
-                                *progress as f32 / kf.timestamp_ms as f32
-                            };
-
-                            let progress = kf.time_scale.map(progress.clamp(0.0, 1.0));
-
-                            kf.target.interpolate_from_start(node.reborrow(), ResourceRestrictedWorld(world), progress);
-                        } else {
-                            let prev_kf = &channel.keyframes[index - 1];
-                            // doing a non saturating sub here is safe since we sort the 
-                            // keyframe list upon construction
-                            let delta_kf = kf.timestamp_ms - prev_kf.timestamp_ms; 
-                            let progress = if delta_kf == 0 {
-                                1.0
-                            } else {
-                                (*progress - prev_kf.timestamp_ms) as f32 / delta_kf as f32
-                            };
-
-                            let progress = kf.time_scale.map(progress.clamp(0.0, 1.0));
-
-                            kf.target.interpolate_with_previous(&prev_kf.target, node.reborrow(), ResourceRestrictedWorld(world), progress);
-                        }
+                if step == PlaybackStep::Finished || playback.held {
+                    // An animation that is being replaced keeps its last keyframes around until
+                    // the crossfade is over, so that there is something to fade from
+                    if playback.replaced_by.is_some() {
+                        playback.held = true;
+                    } else {
+                        *state = InternalPlaybackState::Stopped;
+                        finished.push(animation);
                     }
                 }
+            }
+
+            for ((node_id, type_id), pose) in poses {
+                let readonly = entity.as_readonly();
+                // SAFETY: This is safe since we remove the only other active mutable reference
+                // into the world by making it readonly (we will use it as mutable again later
+                // but for all intents and purposes this is safe)
+                let node = match try_get_descendant_id(unsafe { world.world() }, readonly, node_id) {
+                    DescendantId::None => continue, // We don't log anything because that's done in
+                                                    // the function
+                    // SAFETY: We are repurposing the EntityMut that we had earlier, it is
+                    // still the only exclusive reference
+                    DescendantId::This => unsafe { NodeMut::try_new(world, readonly.id()).unwrap() },
+                    // SAFETY: This is safe since we have confirmed that it is not the same
+                    // entity (therefore no double mutable reference) and we are not iterating
+                    // in parallel so we have exclusive access to this entity
+                    DescendantId::Other(id) => unsafe { NodeMut::try_new(world, id).unwrap() }
+                };
 
-                if are_keyframes_finished || (*is_reverse && *progress == 0) {
+                pose.apply(node, ResourceRestrictedWorld(world));
+
+                // Channels rest where the animations that finished left them, until an animation
+                // applies them on its own again
+                let is_finished = finished.iter().any(|animation| {
+                    animation.get(node_id).is_some_and(|keyframes| {
+                        keyframes.channels.iter().any(|channel| channel.type_id == type_id)
+                    })
+                });
+                if is_finished {
+                    if let Some(target) = pose.into_target() {
+                        resting.entry(node_id.clone()).or_default().insert(type_id, target);
+                    }
+                } else if let BlendedPose::Pose(_) = pose {
+                    if let Some(channels) = resting.get_mut(node_id) {
+                        channels.remove(&type_id);
+                    }
+                }
+            }
+
+            // Animations that were replaced stop once the crossfade that replaced them is over
+            let crossfading: Vec<u64> = state
+                .animations
+                .values()
+                .filter_map(|state| match state {
+                    InternalPlaybackState::Playing(playback)
+                    | InternalPlaybackState::Paused(playback) => {
+                        playback.crossfade.map(|_| playback.order)
+                    }
+                    InternalPlaybackState::Stopped => None,
+                })
+                .collect();
+
+            for state in state.animations.values_mut() {
+                let is_replaced = matches!(
+                    state,
+                    InternalPlaybackState::Playing(playback)
+                    | InternalPlaybackState::Paused(playback)
+                    if playback.replaced_by.is_some_and(|order| !crossfading.contains(&order))
+                );
+
+                if is_replaced {
                     *state = InternalPlaybackState::Stopped;
+                    changed = true;
                 }
             }
+
             // SAFETY: We ensure via the query filter that this entity has
             // LayoutAnimationPlaybackState
             let mut ref_state = unsafe {
@@ -622,5 +1635,9 @@ pub(crate) fn update_animations(world: &mut World) {
                 ref_state.set_changed();
             }
         });
+
+        events
     });
+
+    world.send_event_batch(events);
 }
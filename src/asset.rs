@@ -4,23 +4,33 @@ use std::{
 };
 
 use bevy::{
-    asset::{Asset, AssetLoader, AsyncReadExt, Handle, VisitAssetDependencies},
+    asset::{
+        io::Writer, saver::AssetSaver, saver::SavedAsset, Asset, AssetLoader, Assets, AsyncReadExt,
+        AsyncWriteExt, Handle, VisitAssetDependencies,
+    },
     math::{UVec2, Vec2},
-    reflect::TypePath,
-    render::{color::Color, texture::Image},
+    reflect::{TypePath, TypeRegistry},
+    render::{
+        color::Color,
+        texture::{Image, ImageLoaderSettings, ImageSampler},
+    },
     text::{Font, JustifyText},
 };
-use serde::{Deserialize, Deserializer, Serialize};
+use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
-    animation::LayoutAnimation, components::NodeKind, node::Anchor, DynamicAttribute,
-    LayoutRegistryInner, RestrictedLoadContext,
+    animation::LayoutAnimation, components::NodeKind, node::Anchor, AttributeLoadError,
+    DynamicAttribute, LayoutRegistry, LayoutRegistryInner, RestrictedLoadContext,
 };
 use thiserror::Error;
 
 mod deserialize_animation;
 mod deserialize_layout;
 mod helpers;
+mod layout_schema;
+mod serialize_layout;
 
 pub(crate) fn deserialize_color<'de, D: Deserializer<'de>>(
     deserializer: D,
@@ -39,12 +49,28 @@ pub(crate) fn deserialize_color_opt<'de, D: Deserializer<'de>>(
     }
 }
 
+pub(crate) fn serialize_color<S: Serializer>(
+    color: &Color,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    color.as_rgba_f32().serialize(serializer)
+}
+
+pub(crate) fn serialize_color_opt<S: Serializer>(
+    color: &Option<Color>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    color.map(|color| color.as_rgba_f32()).serialize(serializer)
+}
+
 /// A collection of nodes with an associated coordinate system and resolution
 #[derive(TypePath)]
 pub struct Layout {
     /// The resolution to interpret this layout's coodinate system as
     ///
-    /// If [`None`], default to `canvas_size`
+    /// If [`None`], default to `canvas_size`. The resolution from the layout's
+    /// [`load_settings`](Self::load_settings) takes priority over this one, see
+    /// [`get_resolution`](Self::get_resolution).
     pub resolution: Option<UVec2>,
 
     /// The size of this layout.
@@ -56,86 +82,138 @@ pub struct Layout {
     /// system of the root node
     pub canvas_size: UVec2,
 
+    /// The variables declared by this layout, keyed by their name (including the leading `$`)
+    ///
+    /// Nodes of the layout that reference a variable are loaded with the value declared here.
+    /// When this layout is spawned as a sublayout, the layouts that contain it can give its
+    /// variables other values for the [`BoundField`]s of its nodes, see
+    /// [`LayoutNodeData::variables`].
+    pub variables: IndexMap<String, serde_value::Value>,
+
+    /// Groups of nodes that can be reused by the nodes of this layout, keyed by their name
+    ///
+    /// Each `Prefab` node in the layout is loaded as a copy of its prefab, see
+    /// [`GroupNodeData::prefab`].
+    pub prefabs: IndexMap<String, GroupNodeData>,
+
     /// The nodes of the layout
     pub nodes: Vec<LayoutNode>,
 
     /// Animations associated with this layout
     pub animations: Vec<Handle<LayoutAnimation>>,
+
+    /// The settings that this layout was loaded with
+    ///
+    /// The [`LayoutSaver`] and [`CookedLayoutSaver`] carry these over to the processed layout.
+    pub load_settings: LayoutLoaderSettings,
 }
 
 impl Layout {
+    /// Finds the node at the path of node ids `id`, descending into groups and the template of
+    /// repeated nodes
     pub fn child_by_id(&self, id: impl AsRef<Path>) -> Option<&LayoutNode> {
-        let mut nodes = &self.nodes;
-        let path = id.as_ref();
-        let count = path.components().count();
-        'search: for (idx, id) in path.components().enumerate() {
-            let id = id.as_os_str().to_str().unwrap();
-            for node in nodes.iter() {
-                if node.id != id {
-                    continue;
-                }
-
-                if idx + 1 == count {
-                    return Some(node);
-                } else {
-                    match &node.inner {
-                        LayoutNodeInner::Group(group_data) => nodes = &group_data.nodes,
-                        _ => return None,
-                    }
-                }
-
-                continue 'search;
-            }
-
-            return None;
+        let mut components = id
+            .as_ref()
+            .components()
+            .map(|id| id.as_os_str().to_str().unwrap());
+        let first = components.next()?;
+        let mut node = self.nodes.iter().find(|node| node.id == first)?;
+        for id in components {
+            node = node.child(id)?;
         }
 
-        unreachable!()
+        Some(node)
     }
 
+    /// Finds the node at the path of node ids `id`, see [`child_by_id`](Self::child_by_id)
     pub fn child_by_id_mut(&mut self, id: impl AsRef<Path>) -> Option<&mut LayoutNode> {
-        let mut nodes = &mut self.nodes;
-        let path = id.as_ref();
-        let count = path.components().count();
-        'search: for (idx, id) in path.components().enumerate() {
-            let id = id.as_os_str().to_str().unwrap();
-            for node in nodes.iter_mut() {
-                if node.id != id {
-                    continue;
-                }
-
-                if idx + 1 == count {
-                    return Some(node);
-                } else {
-                    match &mut node.inner {
-                        LayoutNodeInner::Group(group_data) => nodes = &mut group_data.nodes,
-                        _ => return None,
-                    }
-                }
-
-                continue 'search;
-            }
-
-            return None;
+        let mut components = id
+            .as_ref()
+            .components()
+            .map(|id| id.as_os_str().to_str().unwrap());
+        let first = components.next()?;
+        let mut node = self.nodes.iter_mut().find(|node| node.id == first)?;
+        for id in components {
+            node = node.child_mut(id)?;
         }
 
-        unreachable!()
+        Some(node)
     }
 }
 
 impl Asset for Layout {}
 
+/// Where a path of node ids leads to in a list of layout nodes
+pub(crate) enum NodePathTarget<'a> {
+    /// The path leads to a node in the list (or is empty)
+    Node,
+    /// There is no node at this path
+    Missing,
+    /// The path continues into a sublayout, which has to be loaded to resolve the rest of it
+    Sublayout {
+        handle: &'a Handle<Layout>,
+        rest: Utf8PathBuf,
+    },
+}
+
+/// Follows a path of node ids through `nodes`, descending into groups and the template of
+/// repeated nodes
+pub(crate) fn resolve_node_path<'a>(
+    nodes: &'a [LayoutNode],
+    path: &Utf8Path,
+) -> NodePathTarget<'a> {
+    let mut components = path.iter();
+    let Some(first) = components.next() else {
+        return NodePathTarget::Node;
+    };
+
+    let Some(mut node) = nodes.iter().find(|node| node.id == first) else {
+        return NodePathTarget::Missing;
+    };
+
+    loop {
+        if let LayoutNodeInner::Layout(data) = &node.inner {
+            let rest: Utf8PathBuf = components.collect();
+            if rest.as_str().is_empty() {
+                return NodePathTarget::Node;
+            }
+
+            return NodePathTarget::Sublayout {
+                handle: &data.handle,
+                rest,
+            };
+        }
+
+        let Some(id) = components.next() else {
+            return NodePathTarget::Node;
+        };
+
+        let Some(child) = node.child(id) else {
+            return NodePathTarget::Missing;
+        };
+
+        node = child;
+    }
+}
+
 fn visit_node_dependencies(node: &LayoutNode, visit: &mut impl FnMut(bevy::asset::UntypedAssetId)) {
     match &node.inner {
         LayoutNodeInner::Null => {}
         LayoutNodeInner::Image(data) => visit(data.handle.id().untyped()),
         LayoutNodeInner::Text(data) => visit(data.handle.id().untyped()),
-        LayoutNodeInner::Layout(data) => visit(data.handle.id().untyped()),
+        LayoutNodeInner::Layout(data) => {
+            visit(data.handle.id().untyped());
+            visit_override_dependencies(&data.overrides, visit);
+        }
         LayoutNodeInner::Group(data) => {
             for node in data.nodes.iter() {
                 visit_node_dependencies(node, visit)
             }
         }
+        LayoutNodeInner::Repeat(data) => {
+            visit_node_dependencies(&data.template, visit);
+            visit_override_dependencies(&data.overrides, visit);
+        }
     }
 
     for attribute in node.attributes.iter() {
@@ -143,6 +221,21 @@ fn visit_node_dependencies(node: &LayoutNode, visit: &mut impl FnMut(bevy::asset
     }
 }
 
+fn visit_override_dependencies(
+    overrides: &IndexMap<Utf8PathBuf, NodeOverride>,
+    visit: &mut impl FnMut(bevy::asset::UntypedAssetId),
+) {
+    for node_override in overrides.values() {
+        if node_override.image.is_some() {
+            visit(node_override.image_handle.id().untyped());
+        }
+
+        for attribute in node_override.attributes.iter() {
+            attribute.visit_dependencies(visit);
+        }
+    }
+}
+
 impl VisitAssetDependencies for Layout {
     fn visit_dependencies(&self, visit: &mut impl FnMut(bevy::asset::UntypedAssetId)) {
         for node in self.nodes.iter() {
@@ -152,13 +245,81 @@ impl VisitAssetDependencies for Layout {
 }
 
 impl Layout {
-    /// Gets the resolution of the layout, prioritizing the resolution (if it is explicitly defined)
-    /// and falling back to the canvas size
+    /// Gets the resolution of the layout, prioritizing the resolution from the load settings and
+    /// then the layout (if either is explicitly defined) and falling back to the canvas size
     pub fn get_resolution(&self) -> UVec2 {
-        self.resolution.unwrap_or(self.canvas_size)
+        self.load_settings
+            .resolution
+            .or(self.resolution)
+            .unwrap_or(self.canvas_size)
+    }
+
+    /// Serializes this layout back into the `layout.json` format
+    ///
+    /// Attributes and animation targets are serialized with the methods they were registered
+    /// with in the [`LayoutRegistry`]. The animations of the layout are looked up in `animations`,
+    /// and are written out under the label that they were loaded with.
+    pub fn to_json(
+        &self,
+        registry: &LayoutRegistry,
+        animations: &Assets<LayoutAnimation>,
+    ) -> Result<String, LayoutSerializeError> {
+        let animations = self.loaded_animations(animations)?;
+        serialize_layout::serialize_json(self, &registry.inner.read().unwrap(), &animations)
+    }
+
+    /// Builds a JSON schema of the `layout.json` format, which editors can use to validate and
+    /// autocomplete layout files
+    ///
+    /// The schema covers every attribute and animation target registered with `registry`. Their
+    /// payloads are described with the reflection info of their types in `types` (usually the
+    /// [`AppTypeRegistry`](bevy::ecs::reflect::AppTypeRegistry)), and accept any value if their
+    /// type has not been registered there.
+    pub fn json_schema(registry: &LayoutRegistry, types: &TypeRegistry) -> serde_json::Value {
+        layout_schema::layout_schema(&registry.inner.read().unwrap(), types)
+    }
+
+    /// Serializes this layout into the binary format read by [`CookedLayoutLoader`]
+    ///
+    /// See [`to_json`](Self::to_json) for how attributes and animations are written out.
+    #[cfg(feature = "cooked")]
+    pub fn to_cooked(
+        &self,
+        registry: &LayoutRegistry,
+        animations: &Assets<LayoutAnimation>,
+    ) -> Result<Vec<u8>, LayoutSerializeError> {
+        let animations = self.loaded_animations(animations)?;
+        serialize_layout::serialize_cooked(self, &registry.inner.read().unwrap(), &animations)
+    }
+
+    /// Finds the animations of this layout in `animations`, along with their names
+    fn loaded_animations<'a>(
+        &'a self,
+        animations: &'a Assets<LayoutAnimation>,
+    ) -> Result<Vec<(&'a str, &'a LayoutAnimation)>, LayoutSerializeError> {
+        self.animations
+            .iter()
+            .map(|handle| {
+                let name = animation_name(handle)?;
+                animations
+                    .get(handle.id())
+                    .map(|animation| (name, animation))
+                    .ok_or_else(|| LayoutSerializeError::MissingAnimation {
+                        animation: name.to_string(),
+                    })
+            })
+            .collect()
     }
 }
 
+/// The name that an animation of a layout was loaded with
+fn animation_name(handle: &Handle<LayoutAnimation>) -> Result<&str, LayoutSerializeError> {
+    handle
+        .path()
+        .and_then(|path| path.label())
+        .ok_or(LayoutSerializeError::UnnamedAnimation)
+}
+
 /// A single node in a layout
 #[derive(Default)]
 pub struct LayoutNode {
@@ -192,13 +353,119 @@ pub struct LayoutNode {
     pub inner: LayoutNodeInner,
 
     /// User-space attributes for each node
-    pub attributes: Vec<DynamicAttribute>,
+    ///
+    /// Spawned nodes share the attributes that were applied to them, so that they are torn down
+    /// with those attributes even once the layout has changed or is gone.
+    pub attributes: Vec<Arc<DynamicAttribute>>,
+
+    /// The fields of this node that were set to a variable of the layout
+    ///
+    /// The fields already hold the value that the variable was declared with. When the layout
+    /// is spawned as a sublayout, they are given the value of the variable in the enclosing
+    /// layouts instead.
+    pub bindings: Vec<VariableBinding>,
+}
+
+impl LayoutNode {
+    /// The child of this node with the id `id`
+    ///
+    /// Every item of a `Repeat` node is spawned from the same node, its template.
+    pub fn child(&self, id: &str) -> Option<&LayoutNode> {
+        match &self.inner {
+            LayoutNodeInner::Group(group) => group.nodes.iter().find(|node| node.id == id),
+            LayoutNodeInner::Repeat(repeat) => {
+                repeat.item_index(id).is_some().then(|| &*repeat.template)
+            }
+            _ => None,
+        }
+    }
+
+    /// The child of this node with the id `id`, see [`child`](Self::child)
+    ///
+    /// The template of a `Repeat` node can't be changed once the layout has been spawned.
+    pub fn child_mut(&mut self, id: &str) -> Option<&mut LayoutNode> {
+        match &mut self.inner {
+            LayoutNodeInner::Group(group) => group.nodes.iter_mut().find(|node| node.id == id),
+            LayoutNodeInner::Repeat(repeat) => match repeat.item_index(id) {
+                Some(_) => Arc::get_mut(&mut repeat.template),
+                None => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The variable that `field` is bound to, if it is bound to one
+    pub fn binding(&self, field: BoundField) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.field == field)
+            .map(|binding| binding.variable.as_str())
+    }
+
+    /// The attributes that are applied to this node when `overrides` are applied to it
+    ///
+    /// Attributes of later overrides replace the attributes of earlier ones
+    pub(crate) fn attributes_with_overrides<'a>(
+        &'a self,
+        overrides: &'a [&'a NodeOverride],
+    ) -> impl Iterator<Item = &'a Arc<DynamicAttribute>> + 'a {
+        let own = self
+            .attributes
+            .iter()
+            .filter(|attribute| !overrides.iter().any(|o| o.replaces(attribute.name())));
+
+        let overridden = overrides.iter().enumerate().flat_map(move |(idx, o)| {
+            o.attributes.iter().filter(move |attribute| {
+                !overrides[idx + 1..]
+                    .iter()
+                    .any(|o| o.replaces(attribute.name()))
+            })
+        });
+
+        own.chain(overridden)
+    }
+}
+
+/// A field of a node that was set to a layout variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableBinding {
+    pub field: BoundField,
+
+    /// The name of the variable, including the leading `$`
+    pub variable: String,
+}
+
+/// The fields of a node that follow the value of the variable they are set to
+///
+/// Variables can be used anywhere in a node, but only these fields are updated when a
+/// sublayout's variables are given other values. Attributes and animation targets keep the value
+/// that the variable was declared with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BoundField {
+    /// The `position` of the node
+    Position,
+    /// The `size` of the node
+    Size,
+    /// The `rotation` of the node
+    Rotation,
+    /// The `text` of a `Text` node
+    Text,
+    /// The font `size` of a `Text` node
+    TextSize,
+    /// The `tint` of an `Image` node, or the `color` of a `Text` node
+    Color,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ImageNodeData {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<PathBuf>,
-    #[serde(default, deserialize_with = "deserialize_color_opt")]
+    #[serde(
+        default,
+        deserialize_with = "deserialize_color_opt",
+        serialize_with = "serialize_color_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub tint: Option<Color>,
     #[serde(skip)]
     pub handle: Handle<Image>,
@@ -208,9 +475,12 @@ pub struct ImageNodeData {
 pub struct TextNodeData {
     pub text: String,
     pub size: f32,
-    #[serde(deserialize_with = "deserialize_color")]
+    #[serde(
+        deserialize_with = "deserialize_color",
+        serialize_with = "serialize_color"
+    )]
     pub color: Color,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub font: Option<PathBuf>,
     #[serde(skip)]
     pub handle: Handle<Font>,
@@ -218,17 +488,152 @@ pub struct TextNodeData {
     pub alignment: JustifyText,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Default)]
 pub struct LayoutNodeData {
     pub path: PathBuf,
-    #[serde(skip)]
+
+    /// Changes to the nodes of the sublayout which only apply to this instance of it, keyed by
+    /// the path of the node relative to the sublayout
+    ///
+    /// Paths can lead into the sublayouts of the sublayout. When more than one layout overrides
+    /// the same node, the outermost layout is applied last.
+    pub overrides: IndexMap<Utf8PathBuf, NodeOverride>,
+
+    /// Values for the variables of the sublayout, which only apply to this instance of it
+    ///
+    /// Variables that aren't set here take their value from the layouts that contain this node,
+    /// and otherwise keep the value that the sublayout declares them with.
+    ///
+    /// Only the [`BoundField`]s of the sublayout's nodes follow these values. Its attributes and
+    /// animation targets are loaded once with the sublayout and keep the declared values.
+    pub variables: IndexMap<String, serde_value::Value>,
+
     pub handle: Handle<Layout>,
 }
 
+/// Changes made to a single node of a sublayout when it is spawned
+#[derive(Default)]
+pub struct NodeOverride {
+    /// Replaces the text of a `Text` node
+    pub text: Option<String>,
+
+    /// Replaces the image of an `Image` node
+    pub image: Option<PathBuf>,
+
+    /// Replaces the tint of an `Image` node, or the color of a `Text` node
+    pub tint: Option<Color>,
+
+    /// Replaces the position of the node
+    pub position: Option<Vec2>,
+
+    /// Attributes to apply to the node
+    ///
+    /// These replace any attributes of the node with the same name, the rest of the node's
+    /// attributes are still applied
+    pub attributes: Vec<Arc<DynamicAttribute>>,
+
+    /// The handle of `image`, once it has been loaded
+    pub image_handle: Handle<Image>,
+}
+
+impl NodeOverride {
+    /// Whether the node has an attribute named `name` that is replaced by this override
+    pub(crate) fn replaces(&self, name: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| attribute.name() == name)
+    }
+}
+
 #[derive(Default)]
 pub struct GroupNodeData {
     pub child_anchor: Anchor,
     pub nodes: Vec<LayoutNode>,
+
+    /// The prefab that this group is a copy of, if it was loaded from a `Prefab` node
+    ///
+    /// Groups made from a prefab are written back out as a reference to the prefab, so changes
+    /// made to their nodes are not saved.
+    pub prefab: Option<PrefabInstance>,
+}
+
+/// How the items of a `Repeat` node are laid out
+///
+/// Items are placed in rows of `columns` items (or a single row, if it is [`None`]). Each item
+/// is moved by `stride` from the previous item in its row, and each row is moved by `row_stride`
+/// from the previous row.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct RepeatGrid {
+    pub stride: Vec2,
+    pub columns: Option<usize>,
+    pub row_stride: Vec2,
+}
+
+impl RepeatGrid {
+    /// How far the item at `index` is moved from the position of the template
+    pub fn item_offset(&self, index: usize) -> Vec2 {
+        match self.columns.filter(|columns| *columns > 0) {
+            Some(columns) => {
+                self.stride * (index % columns) as f32 + self.row_stride * (index / columns) as f32
+            }
+            None => self.stride * index as f32,
+        }
+    }
+}
+
+/// A node that is spawned once for each item of a `Repeat` node
+pub struct RepeatNodeData {
+    /// How many items are spawned
+    pub count: usize,
+
+    /// Where each item is placed, relative to the position of the template
+    pub grid: RepeatGrid,
+
+    /// The node that each item is spawned from
+    ///
+    /// Items are given the id of the template followed by their index, e.g. `item_0`, `item_1`.
+    /// The template is shared with the spawned node, so that more items can be added at
+    /// runtime.
+    pub template: Arc<LayoutNode>,
+
+    /// Changes to single items or the nodes inside of them, keyed by the path of the node
+    /// relative to this node (e.g. `item_2/label`)
+    pub overrides: IndexMap<Utf8PathBuf, NodeOverride>,
+}
+
+impl RepeatNodeData {
+    /// The id of the item at `index`
+    pub fn item_id(&self, index: usize) -> String {
+        item_id(&self.template.id, index)
+    }
+
+    /// The index of the item with the id `id`, if it is one of the items of this node
+    ///
+    /// Items past [`count`](Self::count) are included, since they can be spawned at runtime
+    /// with [`RepeatNodeMut::set_count`](crate::views::RepeatNodeMut::set_count).
+    pub fn item_index(&self, id: &str) -> Option<usize> {
+        item_index(&self.template.id, id)
+    }
+}
+
+pub(crate) fn item_id(template: &str, index: usize) -> String {
+    format!("{template}_{index}")
+}
+
+pub(crate) fn item_index(template: &str, id: &str) -> Option<usize> {
+    let suffix = id.strip_prefix(template)?.strip_prefix('_')?;
+    let index: usize = suffix.parse().ok()?;
+    // Only the ids that `item_id` generates, so `item_01` is not an item
+    (index.to_string() == suffix).then_some(index)
+}
+
+/// A reference to one of the [`prefabs`](Layout::prefabs) of a layout
+pub struct PrefabInstance {
+    /// The name of the prefab
+    pub prefab: String,
+
+    /// Values for the variables of the layout, which only apply to this copy of the prefab
+    pub variables: IndexMap<String, serde_value::Value>,
 }
 
 /// First-class node data, guaranteed to be supported by yabuil
@@ -258,6 +663,9 @@ pub enum LayoutNodeInner {
     ///
     /// This should primarily be used to make animation easier
     Group(GroupNodeData),
+
+    /// This node is a group of copies of a template node
+    Repeat(RepeatNodeData),
 }
 
 impl LayoutNodeInner {
@@ -268,25 +676,384 @@ impl LayoutNodeInner {
             Self::Text(_) => NodeKind::Text,
             Self::Layout(_) => NodeKind::Layout,
             Self::Group(_) => NodeKind::Group,
+            Self::Repeat(_) => NodeKind::Repeat,
         }
     }
 }
 
-pub(crate) struct LayoutLoader(pub(crate) Arc<RwLock<LayoutRegistryInner>>);
+/// The file formats that layouts can be read from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum LayoutFormat {
+    Json,
+    #[cfg(feature = "ron")]
+    Ron,
+    #[cfg(feature = "cooked")]
+    Cooked,
+}
+
+/// Written at the start of cooked layouts, the last byte is the version of the format
+#[cfg(feature = "cooked")]
+pub(crate) const COOKED_LAYOUT_HEADER: &[u8] = b"yabuil-layout\x01";
+
+/// Loads [`Layout`] assets from `layout.json` files
+pub struct LayoutLoader(pub(crate) Arc<RwLock<LayoutRegistryInner>>);
+
+/// Settings for loading a single layout, shared by all of the layout loaders
+///
+/// These can be given in the `.meta` file of a layout, or when loading it with
+/// `AssetServer::load_with_settings`. Every setting that is [`None`] keeps the default behavior.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct LayoutLoaderSettings {
+    /// Overrides whether unknown attributes and animation targets are ignored for this layout
+    ///
+    /// If [`None`], [`LayoutPlugin::ignore_unknown_registry_data`](crate::LayoutPlugin::ignore_unknown_registry_data)
+    /// is used.
+    pub ignore_unknown_registry_data: Option<bool>,
+
+    /// The sampler that the images of image nodes (and image overrides) are loaded with
+    pub image_sampler: Option<ImageSampler>,
+
+    /// The font that text nodes without a font of their own are loaded with
+    ///
+    /// The font is not written into the nodes, so saving the layout keeps them without a font.
+    pub default_font: Option<PathBuf>,
+
+    /// Overrides the resolution that the layout's coordinate system is interpreted as, see
+    /// [`Layout::get_resolution`]
+    ///
+    /// The resolution is not written into the layout, so saving it keeps the resolution of the
+    /// file.
+    pub resolution: Option<UVec2>,
+}
+
+/// Where in a layout file an error happened
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayoutErrorLocation {
+    /// The path of node ids from the top of the layout to the node that the error happened in
+    /// (e.g. `group_a/button`)
+    ///
+    /// Nodes whose id has not been read yet are named by their index in their parent (e.g. `[2]`).
+    /// This is [`None`] if the error happened outside of a node.
+    pub node_path: Option<Utf8PathBuf>,
+
+    /// The line in the file, starting at 1
+    ///
+    /// This is 0 if the format of the file does not keep track of where values are (i.e. for
+    /// problems found in `layout.ron` files, and for cooked layouts).
+    pub line: usize,
+
+    /// The column in the line, starting at 1
+    ///
+    /// This is 0 whenever [`line`](Self::line) is
+    pub column: usize,
+}
+
+impl LayoutErrorLocation {
+    fn in_node(&self) -> String {
+        self.node_path
+            .as_ref()
+            .map(|path| format!(" in node `{path}`"))
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for LayoutErrorLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.node_path.as_ref(), self.line) {
+            (Some(path), 0) => write!(f, "node `{path}`"),
+            (Some(path), _) => write!(
+                f,
+                "node `{path}` at line {} column {}",
+                self.line, self.column
+            ),
+            (None, 0) => write!(f, "unknown position"),
+            (None, _) => write!(f, "line {} column {}", self.line, self.column),
+        }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum LayoutError {
     #[error(transparent)]
     IO(#[from] std::io::Error),
 
+    /// The file is not valid JSON, or it does not match the structure of a layout
+    #[error("{error}{}", .location.in_node())]
+    JSON {
+        location: LayoutErrorLocation,
+        #[source]
+        error: serde_json::Error,
+    },
+
+    /// The file is not valid RON, or it does not match the structure of a layout
+    #[cfg(feature = "ron")]
+    #[error("{error}{}", .location.in_node())]
+    RON {
+        location: LayoutErrorLocation,
+        #[source]
+        error: Box<ron::error::SpannedError>,
+    },
+
+    /// The cooked layout is corrupted, or it was cooked with an older version of yabuil
+    #[cfg(feature = "cooked")]
+    #[error("{error}{}", .location.in_node())]
+    Cooked {
+        location: LayoutErrorLocation,
+        #[source]
+        error: rmp_serde::decode::Error,
+    },
+
+    /// The file does not start with the header of a cooked layout, or the layout was cooked
+    /// with a different version of the format
+    #[cfg(feature = "cooked")]
+    #[error("File is not a cooked layout, or was cooked by an incompatible version of yabuil")]
+    InvalidCookedHeader,
+
+    /// Every problem that was found in the layout file, when there is more than one
+    #[error("{} problems found in layout:\n{}", .0.len(), display_diagnostics(.0))]
+    Diagnostics(Vec<LayoutError>),
+
+    #[error("Unknown field '{field}' ({location})")]
+    UnknownField {
+        location: LayoutErrorLocation,
+        field: String,
+    },
+
+    #[error("Missing field '{field}' ({location})")]
+    MissingField {
+        location: LayoutErrorLocation,
+        field: String,
+    },
+
+    #[error("Duplicate field '{field}' ({location})")]
+    DuplicateField {
+        location: LayoutErrorLocation,
+        field: String,
+    },
+
+    #[error("Invalid value for field '{field}' ({location}): {error}")]
+    InvalidField {
+        location: LayoutErrorLocation,
+        field: String,
+        #[source]
+        error: Box<serde_value::DeserializerError>,
+    },
+
+    #[error("Node id '{id}' is used by more than one sibling ({location})")]
+    DuplicateNodeId {
+        location: LayoutErrorLocation,
+        id: String,
+    },
+
+    #[error("Variable '{variable}' was not declared before it was used ({location})")]
+    UnknownVariable {
+        location: LayoutErrorLocation,
+        variable: String,
+    },
+
+    #[error("Invalid variable name '{variable}', variables start with a single '$' ({location})")]
+    InvalidVariableName {
+        location: LayoutErrorLocation,
+        variable: String,
+    },
+
+    #[error("Prefab '{prefab}' was not declared before it was used ({location})")]
+    UnknownPrefab {
+        location: LayoutErrorLocation,
+        prefab: String,
+    },
+
+    #[error("Null nodes do not have associated node_data ({location})")]
+    UnexpectedNodeData { location: LayoutErrorLocation },
+
+    #[error("Animation '{animation}' targets a node that does not exist ({location})")]
+    UnknownAnimationNode {
+        location: LayoutErrorLocation,
+        animation: String,
+    },
+
+    #[error("LayoutNode attribute '{attribute}' was not registered ({location})")]
+    UnknownAttribute {
+        location: LayoutErrorLocation,
+        attribute: String,
+    },
+
+    #[error("Failed to deserialize LayoutNode attribute '{attribute}' ({location}): {error}")]
+    InvalidAttribute {
+        location: LayoutErrorLocation,
+        attribute: String,
+        #[source]
+        error: Box<serde_value::DeserializerError>,
+    },
+
+    #[error(
+        "Failed to load the dependencies of LayoutNode attribute '{attribute}' in node `{node_path}`: {error}"
+    )]
+    AttributeDependencies {
+        node_path: Utf8PathBuf,
+        attribute: String,
+        #[source]
+        error: AttributeLoadError,
+    },
+
+    #[error("Failed to deserialize the node_data of {kind:?} node ({location}): {error}")]
+    InvalidNodeData {
+        location: LayoutErrorLocation,
+        kind: NodeKind,
+        #[source]
+        error: Box<serde_value::DeserializerError>,
+    },
+
+    #[error("LayoutAnimationTarget '{target}' in animation '{animation}' was not registered ({location})")]
+    UnknownAnimationTarget {
+        location: LayoutErrorLocation,
+        animation: String,
+        target: String,
+    },
+
+    #[error("Failed to deserialize LayoutAnimationTarget '{target}' in animation '{animation}' ({location}): {error}")]
+    InvalidAnimationTarget {
+        location: LayoutErrorLocation,
+        animation: String,
+        target: String,
+        #[source]
+        error: Box<serde_value::DeserializerError>,
+    },
+}
+
+impl LayoutError {
+    /// Wraps a list of errors, unwrapping it if there is only one
+    pub(crate) fn from_diagnostics(mut errors: Vec<LayoutError>) -> Self {
+        if errors.len() == 1 {
+            errors.pop().unwrap()
+        } else {
+            Self::Diagnostics(errors)
+        }
+    }
+}
+
+/// An error that occurred while writing a layout back out to `layout.json`
+#[derive(Error, Debug)]
+pub enum LayoutSerializeError {
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+
     #[error(transparent)]
     JSON(#[from] serde_json::Error),
+
+    #[cfg(feature = "cooked")]
+    #[error(transparent)]
+    Cooked(#[from] rmp_serde::encode::Error),
+
+    #[error("LayoutNode attribute '{attribute}' in node `{node_path}` was not registered")]
+    UnknownAttribute {
+        node_path: Utf8PathBuf,
+        attribute: String,
+    },
+
+    #[error(
+        "Failed to serialize LayoutNode attribute '{attribute}' in node `{node_path}`: {error}"
+    )]
+    InvalidAttribute {
+        node_path: Utf8PathBuf,
+        attribute: String,
+        #[source]
+        error: Box<serde_value::SerializerError>,
+    },
+
+    #[error("LayoutAnimationTarget '{target}' in animation '{animation}' was not registered")]
+    UnknownAnimationTarget { animation: String, target: String },
+
+    #[error(
+        "Failed to serialize LayoutAnimationTarget '{target}' in animation '{animation}': {error}"
+    )]
+    InvalidAnimationTarget {
+        animation: String,
+        target: String,
+        #[source]
+        error: Box<serde_value::SerializerError>,
+    },
+
+    /// The animation is not loaded, so its keyframes can't be written out
+    #[error("Animation '{animation}' is not loaded")]
+    MissingAnimation { animation: String },
+
+    /// The animation was not loaded as a labeled asset of the layout, so it has no name to be
+    /// written out with
+    #[error("Layout has an animation that was not loaded from a layout file")]
+    UnnamedAnimation,
+}
+
+fn display_diagnostics(errors: &[LayoutError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("  - {error}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads a layout in any of the supported formats and loads the assets that it depends on
+async fn load_layout(
+    reader: &mut bevy::asset::io::Reader<'_>,
+    format: LayoutFormat,
+    registry: &RwLock<LayoutRegistryInner>,
+    settings: &LayoutLoaderSettings,
+    load_context: &mut bevy::asset::LoadContext<'_>,
+) -> Result<Layout, LayoutError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes).await?;
+
+    let mut layout: Layout = {
+        let registry = registry.read().unwrap();
+        let ignore_unknown_registry_data = settings
+            .ignore_unknown_registry_data
+            .unwrap_or(registry.ignore_unknown_registry_data);
+        deserialize_layout::deserialize_layout(
+            &bytes,
+            format,
+            &registry,
+            ignore_unknown_registry_data,
+            load_context,
+        )?
+    };
+
+    layout.load_settings = settings.clone();
+
+    let mut context = RestrictedLoadContext { load_context };
+
+    let mut pending = vec![];
+    for node in layout.nodes.iter_mut() {
+        initialize_node(
+            node,
+            Utf8Path::new(""),
+            settings,
+            &mut context,
+            &mut pending,
+        );
+    }
+
+    let mut errors = vec![];
+    for (node_path, attribute) in pending {
+        if let Err(error) = attribute.load_dependencies(&mut context).await {
+            errors.push(LayoutError::AttributeDependencies {
+                node_path,
+                attribute: attribute.name().to_string(),
+                error,
+            });
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(LayoutError::from_diagnostics(errors));
+    }
+
+    Ok(layout)
 }
 
 impl AssetLoader for LayoutLoader {
     type Asset = Layout;
     type Error = LayoutError;
-    type Settings = ();
+    type Settings = LayoutLoaderSettings;
 
     fn extensions(&self) -> &[&str] {
         &["layout.json"]
@@ -295,52 +1062,279 @@ impl AssetLoader for LayoutLoader {
     fn load<'a>(
         &'a self,
         reader: &'a mut bevy::asset::io::Reader,
-        _settings: &'a Self::Settings,
+        settings: &'a Self::Settings,
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(load_layout(
+            reader,
+            LayoutFormat::Json,
+            &self.0,
+            settings,
+            load_context,
+        ))
+    }
+}
+
+/// Loads [`Layout`] assets from `layout.ron` files
+///
+/// The fields of layouts and nodes can be written as RON structs, but any other values (such as
+/// attributes, or the anchor of a node) are read without knowing their type. Because of that, enum
+/// values are written the same way as they are in JSON: unit variants as strings (`"TopLeft"`)
+/// and other variants as a map with a single entry (`{"Quadratic": (0.5, 0.0)}`).
+///
+/// Optional fields do not need to be wrapped in `Some`.
+#[cfg(feature = "ron")]
+pub struct RonLayoutLoader(pub(crate) Arc<RwLock<LayoutRegistryInner>>);
+
+#[cfg(feature = "ron")]
+impl AssetLoader for RonLayoutLoader {
+    type Asset = Layout;
+    type Error = LayoutError;
+    type Settings = LayoutLoaderSettings;
+
+    fn extensions(&self) -> &[&str] {
+        &["layout.ron"]
+    }
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        settings: &'a Self::Settings,
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(load_layout(
+            reader,
+            LayoutFormat::Ron,
+            &self.0,
+            settings,
+            load_context,
+        ))
+    }
+}
+
+/// Loads [`Layout`] assets from the binary format written by [`CookedLayoutSaver`]
+///
+/// This is used to load the layouts that bevy's asset processor has cooked, but it can also
+/// load `layout.bin` files directly.
+#[cfg(feature = "cooked")]
+pub struct CookedLayoutLoader(pub(crate) Arc<RwLock<LayoutRegistryInner>>);
+
+#[cfg(feature = "cooked")]
+impl AssetLoader for CookedLayoutLoader {
+    type Asset = Layout;
+    type Error = LayoutError;
+    type Settings = LayoutLoaderSettings;
+
+    fn extensions(&self) -> &[&str] {
+        &["layout.bin"]
+    }
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        settings: &'a Self::Settings,
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(load_layout(
+            reader,
+            LayoutFormat::Cooked,
+            &self.0,
+            settings,
+            load_context,
+        ))
+    }
+}
+
+/// Finds the animations of a layout that is being saved, along with their names
+fn saved_animations<'a>(
+    asset: &'a SavedAsset<'a, Layout>,
+) -> Result<Vec<(&'a str, SavedAsset<'a, LayoutAnimation>)>, LayoutSerializeError> {
+    asset
+        .animations
+        .iter()
+        .map(|handle| {
+            let name = animation_name(handle)?;
+            asset
+                .get_labeled::<LayoutAnimation, _>(name)
+                .map(|animation| (name, animation))
+                .ok_or_else(|| LayoutSerializeError::MissingAnimation {
+                    animation: name.to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Saves [`Layout`] assets as `layout.json` files, so that they can be used with bevy's asset
+/// processor
+pub struct LayoutSaver(pub(crate) Arc<RwLock<LayoutRegistryInner>>);
+
+impl LayoutSaver {
+    /// Creates a saver that uses the attributes and animation targets of `registry`
+    pub fn new(registry: &LayoutRegistry) -> Self {
+        Self(registry.inner.clone())
+    }
+}
+
+impl AssetSaver for LayoutSaver {
+    type Asset = Layout;
+    type Settings = ();
+    type OutputLoader = LayoutLoader;
+    type Error = LayoutSerializeError;
+
+    fn save<'a>(
+        &'a self,
+        writer: &'a mut Writer,
+        asset: SavedAsset<'a, Self::Asset>,
+        _settings: &'a Self::Settings,
+    ) -> bevy::utils::BoxedFuture<'a, Result<LayoutLoaderSettings, Self::Error>> {
         Box::pin(async move {
-            let mut bytes = vec![];
-            reader.read_to_end(&mut bytes).await?;
+            let saved_animations = saved_animations(&asset)?;
+            let animations: Vec<_> = saved_animations
+                .iter()
+                .map(|(name, animation)| (*name, animation.get()))
+                .collect();
 
-            let mut layout: Layout = deserialize_layout::deserialize_layout(
-                &bytes,
+            let json = serialize_layout::serialize_json(
+                asset.get(),
                 &self.0.read().unwrap(),
-                load_context,
+                &animations,
             )?;
 
-            let mut context = RestrictedLoadContext { load_context };
+            writer.write_all(json.as_bytes()).await?;
+            Ok(asset.load_settings.clone())
+        })
+    }
+}
 
-            for node in layout.nodes.iter_mut() {
-                initialize_node(node, &mut context);
-            }
+/// Saves [`Layout`] assets in a compact binary format, to be loaded by [`CookedLayoutLoader`]
+///
+/// Layouts are only saved once they have been loaded, so cooked layouts have already been
+/// validated. When bevy's asset processor is enabled, the [`LayoutPlugin`](crate::LayoutPlugin)
+/// uses this to cook every `layout.json` and `layout.ron` file.
+#[cfg(feature = "cooked")]
+pub struct CookedLayoutSaver(pub(crate) Arc<RwLock<LayoutRegistryInner>>);
+
+#[cfg(feature = "cooked")]
+impl CookedLayoutSaver {
+    /// Creates a saver that uses the attributes and animation targets of `registry`
+    pub fn new(registry: &LayoutRegistry) -> Self {
+        Self(registry.inner.clone())
+    }
+}
+
+#[cfg(feature = "cooked")]
+impl AssetSaver for CookedLayoutSaver {
+    type Asset = Layout;
+    type Settings = ();
+    type OutputLoader = CookedLayoutLoader;
+    type Error = LayoutSerializeError;
+
+    fn save<'a>(
+        &'a self,
+        writer: &'a mut Writer,
+        asset: SavedAsset<'a, Self::Asset>,
+        _settings: &'a Self::Settings,
+    ) -> bevy::utils::BoxedFuture<'a, Result<LayoutLoaderSettings, Self::Error>> {
+        Box::pin(async move {
+            let saved_animations = saved_animations(&asset)?;
+            let animations: Vec<_> = saved_animations
+                .iter()
+                .map(|(name, animation)| (*name, animation.get()))
+                .collect();
 
-            Ok(layout)
+            let bytes = serialize_layout::serialize_cooked(
+                asset.get(),
+                &self.0.read().unwrap(),
+                &animations,
+            )?;
+
+            writer.write_all(&bytes).await?;
+            Ok(asset.load_settings.clone())
         })
     }
 }
 
-fn initialize_node(node: &mut LayoutNode, context: &mut RestrictedLoadContext<'_, '_>) {
+/// Loads the image of an image node, with the sampler from the layout's settings if it has one
+fn load_image(
+    path: &Path,
+    settings: &LayoutLoaderSettings,
+    context: &mut RestrictedLoadContext<'_, '_>,
+) -> Handle<Image> {
+    match settings.image_sampler.clone() {
+        Some(sampler) => context.load_with_settings(
+            path.to_path_buf(),
+            move |image_settings: &mut ImageLoaderSettings| {
+                image_settings.sampler = sampler.clone();
+            },
+        ),
+        None => context.load(path.to_path_buf()),
+    }
+}
+
+/// An attribute that still has to load its dependencies, along with the path of the node it is on
+type PendingAttribute<'a> = (Utf8PathBuf, &'a mut DynamicAttribute);
+
+fn initialize_node<'a>(
+    node: &'a mut LayoutNode,
+    parent: &Utf8Path,
+    settings: &LayoutLoaderSettings,
+    context: &mut RestrictedLoadContext<'_, '_>,
+    pending: &mut Vec<PendingAttribute<'a>>,
+) {
+    let node_path = parent.join(&node.id);
     match &mut node.inner {
         LayoutNodeInner::Null => {}
         LayoutNodeInner::Image(data) => {
             if let Some(path) = data.path.as_ref() {
-                data.handle = context.load(path.clone());
+                data.handle = load_image(path, settings, context);
             }
         }
         LayoutNodeInner::Text(data) => {
-            if let Some(font) = data.font.as_ref() {
+            if let Some(font) = data.font.as_ref().or(settings.default_font.as_ref()) {
                 data.handle = context.load(font.clone())
             }
         }
-        LayoutNodeInner::Layout(data) => data.handle = context.load(data.path.clone()),
+        LayoutNodeInner::Layout(data) => {
+            data.handle = context.load(data.path.clone());
+            initialize_overrides(&mut data.overrides, &node_path, settings, context, pending);
+        }
         LayoutNodeInner::Group(group) => {
             for node in group.nodes.iter_mut() {
-                initialize_node(node, context);
+                initialize_node(node, &node_path, settings, context, pending);
             }
         }
+        LayoutNodeInner::Repeat(data) => {
+            // The template is only shared once the layout has been spawned
+            if let Some(template) = Arc::get_mut(&mut data.template) {
+                initialize_node(template, &node_path, settings, context, pending);
+            }
+
+            initialize_overrides(&mut data.overrides, &node_path, settings, context, pending);
+        }
     }
 
-    for attribute in node.attributes.iter_mut() {
+    // Attributes are only shared once the layout has been spawned
+    for attribute in node.attributes.iter_mut().filter_map(Arc::get_mut) {
         attribute.initialize_dependencies(context);
+        pending.push((node_path.clone(), attribute));
+    }
+}
+
+fn initialize_overrides<'a>(
+    overrides: &'a mut IndexMap<Utf8PathBuf, NodeOverride>,
+    node_path: &Utf8Path,
+    settings: &LayoutLoaderSettings,
+    context: &mut RestrictedLoadContext<'_, '_>,
+    pending: &mut Vec<PendingAttribute<'a>>,
+) {
+    for (path, node_override) in overrides.iter_mut() {
+        if let Some(image) = node_override.image.as_ref() {
+            node_override.image_handle = load_image(image, settings, context);
+        }
+
+        for attribute in node_override.attributes.iter_mut().filter_map(Arc::get_mut) {
+            attribute.initialize_dependencies(context);
+            pending.push((node_path.join(path), attribute));
+        }
     }
 }
@@ -1,24 +1,55 @@
 use serde::{
-    de::{DeserializeSeed, Visitor},
+    de::{DeserializeSeed, IgnoredAny, Visitor},
     Deserialize, Deserializer,
 };
 
 use std::marker::PhantomData;
 
-use crate::{
-    animation::{DynamicAnimationTarget, RawKeyframe, RawLayoutAnimations, TimeBezierCurve},
-    LayoutRegistryInner,
+use bevy::utils::HashMap;
+
+use crate::animation::{
+    AnimationLoopMode, AnimationMarker, DynamicAnimationTarget, RawKeyframe, RawLayoutAnimation,
+    RawLayoutAnimations, TimeBezierCurve,
 };
 
-use super::helpers::{
-    decl_ident_parse, decl_struct_parse, HashMapSeedPassthrough, VecSeedPassthrough,
+use super::{
+    deserialize_layout::LayoutDeserializeContext,
+    helpers::{decl_ident_parse, decl_struct_parse, FieldName, VecSeedPassthrough},
+    LayoutError,
 };
 
 decl_ident_parse!(
     field RawKeyframe(TimestampMs, TimeScale, Targets)
 );
 
-pub(crate) struct RawLayoutAnimationsSeed<'de>(pub(crate) &'de LayoutRegistryInner);
+decl_ident_parse!(
+    field AnimationSettings(LoopMode, Priority, Events)
+);
+
+pub(crate) struct RawLayoutAnimationsSeed<'de>(pub(crate) &'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for RawLayoutAnimationsSeed<'de> {
+    type Value = RawLayoutAnimations;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of layout animations")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut output = HashMap::with_capacity(map.size_hint().unwrap_or_default());
+        while let Some(name) = map.next_key::<String>()? {
+            self.0.set_animation(Some(name.clone()));
+            let animation = map.next_value_seed(AnimationNodesSeed(self.0))?;
+            self.0.set_animation(None);
+            output.insert(name, animation);
+        }
+
+        Ok(RawLayoutAnimations(output))
+    }
+}
 
 impl<'de> DeserializeSeed<'de> for RawLayoutAnimationsSeed<'de> {
     type Value = RawLayoutAnimations;
@@ -27,16 +58,175 @@ impl<'de> DeserializeSeed<'de> for RawLayoutAnimationsSeed<'de> {
     where
         D: Deserializer<'de>,
     {
-        let inner = deserializer.deserialize_map(HashMapSeedPassthrough::new(
-            HashMapSeedPassthrough::new(VecSeedPassthrough::new(RawKeyframeSeed(self.0))),
-        ))?;
+        deserializer.deserialize_map(self)
+    }
+}
+
+/// The key of an animation's settings, which are written next to the paths of its nodes
+///
+/// Node ids can't be told apart from it by name, so a list of keyframes under this key still
+/// animates a node with that id
+pub(crate) const SETTINGS_KEY: &str = "@settings";
+
+/// The key that an animation's settings were read from before [`SETTINGS_KEY`]
+const LEGACY_SETTINGS_KEY: &str = "settings";
+
+/// The keyframes of every node in a single animation, keyed by node path, along with the
+/// settings of the animation
+struct AnimationNodesSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for AnimationNodesSeed<'de> {
+    type Value = RawLayoutAnimation;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of node keyframes")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut output = HashMap::with_capacity(map.size_hint().unwrap_or_default());
+        let mut settings = None;
+        while let Some(node) = map.next_key::<String>()? {
+            if node == SETTINGS_KEY || node == LEGACY_SETTINGS_KEY {
+                let location = self.0.location();
+                match map.next_value_seed(AnimationSettingsSeed(self.0, &node))? {
+                    AnimationEntry::Settings(value) => {
+                        self.0.store_field(&mut settings, value, location, &node);
+                    }
+                    AnimationEntry::Keyframes(keyframes) => {
+                        output.insert(node, keyframes);
+                    }
+                }
+                continue;
+            }
+
+            self.0.push_node(node.clone());
+            self.0.record_animation_node(&node);
+            let keyframes =
+                map.next_value_seed(VecSeedPassthrough::new(RawKeyframeSeed(self.0)))?;
+            self.0.pop_node();
+            output.insert(node, keyframes);
+        }
+
+        let AnimationSettings {
+            loop_mode,
+            priority,
+            mut markers,
+        } = settings.unwrap_or_default();
+        markers.sort_by_key(|marker| marker.timestamp_ms);
+
+        Ok(RawLayoutAnimation {
+            loop_mode,
+            priority,
+            markers,
+            nodes: output,
+        })
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for AnimationNodesSeed<'de> {
+    type Value = RawLayoutAnimation;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+/// How an animation is played when its play options don't say otherwise
+#[derive(Default)]
+struct AnimationSettings {
+    loop_mode: AnimationLoopMode,
+    priority: i32,
+    markers: Vec<AnimationMarker>,
+}
+
+/// What was written under one of the keys that can hold the settings of an animation
+enum AnimationEntry {
+    Settings(Option<AnimationSettings>),
+    /// The keyframes of a node whose id is the same as the key
+    Keyframes(Vec<RawKeyframe>),
+}
+
+/// Reads the settings of an animation, or the keyframes of a node if the key holds a list
+struct AnimationSettingsSeed<'de, 'a>(&'de LayoutDeserializeContext<'de>, &'a str);
+
+impl<'de> Visitor<'de> for AnimationSettingsSeed<'de, '_> {
+    type Value = AnimationEntry;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct AnimationSettings or list of keyframes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        self.0.push_node(self.1.to_string());
+        self.0.record_animation_node(self.1);
+        let mut keyframes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(keyframe) = seq.next_element_seed(RawKeyframeSeed(self.0))? {
+            keyframes.push(keyframe);
+        }
+        self.0.pop_node();
+
+        Ok(AnimationEntry::Keyframes(keyframes))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut loop_mode = None;
+        let mut priority = None;
+        let mut markers = None;
+        while let Some(FieldName(key)) = map.next_key()? {
+            let location = self.0.location();
+            match self.0.field_id::<AnimationSettingsFieldId>(&key, &location) {
+                None => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                }
+                Some(AnimationSettingsFieldId::LoopMode) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut loop_mode, value, location, &key);
+                }
+                Some(AnimationSettingsFieldId::Priority) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut priority, value, location, &key);
+                }
+                Some(AnimationSettingsFieldId::Events) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut markers, value, location, &key);
+                }
+            }
+        }
 
-        Ok(RawLayoutAnimations(inner))
+        Ok(AnimationEntry::Settings(Some(AnimationSettings {
+            loop_mode: loop_mode.unwrap_or_default(),
+            priority: priority.unwrap_or_default(),
+            markers: markers.unwrap_or_default(),
+        })))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for AnimationSettingsSeed<'de, '_> {
+    type Value = AnimationEntry;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Read as any value, since the key can also hold the keyframes of a node
+        deserializer.deserialize_any(self)
     }
 }
 
 #[derive(Copy, Clone)]
-struct RawKeyframeSeed<'de>(&'de LayoutRegistryInner);
+struct RawKeyframeSeed<'de>(&'de LayoutDeserializeContext<'de>);
 
 impl<'de> Visitor<'de> for RawKeyframeSeed<'de> {
     type Value = RawKeyframe;
@@ -72,11 +262,11 @@ impl<'de> DeserializeSeed<'de> for RawKeyframeSeed<'de> {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_map(self)
+        deserializer.deserialize_struct("RawKeyframe", RawKeyframeFieldId::NAMES, self)
     }
 }
 
-struct TargetListSeed<'de>(&'de LayoutRegistryInner);
+struct TargetListSeed<'de>(&'de LayoutDeserializeContext<'de>);
 
 impl<'de> Visitor<'de> for TargetListSeed<'de> {
     type Value = Vec<DynamicAnimationTarget>;
@@ -91,22 +281,33 @@ impl<'de> Visitor<'de> for TargetListSeed<'de> {
     {
         let mut list = Vec::with_capacity(map.size_hint().unwrap_or_default());
         while let Some(key) = map.next_key::<String>()? {
-            match self.0.animations.get(key.as_str()) {
+            let location = self.0.location();
+            match self.0.registry.animations.get(key.as_str()) {
                 Some(data) => {
                     let content = map.next_value::<serde_value::Value>()?;
-                    list.push(
-                        (data.deserialize)(content)
-                            .map_err(<A::Error as serde::de::Error>::custom)?,
-                    );
+                    let content = self.0.resolve_variables(content);
+
+                    match (data.deserialize)(content) {
+                        Ok(target) => list.push(target),
+                        Err(error) => self.0.diagnose(LayoutError::InvalidAnimationTarget {
+                            location,
+                            animation: self.0.animation(),
+                            target: key,
+                            error: Box::new(error),
+                        }),
+                    }
                 }
                 None if self.0.ignore_unknown_registry_data => {
                     log::trace!("Ignoring unregistered LayoutAnimationTarget {key}");
-                    let _ = map.next_value::<serde_value::Value>()?;
+                    let _ = map.next_value::<IgnoredAny>()?;
                 }
                 None => {
-                    return Err(<A::Error as serde::de::Error>::custom(format!(
-                        "LayoutAnimationTarget {key} is not registered"
-                    )));
+                    self.0.diagnose(LayoutError::UnknownAnimationTarget {
+                        location,
+                        animation: self.0.animation(),
+                        target: key,
+                    });
+                    let _ = map.next_value::<IgnoredAny>()?;
                 }
             }
         }
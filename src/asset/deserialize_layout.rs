@@ -1,38 +1,645 @@
-use std::marker::PhantomData;
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    io::{BufRead, BufReader, Read},
+    marker::PhantomData,
+    path::PathBuf,
+    sync::Arc,
+};
 
 use bevy::{
     asset::LoadContext,
     math::{UVec2, Vec2},
+    render::color::Color,
+    utils::HashSet,
 };
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::IndexMap;
 use serde::{
-    de::{DeserializeSeed, Visitor},
+    de::{DeserializeOwned, DeserializeSeed, IgnoredAny, Visitor},
     Deserialize,
 };
-use serde_value::ValueDeserializer;
+use serde_value::{DeserializerError, ValueDeserializer};
 
 use crate::{
-    animation::{Keyframes, LayoutAnimation},
-    node::Anchor,
+    animation::{Keyframes, LayoutAnimation, RawLayoutAnimations},
+    components::NodeKind,
     DynamicAttribute, LayoutRegistryInner,
 };
 
 use super::{
-    deserialize_animation::RawLayoutAnimationsSeed, GroupNodeData, Layout, LayoutNode,
-    LayoutNodeInner,
+    deserialize_animation::RawLayoutAnimationsSeed, resolve_node_path, BoundField, GroupNodeData,
+    Layout, LayoutError, LayoutErrorLocation, LayoutFormat, LayoutNode, LayoutNodeData,
+    LayoutNodeInner, NodeOverride, NodePathTarget, PrefabInstance, RepeatGrid, RepeatNodeData,
+    VariableBinding,
 };
 
-use super::helpers::{decl_ident_parse, decl_struct_parse};
+#[cfg(feature = "cooked")]
+use super::COOKED_LAYOUT_HEADER;
+
+use super::helpers::{decl_ident_parse, decl_struct_parse, FieldName};
 
-decl_ident_parse!(variant LayoutNode(Null, Image, Text, Layout, Group));
-decl_ident_parse!(field Layout(Resolution, CanvasSize, Nodes, Animations));
+decl_ident_parse!(variant LayoutNode(Null, Image, Text, Layout, Group, Prefab, Repeat));
+decl_ident_parse!(field Layout(Resolution, CanvasSize, Variables, Prefabs, Nodes, Animations));
 decl_ident_parse!(field Node(Id, Position, Size, Rotation, Anchor, Attributes, NodeKind, NodeData));
 decl_ident_parse!(field GroupNode(ChildAnchor, Nodes));
+decl_ident_parse!(field LayoutNodeData(Path, Overrides, Variables));
+decl_ident_parse!(field NodeOverride(Text, Image, Tint, Position, Attributes));
+decl_ident_parse!(field PrefabNodeData(Prefab, Variables));
+decl_ident_parse!(field RepeatNodeData(Count, Stride, Columns, RowStride, Template, Overrides));
+
+/// Shared state of the layout deserializer
+///
+/// Problems in nodes, attributes and animation targets don't stop the deserializer. They are
+/// collected here, along with where in the file they happened, and the offending node or
+/// attribute is skipped so that the rest of the file can still be checked.
+pub(crate) struct LayoutDeserializeContext<'a> {
+    pub(crate) registry: &'a LayoutRegistryInner,
+    /// Whether unregistered attributes and other problems are skipped instead of failing to load
+    /// the layout, from the registry unless the layout's load settings override it
+    pub(crate) ignore_unknown_registry_data: bool,
+    /// The file being read, if the format keeps track of how far into it the deserializer is
+    source: Option<&'a [u8]>,
+    offset: Cell<usize>,
+    /// The offset of the start of every line in `source`, built the first time a location is
+    /// needed
+    line_starts: OnceCell<Vec<usize>>,
+    node_path: RefCell<Vec<NodeFrame>>,
+    sibling_ids: RefCell<Vec<HashSet<String>>>,
+    animation: RefCell<Option<String>>,
+    animation_nodes: RefCell<Vec<(String, String, LayoutErrorLocation)>>,
+    failed_node_path: RefCell<Option<Utf8PathBuf>>,
+    /// The variables that have been declared so far, values can only reference variables that
+    /// are declared before them
+    variables: RefCell<IndexMap<String, serde_value::Value>>,
+    /// The prefabs that have been declared so far, along with the data that they are copied
+    /// from (or [`None`] if there was a problem with the prefab)
+    prefabs: RefCell<IndexMap<String, Option<serde_value::Value>>>,
+    errors: RefCell<Vec<LayoutError>>,
+}
+
+struct NodeFrame {
+    segment: String,
+    kind: Option<LayoutNodeVariantId>,
+}
+
+impl<'a> LayoutDeserializeContext<'a> {
+    fn new(
+        registry: &'a LayoutRegistryInner,
+        ignore_unknown_registry_data: bool,
+        source: Option<&'a [u8]>,
+    ) -> Self {
+        Self {
+            registry,
+            ignore_unknown_registry_data,
+            source,
+            offset: Cell::new(0),
+            line_starts: OnceCell::new(),
+            node_path: RefCell::new(vec![]),
+            sibling_ids: RefCell::new(vec![]),
+            animation: RefCell::new(None),
+            animation_nodes: RefCell::new(vec![]),
+            failed_node_path: RefCell::new(None),
+            variables: RefCell::new(IndexMap::new()),
+            prefabs: RefCell::new(IndexMap::new()),
+            errors: RefCell::new(vec![]),
+        }
+    }
+
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let Some(source) = self.source else {
+            return (0, 0);
+        };
+
+        let line_starts = self.line_starts.get_or_init(|| {
+            let newlines = source
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n');
+            std::iter::once(0)
+                .chain(newlines.map(|(pos, _)| pos + 1))
+                .collect()
+        });
+
+        let offset = offset.min(source.len());
+        // The first line starts at 0, so there is always at least one line before the offset
+        let line = line_starts.partition_point(|start| *start <= offset);
+        (line, offset - line_starts[line - 1] + 1)
+    }
+
+    fn node_path(&self) -> Option<Utf8PathBuf> {
+        let frames = self.node_path.borrow();
+        (!frames.is_empty()).then(|| frames.iter().map(|frame| frame.segment.as_str()).collect())
+    }
+
+    /// The location that the deserializer is currently at
+    pub(crate) fn location(&self) -> LayoutErrorLocation {
+        let (line, column) = self.line_column(self.offset.get());
+        LayoutErrorLocation {
+            node_path: self.node_path(),
+            line,
+            column,
+        }
+    }
+
+    pub(crate) fn push_node(&self, segment: String) {
+        self.node_path.borrow_mut().push(NodeFrame {
+            segment,
+            kind: None,
+        });
+    }
+
+    pub(crate) fn pop_node(&self) {
+        self.node_path.borrow_mut().pop();
+    }
+
+    pub(crate) fn animation(&self) -> String {
+        self.animation.borrow().clone().unwrap_or_default()
+    }
+
+    pub(crate) fn set_animation(&self, animation: Option<String>) {
+        *self.animation.borrow_mut() = animation;
+    }
+
+    /// Remembers where the keyframes of a node in the current animation are, so that the path
+    /// can be validated once all of the nodes are known
+    pub(crate) fn record_animation_node(&self, node: &str) {
+        let location = self.location();
+        self.animation_nodes
+            .borrow_mut()
+            .push((self.animation(), node.to_string(), location));
+    }
+
+    /// Removes the keyframes of animated nodes that do not exist in the layout
+    ///
+    /// Paths that lead into sublayouts can only be checked once the sublayouts are loaded, so
+    /// they are kept
+    fn validate_animation_nodes(&self, nodes: &[LayoutNode], animations: &mut RawLayoutAnimations) {
+        for (animation, node, location) in self.animation_nodes.take() {
+            if let NodePathTarget::Missing = resolve_node_path(nodes, Utf8Path::new(&node)) {
+                if let Some(animation) = animations.0.get_mut(&animation) {
+                    animation.nodes.remove(&node);
+                }
+
+                self.diagnose(LayoutError::UnknownAnimationNode {
+                    location,
+                    animation,
+                });
+            }
+        }
+    }
+
+    fn set_current_node_id(&self, id: &str) {
+        if let Some(frame) = self.node_path.borrow_mut().last_mut() {
+            frame.segment = id.to_string();
+        }
+    }
+
+    fn set_current_node_kind(&self, kind: LayoutNodeVariantId) {
+        if let Some(frame) = self.node_path.borrow_mut().last_mut() {
+            frame.kind = Some(kind);
+        }
+    }
+
+    fn current_node_kind(&self) -> Option<LayoutNodeVariantId> {
+        self.node_path.borrow().last().and_then(|frame| frame.kind)
+    }
+
+    /// Records the id of the current node in its set of siblings, returns `false` if a sibling
+    /// already has the same id
+    fn insert_sibling_id(&self, id: &str) -> bool {
+        let inserted = match self.sibling_ids.borrow_mut().last_mut() {
+            Some(siblings) => siblings.insert(id.to_string()),
+            None => true,
+        };
+
+        if inserted {
+            true
+        } else {
+            self.diagnose(LayoutError::DuplicateNodeId {
+                location: self.location(),
+                id: id.to_string(),
+            });
+            false
+        }
+    }
+
+    /// Remembers the innermost node that failed, for errors coming from the deserializer itself
+    fn mark_failed_node(&self) {
+        let mut failed = self.failed_node_path.borrow_mut();
+        if failed.is_none() {
+            *failed = self.node_path();
+        }
+    }
+
+    /// Records a problem found in the file, which doesn't stop the deserializer
+    pub(crate) fn diagnose(&self, error: LayoutError) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    /// Parses the identifier of a struct field, reporting it if it is unknown
+    pub(crate) fn field_id<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        location: &LayoutErrorLocation,
+    ) -> Option<T> {
+        match T::deserialize(ValueDeserializer::<DeserializerError>::new(
+            serde_value::Value::String(key.to_string()),
+        )) {
+            Ok(id) => Some(id),
+            Err(_) => {
+                self.diagnose(LayoutError::UnknownField {
+                    location: location.clone(),
+                    field: key.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Converts the value of a struct field, reporting it if it is invalid
+    fn convert_field<T: DeserializeOwned>(
+        &self,
+        location: &LayoutErrorLocation,
+        field: &str,
+        value: serde_value::Value,
+    ) -> Option<T> {
+        match T::deserialize(ValueDeserializer::<DeserializerError>::new(value)) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.diagnose(LayoutError::InvalidField {
+                    location: location.clone(),
+                    field: field.to_string(),
+                    error: Box::new(error),
+                });
+                None
+            }
+        }
+    }
 
-struct AttributeMapVisitor<'de>(&'de LayoutRegistryInner);
+    /// Replaces the variables referenced in `value` with the values that they were declared with
+    ///
+    /// Strings that look like a variable but don't name a declared one are kept as they are, so
+    /// text such as `"$5"` doesn't need to be escaped
+    pub(crate) fn resolve_variables(&self, value: serde_value::Value) -> serde_value::Value {
+        use serde_value::Value;
+
+        match value {
+            Value::String(string) if string.starts_with("$$") => {
+                Value::String(string[1..].to_string())
+            }
+            Value::String(string) => match self.variables.borrow().get(&string) {
+                Some(value) if is_variable_name(&string) => value.clone(),
+                _ => Value::String(string),
+            },
+            Value::Seq(values) => Value::Seq(
+                values
+                    .into_iter()
+                    .map(|value| self.resolve_variables(value))
+                    .collect(),
+            ),
+            Value::Map(map) => Value::Map(
+                map.into_iter()
+                    .map(|(key, value)| (key, self.resolve_variables(value)))
+                    .collect(),
+            ),
+            Value::Option(Some(value)) => {
+                Value::Option(Some(Box::new(self.resolve_variables(*value))))
+            }
+            Value::Newtype(value) => Value::Newtype(Box::new(self.resolve_variables(*value))),
+            value => value,
+        }
+    }
+
+    /// Whether `name` is a variable that the layout has declared so far
+    fn is_declared(&self, name: &str) -> bool {
+        self.variables.borrow().contains_key(name)
+    }
+
+    /// Gives variables other values, until the returned values are passed to
+    /// [`restore_variables`](Self::restore_variables)
+    fn shadow_variables(
+        &self,
+        values: &IndexMap<String, serde_value::Value>,
+    ) -> Vec<(String, Option<serde_value::Value>)> {
+        let mut variables = self.variables.borrow_mut();
+        values
+            .iter()
+            .map(|(name, value)| (name.clone(), variables.insert(name.clone(), value.clone())))
+            .collect()
+    }
+
+    fn restore_variables(&self, shadowed: Vec<(String, Option<serde_value::Value>)>) {
+        let mut variables = self.variables.borrow_mut();
+        for (name, value) in shadowed {
+            match value {
+                Some(value) => variables.insert(name, value),
+                None => variables.shift_remove(&name),
+            };
+        }
+    }
+
+    /// Converts the value of a struct field after replacing the variables it references
+    pub(crate) fn resolve_field<T: DeserializeOwned>(
+        &self,
+        location: &LayoutErrorLocation,
+        field: &str,
+        value: serde_value::Value,
+    ) -> Option<T> {
+        let value = self.resolve_variables(value);
+        self.convert_field(location, field, value)
+    }
+
+    /// Stores the value of a struct field, reporting it if the field was already set
+    ///
+    /// Returns `false` if there is no value to store or it is a duplicate
+    pub(crate) fn store_field<T>(
+        &self,
+        slot: &mut Option<T>,
+        value: Option<T>,
+        location: LayoutErrorLocation,
+        field: &str,
+    ) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+
+        if slot.is_some() {
+            self.diagnose(LayoutError::DuplicateField {
+                location,
+                field: field.to_string(),
+            });
+            return false;
+        }
+
+        *slot = Some(value);
+        true
+    }
+
+    /// Reports every required field that was never seen
+    fn check_required<T: PartialEq>(
+        &self,
+        location: &LayoutErrorLocation,
+        seen: &[T],
+        required: &[(T, &str)],
+    ) -> bool {
+        let mut valid = true;
+        for (field, name) in required {
+            if !seen.contains(field) {
+                self.diagnose(LayoutError::MissingField {
+                    location: location.clone(),
+                    field: name.to_string(),
+                });
+                valid = false;
+            }
+        }
+
+        valid
+    }
+
+    fn finish(self, result: Result<Layout, ParseError>) -> Result<Layout, LayoutError> {
+        let mut errors = self.errors.into_inner();
+
+        let layout = match result {
+            Ok(layout) => layout,
+            Err(error) => {
+                errors.push(error.into_layout_error(self.failed_node_path.into_inner()));
+                return Err(LayoutError::from_diagnostics(errors));
+            }
+        };
+
+        if errors.is_empty() {
+            Ok(layout)
+        } else if self.ignore_unknown_registry_data {
+            for error in errors {
+                log::warn!("Skipping part of layout: {error}");
+            }
+
+            Ok(layout)
+        } else {
+            Err(LayoutError::from_diagnostics(errors))
+        }
+    }
+}
+
+/// An error from the underlying format that stopped the deserializer
+enum ParseError {
+    Json(serde_json::Error),
+    #[cfg(feature = "ron")]
+    Ron(ron::error::SpannedError),
+    #[cfg(feature = "cooked")]
+    Cooked(rmp_serde::decode::Error),
+}
+
+impl ParseError {
+    fn into_layout_error(self, node_path: Option<Utf8PathBuf>) -> LayoutError {
+        match self {
+            Self::Json(error) => LayoutError::JSON {
+                location: LayoutErrorLocation {
+                    node_path,
+                    line: error.line(),
+                    column: error.column(),
+                },
+                error,
+            },
+            #[cfg(feature = "ron")]
+            Self::Ron(error) => LayoutError::RON {
+                location: LayoutErrorLocation {
+                    node_path,
+                    line: error.position.line,
+                    column: error.position.col,
+                },
+                error: Box::new(error),
+            },
+            #[cfg(feature = "cooked")]
+            Self::Cooked(error) => LayoutError::Cooked {
+                location: LayoutErrorLocation {
+                    node_path,
+                    line: 0,
+                    column: 0,
+                },
+                error,
+            },
+        }
+    }
+}
+
+/// Tracks how far into the source the JSON deserializer has read, so that errors which don't
+/// come from `serde_json` can still be given a line and column
+///
+/// `serde_json` reads one byte at a time, so the buffering happens in the reader below this one.
+/// Buffering above it would move the offset ahead of the deserializer by up to a whole buffer.
+struct TrackingReader<'a, R> {
+    reader: BufReader<R>,
+    offset: &'a Cell<usize>,
+}
+
+impl<R: Read> Read for TrackingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.reader.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.reader.consume(count);
+        self.offset.set(self.offset.get() + count);
+        Ok(count)
+    }
+}
+
+/// Whether `name` is the name of a variable
+///
+/// Variables start with `$`, strings that start with `$$` are escaped strings which start with a
+/// literal `$`
+fn is_variable_name(name: &str) -> bool {
+    name.len() > 1 && name.starts_with('$') && !name.starts_with("$$")
+}
+
+/// Records that `field` is bound to a variable, if `value` references a declared one
+fn binding(
+    context: &LayoutDeserializeContext,
+    field: BoundField,
+    value: &serde_value::Value,
+) -> Option<VariableBinding> {
+    match value {
+        serde_value::Value::String(variable)
+            if is_variable_name(variable) && context.is_declared(variable) =>
+        {
+            Some(VariableBinding {
+                field,
+                variable: variable.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Finds the fields of buffered node data that are bound to variables
+fn node_data_bindings(
+    context: &LayoutDeserializeContext,
+    value: &serde_value::Value,
+    fields: &[(&str, BoundField)],
+) -> Vec<VariableBinding> {
+    let serde_value::Value::Map(map) = value else {
+        return vec![];
+    };
+
+    fields
+        .iter()
+        .filter_map(|(name, field)| {
+            map.get(&serde_value::Value::String(name.to_string()))
+                .and_then(|value| binding(context, *field, value))
+        })
+        .collect()
+}
+
+/// Reads a map of variables, skipping the ones that had a problem
+///
+/// If `declare` is set, the variables can be referenced by the rest of the layout
+fn read_variables<'de, A>(
+    context: &LayoutDeserializeContext<'de>,
+    mut map: A,
+    declare: bool,
+) -> Result<IndexMap<String, serde_value::Value>, A::Error>
+where
+    A: serde::de::MapAccess<'de>,
+{
+    let mut variables = IndexMap::with_capacity(map.size_hint().unwrap_or_default());
+
+    while let Some(name) = map.next_key::<String>()? {
+        let location = context.location();
+        let value = map.next_value::<serde_value::Value>()?;
+
+        if !is_variable_name(&name) {
+            context.diagnose(LayoutError::InvalidVariableName {
+                location,
+                variable: name,
+            });
+            continue;
+        }
+
+        let value = context.resolve_variables(value);
+
+        if variables.contains_key(&name) {
+            context.diagnose(LayoutError::DuplicateField {
+                location,
+                field: name,
+            });
+            continue;
+        }
+
+        if declare {
+            context
+                .variables
+                .borrow_mut()
+                .insert(name.clone(), value.clone());
+        }
+
+        variables.insert(name, value);
+    }
+
+    Ok(variables)
+}
+
+/// Deserializes the variables that a layout declares
+struct VariablesSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for VariablesSeed<'de> {
+    type Value = IndexMap<String, serde_value::Value>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of variables")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        read_variables(self.0, map, true)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for VariablesSeed<'de> {
+    type Value = IndexMap<String, serde_value::Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+/// Deserializes the values that a sublayout or prefab node gives to variables
+struct VariableValuesSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for VariableValuesSeed<'de> {
+    type Value = IndexMap<String, serde_value::Value>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of variables")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        read_variables(self.0, map, false)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for VariableValuesSeed<'de> {
+    type Value = IndexMap<String, serde_value::Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+struct AttributeMapVisitor<'de>(&'de LayoutDeserializeContext<'de>);
 
 impl<'de> Visitor<'de> for AttributeMapVisitor<'de> {
-    type Value = Vec<DynamicAttribute>;
+    type Value = Vec<Arc<DynamicAttribute>>;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         formatter.write_str("map of LayoutNode attributes")
@@ -49,21 +656,31 @@ impl<'de> Visitor<'de> for AttributeMapVisitor<'de> {
         };
 
         while let Some(key) = map.next_key::<String>()? {
-            match self.0.attributes.get(key.as_str()) {
+            let location = self.0.location();
+            match self.0.registry.attributes.get(key.as_str()) {
                 Some(data) => {
                     let value = map.next_value::<serde_value::Value>()?;
-                    let value = (data.deserialize)(value)
-                        .map_err(<A::Error as serde::de::Error>::custom)?;
-                    list.push(value);
+                    let value = self.0.resolve_variables(value);
+
+                    match (data.deserialize)(value) {
+                        Ok(value) => list.push(Arc::new(value)),
+                        Err(error) => self.0.diagnose(LayoutError::InvalidAttribute {
+                            location,
+                            attribute: key,
+                            error: Box::new(error),
+                        }),
+                    }
                 }
                 None if self.0.ignore_unknown_registry_data => {
                     log::trace!("Ignoring unknown LayoutAttribute {key}");
-                    let _ = map.next_value::<serde_value::Value>()?;
+                    let _ = map.next_value::<IgnoredAny>()?;
                 }
                 None => {
-                    return Err(<A::Error as serde::de::Error>::custom(format!(
-                        "LayoutNode attribute '{key}' was not registered"
-                    )));
+                    self.0.diagnose(LayoutError::UnknownAttribute {
+                        location,
+                        attribute: key,
+                    });
+                    let _ = map.next_value::<IgnoredAny>()?;
                 }
             }
         }
@@ -72,10 +689,10 @@ impl<'de> Visitor<'de> for AttributeMapVisitor<'de> {
     }
 }
 
-struct AttributeDeserializer<'de>(&'de LayoutRegistryInner);
+struct AttributeDeserializer<'de>(&'de LayoutDeserializeContext<'de>);
 
 impl<'de> DeserializeSeed<'de> for AttributeDeserializer<'de> {
-    type Value = Vec<DynamicAttribute>;
+    type Value = Vec<Arc<DynamicAttribute>>;
 
     fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
@@ -85,36 +702,201 @@ impl<'de> DeserializeSeed<'de> for AttributeDeserializer<'de> {
     }
 }
 
-struct GroupNodeSeed<'de>(&'de LayoutRegistryInner);
+/// Deserializes the node data of a group, producing [`None`] if there was a problem with it
+struct GroupNodeSeed<'de>(&'de LayoutDeserializeContext<'de>);
 
 impl<'de> Visitor<'de> for GroupNodeSeed<'de> {
-    type Value = GroupNodeData;
+    type Value = Option<GroupNodeData>;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("struct GroupNodeData")
+        formatter.write_str("struct GroupNodeData or sequence of LayoutNode")
     }
 
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
     where
         A: serde::de::MapAccess<'de>,
     {
-        decl_struct_parse!(
-            self, GroupNodeFieldId, map;
-            (child_anchor => Anchor),
-            (passthrough nodes => NodeListSeed);
-            require(nodes);
-            default(child_anchor)
-        );
+        let start = self.0.location();
+        let mut valid = true;
+        let mut seen = vec![];
+        let mut child_anchor = None;
+        let mut nodes = None;
 
-        Ok(Self::Value {
-            child_anchor,
+        while let Some(FieldName(key)) = map.next_key()? {
+            let location = self.0.location();
+            let field = self.0.field_id::<GroupNodeFieldId>(&key, &location);
+            seen.extend(field);
+            valid &= match field {
+                None => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                    false
+                }
+                Some(GroupNodeFieldId::ChildAnchor) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut child_anchor, value, location, &key)
+                }
+                Some(GroupNodeFieldId::Nodes) => {
+                    let value = map.next_value_seed(NodeListSeed(self.0))?;
+                    self.0.store_field(&mut nodes, Some(value), location, &key)
+                }
+            };
+        }
+
+        valid &= self
+            .0
+            .check_required(&start, &seen, &[(GroupNodeFieldId::Nodes, "nodes")]);
+
+        let (true, Some(nodes)) = (valid, nodes) else {
+            return Ok(None);
+        };
+
+        Ok(Some(GroupNodeData {
+            child_anchor: child_anchor.unwrap_or_default(),
             nodes,
-        })
+            prefab: None,
+        }))
+    }
+
+    /// Groups can also be written as just their list of nodes
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        Ok(Some(GroupNodeData {
+            child_anchor: Default::default(),
+            nodes: NodeListSeed(self.0).visit_seq(seq)?,
+            prefab: None,
+        }))
     }
 }
 
 impl<'de> DeserializeSeed<'de> for GroupNodeSeed<'de> {
-    type Value = GroupNodeData;
+    type Value = Option<GroupNodeData>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+/// Deserializes a single override of a sublayout node, producing [`None`] if there was a
+/// problem with it
+struct NodeOverrideSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for NodeOverrideSeed<'de> {
+    type Value = Option<NodeOverride>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct NodeOverride")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut valid = true;
+        let mut text = None;
+        let mut image: Option<PathBuf> = None;
+        let mut tint = None;
+        let mut position: Option<Vec2> = None;
+        let mut attributes = None;
+
+        while let Some(FieldName(key)) = map.next_key()? {
+            let location = self.0.location();
+            valid &= match self.0.field_id::<NodeOverrideFieldId>(&key, &location) {
+                None => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                    false
+                }
+                Some(NodeOverrideFieldId::Text) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut text, value, location, &key)
+                }
+                Some(NodeOverrideFieldId::Image) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut image, value, location, &key)
+                }
+                Some(NodeOverrideFieldId::Tint) => {
+                    let value: Option<[f32; 4]> =
+                        self.0.resolve_field(&location, &key, map.next_value()?);
+                    let value = value.map(|[r, g, b, a]| Color::rgba(r, g, b, a));
+                    self.0.store_field(&mut tint, value, location, &key)
+                }
+                Some(NodeOverrideFieldId::Position) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut position, value, location, &key)
+                }
+                Some(NodeOverrideFieldId::Attributes) => {
+                    let value = map.next_value_seed(AttributeDeserializer(self.0))?;
+                    self.0
+                        .store_field(&mut attributes, Some(value), location, &key)
+                }
+            };
+        }
+
+        Ok(valid.then(|| NodeOverride {
+            text,
+            image,
+            tint,
+            position,
+            attributes: attributes.unwrap_or_default(),
+            image_handle: Default::default(),
+        }))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for NodeOverrideSeed<'de> {
+    type Value = Option<NodeOverride>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("NodeOverride", NodeOverrideFieldId::NAMES, self)
+    }
+}
+
+/// Deserializes the overrides of a sublayout, skipping the ones that had a problem
+struct NodeOverrideMapSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for NodeOverrideMapSeed<'de> {
+    type Value = IndexMap<Utf8PathBuf, NodeOverride>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of node paths to NodeOverride")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut overrides = IndexMap::with_capacity(map.size_hint().unwrap_or_default());
+
+        while let Some(path) = map.next_key::<String>()? {
+            let location = self.0.location();
+            let Some(node_override) = map.next_value_seed(NodeOverrideSeed(self.0))? else {
+                continue;
+            };
+
+            if overrides.contains_key(Utf8Path::new(&path)) {
+                self.0.diagnose(LayoutError::DuplicateField {
+                    location,
+                    field: path,
+                });
+                continue;
+            }
+
+            overrides.insert(Utf8PathBuf::from(path), node_override);
+        }
+
+        Ok(overrides)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for NodeOverrideMapSeed<'de> {
+    type Value = IndexMap<Utf8PathBuf, NodeOverride>;
 
     fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
@@ -124,92 +906,708 @@ impl<'de> DeserializeSeed<'de> for GroupNodeSeed<'de> {
     }
 }
 
-struct NodeSeed<'de>(&'de LayoutRegistryInner);
+/// Deserializes the node data of a sublayout, producing [`None`] if there was a problem with it
+struct LayoutNodeDataSeed<'de>(&'de LayoutDeserializeContext<'de>);
 
-impl<'de> Visitor<'de> for NodeSeed<'de> {
-    type Value = LayoutNode;
+impl<'de> Visitor<'de> for LayoutNodeDataSeed<'de> {
+    type Value = Option<LayoutNodeData>;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("struct LayoutNode")
+        formatter.write_str("struct LayoutNodeData")
     }
 
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
     where
         A: serde::de::MapAccess<'de>,
     {
-        decl_struct_parse!(
-            self, NodeFieldId, map;
-            (id => String),
-            (position => Vec2),
-            (size => Vec2),
-            (rotation => f32),
-            (anchor => Anchor),
-            (passthrough attributes => AttributeDeserializer),
-            (node_kind => LayoutNodeVariantId),
-            (node_data => serde_value::Value);
-            require(id, position, size, anchor, node_kind);
-            default(rotation, attributes)
+        let start = self.0.location();
+        let mut valid = true;
+        let mut seen = vec![];
+        let mut path = None;
+        let mut overrides = None;
+        let mut variables = None;
+
+        while let Some(FieldName(key)) = map.next_key()? {
+            let location = self.0.location();
+            let field = self.0.field_id::<LayoutNodeDataFieldId>(&key, &location);
+            seen.extend(field);
+            valid &= match field {
+                None => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                    false
+                }
+                Some(LayoutNodeDataFieldId::Path) => {
+                    let value = self.0.convert_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut path, value, location, &key)
+                }
+                Some(LayoutNodeDataFieldId::Overrides) => {
+                    let value = map.next_value_seed(NodeOverrideMapSeed(self.0))?;
+                    self.0
+                        .store_field(&mut overrides, Some(value), location, &key)
+                }
+                Some(LayoutNodeDataFieldId::Variables) => {
+                    let value = map.next_value_seed(VariableValuesSeed(self.0))?;
+                    self.0
+                        .store_field(&mut variables, Some(value), location, &key)
+                }
+            };
+        }
+
+        valid &= self
+            .0
+            .check_required(&start, &seen, &[(LayoutNodeDataFieldId::Path, "path")]);
+
+        let (true, Some(path)) = (valid, path) else {
+            return Ok(None);
+        };
+
+        Ok(Some(LayoutNodeData {
+            path,
+            overrides: overrides.unwrap_or_default(),
+            variables: variables.unwrap_or_default(),
+            handle: Default::default(),
+        }))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for LayoutNodeDataSeed<'de> {
+    type Value = Option<LayoutNodeData>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("LayoutNodeData", LayoutNodeDataFieldId::NAMES, self)
+    }
+}
+
+/// Deserializes the prefabs of a layout, checking each of them once as it is declared
+struct PrefabsSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for PrefabsSeed<'de> {
+    type Value = IndexMap<String, GroupNodeData>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of prefabs")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut prefabs = IndexMap::with_capacity(map.size_hint().unwrap_or_default());
+
+        while let Some(name) = map.next_key::<String>()? {
+            let location = self.0.location();
+            // Kept around so that every node that uses the prefab can be deserialized from it
+            let value = map.next_value::<serde_value::Value>()?;
+
+            if self.0.prefabs.borrow().contains_key(&name) {
+                self.0.diagnose(LayoutError::DuplicateField {
+                    location,
+                    field: name,
+                });
+                continue;
+            }
+
+            self.0.push_node(format!("prefabs/{name}"));
+            let group = GroupNodeSeed(self.0)
+                .deserialize(ValueDeserializer::<A::Error>::new(value.clone()));
+            if group.is_err() {
+                self.0.mark_failed_node();
+            }
+            self.0.pop_node();
+
+            let group = group?;
+            self.0
+                .prefabs
+                .borrow_mut()
+                .insert(name.clone(), group.is_some().then_some(value));
+            prefabs.extend(group.map(|group| (name, group)));
+        }
+
+        Ok(prefabs)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for PrefabsSeed<'de> {
+    type Value = IndexMap<String, GroupNodeData>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+/// Deserializes the node data of a prefab node, producing [`None`] if there was a problem with it
+struct PrefabNodeSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for PrefabNodeSeed<'de> {
+    type Value = Option<PrefabInstance>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct PrefabNodeData")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let start = self.0.location();
+        let mut valid = true;
+        let mut seen = vec![];
+        let mut prefab = None;
+        let mut variables: Option<IndexMap<String, serde_value::Value>> = None;
+
+        while let Some(FieldName(key)) = map.next_key()? {
+            let location = self.0.location();
+            let field = self.0.field_id::<PrefabNodeDataFieldId>(&key, &location);
+            seen.extend(field);
+            valid &= match field {
+                None => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                    false
+                }
+                Some(PrefabNodeDataFieldId::Prefab) => {
+                    let value = self.0.convert_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut prefab, value, location, &key)
+                }
+                Some(PrefabNodeDataFieldId::Variables) => {
+                    let value = map.next_value_seed(VariableValuesSeed(self.0))?;
+
+                    // The prefab can only use the variables that the layout declares
+                    let mut declared = true;
+                    for name in value.keys() {
+                        if !self.0.is_declared(name) {
+                            self.0.diagnose(LayoutError::UnknownVariable {
+                                location: location.clone(),
+                                variable: name.clone(),
+                            });
+                            declared = false;
+                        }
+                    }
+
+                    self.0
+                        .store_field(&mut variables, Some(value), location, &key)
+                        && declared
+                }
+            };
+        }
+
+        valid &= self
+            .0
+            .check_required(&start, &seen, &[(PrefabNodeDataFieldId::Prefab, "prefab")]);
+
+        let (true, Some(prefab)) = (valid, prefab) else {
+            return Ok(None);
+        };
+
+        Ok(Some(PrefabInstance {
+            prefab,
+            variables: variables.unwrap_or_default(),
+        }))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for PrefabNodeSeed<'de> {
+    type Value = Option<PrefabInstance>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("PrefabNodeData", PrefabNodeDataFieldId::NAMES, self)
+    }
+}
+
+/// Deserializes the node data of a repeated node, producing [`None`] if there was a problem with it
+struct RepeatNodeSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> Visitor<'de> for RepeatNodeSeed<'de> {
+    type Value = Option<RepeatNodeData>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct RepeatNodeData")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let start = self.0.location();
+        let mut valid = true;
+        let mut seen = vec![];
+        let mut count = None;
+        let mut stride = None;
+        let mut columns = None;
+        let mut row_stride = None;
+        let mut template = None;
+        let mut overrides = None;
+
+        while let Some(FieldName(key)) = map.next_key()? {
+            let location = self.0.location();
+            let field = self.0.field_id::<RepeatNodeDataFieldId>(&key, &location);
+            seen.extend(field);
+            valid &= match field {
+                None => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                    false
+                }
+                Some(RepeatNodeDataFieldId::Count) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut count, value, location, &key)
+                }
+                Some(RepeatNodeDataFieldId::Stride) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut stride, value, location, &key)
+                }
+                Some(RepeatNodeDataFieldId::Columns) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut columns, value, location, &key)
+                }
+                Some(RepeatNodeDataFieldId::RowStride) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut row_stride, value, location, &key)
+                }
+                Some(RepeatNodeDataFieldId::Template) => {
+                    // The template doesn't have any siblings, the ids of the items are unique
+                    self.0.sibling_ids.borrow_mut().push(HashSet::new());
+                    let value = map.next_value_seed(NodeSeed(self.0, 0));
+                    self.0.sibling_ids.borrow_mut().pop();
+                    self.0.store_field(&mut template, value?, location, &key)
+                }
+                Some(RepeatNodeDataFieldId::Overrides) => {
+                    let value = map.next_value_seed(NodeOverrideMapSeed(self.0))?;
+                    self.0
+                        .store_field(&mut overrides, Some(value), location, &key)
+                }
+            };
+        }
+
+        valid &= self.0.check_required(
+            &start,
+            &seen,
+            &[
+                (RepeatNodeDataFieldId::Count, "count"),
+                (RepeatNodeDataFieldId::Stride, "stride"),
+                (RepeatNodeDataFieldId::Template, "template"),
+            ],
         );
 
-        let inner = if node_kind == LayoutNodeVariantId::Null {
-            if node_data.is_some() {
-                return Err(<A::Error as serde::de::Error>::custom(
-                    "Null nodes do not have associated node_data",
-                ));
+        let (true, Some(count), Some(stride), Some(template)) = (valid, count, stride, template)
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(RepeatNodeData {
+            count,
+            grid: RepeatGrid {
+                stride,
+                columns: columns.unwrap_or_default(),
+                row_stride: row_stride.unwrap_or_default(),
+            },
+            template: Arc::new(template),
+            overrides: overrides.unwrap_or_default(),
+        }))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for RepeatNodeSeed<'de> {
+    type Value = Option<RepeatNodeData>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("RepeatNodeData", RepeatNodeDataFieldId::NAMES, self)
+    }
+}
+
+/// Removes the bindings of `nodes` to `variables`
+///
+/// The nodes of a prefab take the value that the prefab node gives to these variables, rather
+/// than following the value of the variable in the layouts that contain them.
+fn unbind_variables(nodes: &mut [LayoutNode], variables: &IndexMap<String, serde_value::Value>) {
+    for node in nodes.iter_mut() {
+        node.bindings
+            .retain(|binding| !variables.contains_key(&binding.variable));
+        match &mut node.inner {
+            LayoutNodeInner::Group(group) => unbind_variables(&mut group.nodes, variables),
+            LayoutNodeInner::Repeat(repeat) => {
+                if let Some(template) = Arc::get_mut(&mut repeat.template) {
+                    unbind_variables(std::slice::from_mut(template), variables);
+                }
             }
+            _ => {}
+        }
+    }
+}
 
-            LayoutNodeInner::Null
-        } else {
-            let Some(node_data) = node_data else {
-                return Err(<A::Error as serde::de::Error>::missing_field("node_data"));
+enum RawNodeData {
+    Group(GroupNodeData),
+    Layout(LayoutNodeData),
+    Prefab(PrefabInstance),
+    Repeat(RepeatNodeData),
+    Buffered(LayoutErrorLocation, serde_value::Value),
+}
+
+/// Reads the node_data of a node
+///
+/// If the kind of the node has already been read and it is a group, a sublayout, a prefab or a
+/// repeated node, the data is deserialized directly so that errors inside of it have accurate
+/// locations
+struct NodeDataSeed<'de>(&'de LayoutDeserializeContext<'de>);
+
+impl<'de> DeserializeSeed<'de> for NodeDataSeed<'de> {
+    type Value = Option<RawNodeData>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match self.0.current_node_kind() {
+            Some(LayoutNodeVariantId::Group) => Ok(GroupNodeSeed(self.0)
+                .deserialize(deserializer)?
+                .map(RawNodeData::Group)),
+            Some(LayoutNodeVariantId::Layout) => Ok(LayoutNodeDataSeed(self.0)
+                .deserialize(deserializer)?
+                .map(RawNodeData::Layout)),
+            Some(LayoutNodeVariantId::Prefab) => Ok(PrefabNodeSeed(self.0)
+                .deserialize(deserializer)?
+                .map(RawNodeData::Prefab)),
+            Some(LayoutNodeVariantId::Repeat) => Ok(RepeatNodeSeed(self.0)
+                .deserialize(deserializer)?
+                .map(RawNodeData::Repeat)),
+            _ => {
+                let location = self.0.location();
+                serde_value::Value::deserialize(deserializer)
+                    .map(|value| Some(RawNodeData::Buffered(location, value)))
+            }
+        }
+    }
+}
+
+/// Deserializes a single node, producing [`None`] if there was a problem with it
+struct NodeSeed<'de>(&'de LayoutDeserializeContext<'de>, usize);
+
+impl<'de> NodeSeed<'de> {
+    fn deserialize_node_data<T: DeserializeOwned>(
+        &self,
+        kind: NodeKind,
+        location: LayoutErrorLocation,
+        value: serde_value::Value,
+    ) -> Option<T> {
+        let value = self.0.resolve_variables(value);
+        match T::deserialize(ValueDeserializer::<DeserializerError>::new(value)) {
+            Ok(data) => Some(data),
+            Err(error) => {
+                self.0.diagnose(LayoutError::InvalidNodeData {
+                    location,
+                    kind,
+                    error: Box::new(error),
+                });
+                None
+            }
+        }
+    }
+
+    /// Copies the prefab that `instance` refers to into a group
+    fn instantiate_prefab<E: serde::de::Error>(
+        &self,
+        instance: PrefabInstance,
+        location: LayoutErrorLocation,
+    ) -> Result<Option<GroupNodeData>, E> {
+        let value = self.0.prefabs.borrow().get(&instance.prefab).cloned();
+        let value = match value {
+            Some(Some(value)) => value,
+            // The problem with the prefab has already been reported
+            Some(None) => return Ok(None),
+            None => {
+                self.0.diagnose(LayoutError::UnknownPrefab {
+                    location,
+                    prefab: instance.prefab,
+                });
+                return Ok(None);
+            }
+        };
+
+        let shadowed = self.0.shadow_variables(&instance.variables);
+        let group = GroupNodeSeed(self.0).deserialize(ValueDeserializer::<E>::new(value));
+        self.0.restore_variables(shadowed);
+
+        Ok(group?.map(|mut group| {
+            unbind_variables(&mut group.nodes, &instance.variables);
+            group.prefab = Some(instance);
+            group
+        }))
+    }
+
+    fn visit_node<A>(&self, mut map: A) -> Result<Option<LayoutNode>, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let start = self.0.location();
+        let mut valid = true;
+        let mut seen = vec![];
+
+        let mut id = None;
+        let mut position = None;
+        let mut size = None;
+        let mut rotation = None;
+        let mut anchor = None;
+        let mut attributes = None;
+        let mut node_kind = None;
+        let mut node_data = None;
+        let mut bindings = vec![];
+
+        while let Some(FieldName(key)) = map.next_key()? {
+            let location = self.0.location();
+            let field = self.0.field_id::<NodeFieldId>(&key, &location);
+            seen.extend(field);
+            valid &= match field {
+                None => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                    false
+                }
+                Some(NodeFieldId::Id) => {
+                    let value: Option<String> =
+                        self.0.convert_field(&location, &key, map.next_value()?);
+                    if let Some(value) = value.as_ref() {
+                        self.0.set_current_node_id(value);
+                    }
+
+                    let unique = value
+                        .as_ref()
+                        .is_none_or(|value| self.0.insert_sibling_id(value));
+
+                    self.0.store_field(&mut id, value, location, &key) && unique
+                }
+                Some(NodeFieldId::Position) => {
+                    let value = map.next_value::<serde_value::Value>()?;
+                    bindings.extend(binding(self.0, BoundField::Position, &value));
+                    let value = self.0.resolve_field(&location, &key, value);
+                    self.0.store_field(&mut position, value, location, &key)
+                }
+                Some(NodeFieldId::Size) => {
+                    let value = map.next_value::<serde_value::Value>()?;
+                    bindings.extend(binding(self.0, BoundField::Size, &value));
+                    let value = self.0.resolve_field(&location, &key, value);
+                    self.0.store_field(&mut size, value, location, &key)
+                }
+                Some(NodeFieldId::Rotation) => {
+                    let value = map.next_value::<serde_value::Value>()?;
+                    bindings.extend(binding(self.0, BoundField::Rotation, &value));
+                    let value = self.0.resolve_field(&location, &key, value);
+                    self.0.store_field(&mut rotation, value, location, &key)
+                }
+                Some(NodeFieldId::Anchor) => {
+                    let value = self.0.resolve_field(&location, &key, map.next_value()?);
+                    self.0.store_field(&mut anchor, value, location, &key)
+                }
+                Some(NodeFieldId::Attributes) => {
+                    let value = map.next_value_seed(AttributeDeserializer(self.0))?;
+                    self.0
+                        .store_field(&mut attributes, Some(value), location, &key)
+                }
+                Some(NodeFieldId::NodeKind) => {
+                    let value = self.0.convert_field(&location, &key, map.next_value()?);
+                    if let Some(kind) = value {
+                        self.0.set_current_node_kind(kind);
+                    }
+
+                    self.0.store_field(&mut node_kind, value, location, &key)
+                }
+                Some(NodeFieldId::NodeData) => {
+                    let value = map.next_value_seed(NodeDataSeed(self.0))?;
+                    self.0.store_field(&mut node_data, value, location, &key)
+                }
             };
+        }
 
-            match node_kind {
-                LayoutNodeVariantId::Image => LayoutNodeInner::Image(
-                    Deserialize::deserialize(ValueDeserializer::<A::Error>::new(node_data))
-                        .map_err(<A::Error as serde::de::Error>::custom)?,
-                ),
-                LayoutNodeVariantId::Text => LayoutNodeInner::Text(
-                    Deserialize::deserialize(ValueDeserializer::<A::Error>::new(node_data))
-                        .map_err(<A::Error as serde::de::Error>::custom)?,
-                ),
-                LayoutNodeVariantId::Layout => LayoutNodeInner::Layout(
-                    Deserialize::deserialize(ValueDeserializer::<A::Error>::new(node_data))
-                        .map_err(<A::Error as serde::de::Error>::custom)?,
-                ),
-                LayoutNodeVariantId::Group => LayoutNodeInner::Group(
-                    GroupNodeSeed(self.0)
-                        .deserialize(ValueDeserializer::<A::Error>::new(node_data))
-                        .map_err(<A::Error as serde::de::Error>::custom)?,
+        // The id might not have been known at the start of the node
+        let start = LayoutErrorLocation {
+            node_path: self.0.node_path(),
+            ..start
+        };
+
+        valid &= self.0.check_required(
+            &start,
+            &seen,
+            &[
+                (NodeFieldId::Id, "id"),
+                (NodeFieldId::Position, "position"),
+                (NodeFieldId::Size, "size"),
+                (NodeFieldId::Anchor, "anchor"),
+                (NodeFieldId::NodeKind, "node_kind"),
+            ],
+        );
+
+        let (true, Some(id), Some(position), Some(size), Some(anchor), Some(node_kind)) =
+            (valid, id, position, size, anchor, node_kind)
+        else {
+            return Ok(None);
+        };
+
+        let inner = match (node_kind, node_data) {
+            (LayoutNodeVariantId::Null, None) => LayoutNodeInner::Null,
+            (LayoutNodeVariantId::Null, Some(_)) => {
+                self.0
+                    .diagnose(LayoutError::UnexpectedNodeData { location: start });
+                return Ok(None);
+            }
+            (_, None) => {
+                self.0.diagnose(LayoutError::MissingField {
+                    location: start,
+                    field: "node_data".to_string(),
+                });
+                return Ok(None);
+            }
+            (LayoutNodeVariantId::Group, Some(RawNodeData::Group(group))) => {
+                LayoutNodeInner::Group(group)
+            }
+            (LayoutNodeVariantId::Group, Some(RawNodeData::Buffered(_, value))) => {
+                let Some(group) =
+                    GroupNodeSeed(self.0).deserialize(ValueDeserializer::<A::Error>::new(value))?
+                else {
+                    return Ok(None);
+                };
+
+                LayoutNodeInner::Group(group)
+            }
+            (LayoutNodeVariantId::Layout, Some(RawNodeData::Layout(data))) => {
+                LayoutNodeInner::Layout(data)
+            }
+            (LayoutNodeVariantId::Layout, Some(RawNodeData::Buffered(_, value))) => {
+                let Some(data) =
+                    LayoutNodeDataSeed(self.0)
+                        .deserialize(ValueDeserializer::<A::Error>::new(value))?
+                else {
+                    return Ok(None);
+                };
+
+                LayoutNodeInner::Layout(data)
+            }
+            (LayoutNodeVariantId::Prefab, Some(RawNodeData::Prefab(instance))) => {
+                let Some(group) = self.instantiate_prefab::<A::Error>(instance, start)? else {
+                    return Ok(None);
+                };
+
+                LayoutNodeInner::Group(group)
+            }
+            (LayoutNodeVariantId::Prefab, Some(RawNodeData::Buffered(_, value))) => {
+                let Some(instance) =
+                    PrefabNodeSeed(self.0)
+                        .deserialize(ValueDeserializer::<A::Error>::new(value))?
+                else {
+                    return Ok(None);
+                };
+
+                let Some(group) = self.instantiate_prefab::<A::Error>(instance, start)? else {
+                    return Ok(None);
+                };
+
+                LayoutNodeInner::Group(group)
+            }
+            (LayoutNodeVariantId::Repeat, Some(RawNodeData::Repeat(data))) => {
+                LayoutNodeInner::Repeat(data)
+            }
+            (LayoutNodeVariantId::Repeat, Some(RawNodeData::Buffered(_, value))) => {
+                let Some(data) = RepeatNodeSeed(self.0)
+                    .deserialize(ValueDeserializer::<A::Error>::new(value))?
+                else {
+                    return Ok(None);
+                };
+
+                LayoutNodeInner::Repeat(data)
+            }
+            (
+                _,
+                Some(
+                    RawNodeData::Group(_)
+                    | RawNodeData::Layout(_)
+                    | RawNodeData::Prefab(_)
+                    | RawNodeData::Repeat(_),
                 ),
-                LayoutNodeVariantId::Null => unreachable!(),
+            ) => unreachable!(),
+            (LayoutNodeVariantId::Image, Some(RawNodeData::Buffered(location, value))) => {
+                bindings.extend(node_data_bindings(
+                    self.0,
+                    &value,
+                    &[("tint", BoundField::Color)],
+                ));
+                let Some(data) = self.deserialize_node_data(NodeKind::Image, location, value)
+                else {
+                    return Ok(None);
+                };
+
+                LayoutNodeInner::Image(data)
+            }
+            (LayoutNodeVariantId::Text, Some(RawNodeData::Buffered(location, value))) => {
+                bindings.extend(node_data_bindings(
+                    self.0,
+                    &value,
+                    &[
+                        ("text", BoundField::Text),
+                        ("size", BoundField::TextSize),
+                        ("color", BoundField::Color),
+                    ],
+                ));
+                let Some(data) = self.deserialize_node_data(NodeKind::Text, location, value) else {
+                    return Ok(None);
+                };
+
+                LayoutNodeInner::Text(data)
             }
         };
 
-        Ok(Self::Value {
+        Ok(Some(LayoutNode {
             id,
             position,
             size,
-            rotation,
+            rotation: rotation.unwrap_or_default(),
             anchor,
             inner,
-            attributes,
-        })
+            attributes: attributes.unwrap_or_default(),
+            bindings,
+        }))
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'de> {
+    type Value = Option<LayoutNode>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct LayoutNode")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        self.0.push_node(format!("[{}]", self.1));
+        let result = self.visit_node(map);
+        if result.is_err() {
+            self.0.mark_failed_node();
+        }
+        self.0.pop_node();
+        result
     }
 }
 
 impl<'de> DeserializeSeed<'de> for NodeSeed<'de> {
-    type Value = LayoutNode;
+    type Value = Option<LayoutNode>;
 
     fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        deserializer.deserialize_map(self)
+        deserializer.deserialize_struct("LayoutNode", NodeFieldId::NAMES, self)
     }
 }
 
-struct NodeListSeed<'de>(&'de LayoutRegistryInner);
+struct NodeListSeed<'de>(&'de LayoutDeserializeContext<'de>);
 
 impl<'de> DeserializeSeed<'de> for NodeListSeed<'de> {
     type Value = Vec<LayoutNode>;
@@ -238,15 +1636,21 @@ impl<'de> Visitor<'de> for NodeListSeed<'de> {
             Vec::new()
         };
 
-        while let Some(next) = seq.next_element_seed(NodeSeed(self.0))? {
-            list.push(next);
+        self.0.sibling_ids.borrow_mut().push(HashSet::new());
+
+        let mut index = 0;
+        while let Some(next) = seq.next_element_seed(NodeSeed(self.0, index))? {
+            list.extend(next);
+            index += 1;
         }
 
+        self.0.sibling_ids.borrow_mut().pop();
+
         Ok(list)
     }
 }
 
-struct LayoutDeserializer<'de, 'a>(&'de LayoutRegistryInner, &'de mut LoadContext<'a>);
+struct LayoutDeserializer<'de, 'a>(&'de LayoutDeserializeContext<'de>, &'de mut LoadContext<'a>);
 
 impl<'de> Visitor<'de> for LayoutDeserializer<'de, '_> {
     type Value = Layout;
@@ -263,18 +1667,24 @@ impl<'de> Visitor<'de> for LayoutDeserializer<'de, '_> {
             self, LayoutFieldId, map;
             (canvas_size => UVec2),
             (resolution => Option<UVec2>),
+            (passthrough variables => VariablesSeed),
+            (passthrough prefabs => PrefabsSeed),
             (passthrough nodes => NodeListSeed),
             (passthrough animations => RawLayoutAnimationsSeed);
             require(canvas_size, nodes);
-            default(resolution, animations)
+            default(resolution, variables, prefabs, animations)
         );
 
+        let mut animations = animations;
+        self.0.validate_animation_nodes(&nodes, &mut animations);
+
         let mut handles = Vec::with_capacity(animations.0.len());
 
-        for (name, node_set) in animations.0 {
+        for (name, animation) in animations.0 {
             handles.push(self.1.labeled_asset_scope(name, move |_context| {
-                LayoutAnimation(
-                    node_set
+                LayoutAnimation {
+                    nodes: animation
+                        .nodes
                         .into_iter()
                         .map(|(node_id, keyframes)| {
                             (
@@ -283,15 +1693,21 @@ impl<'de> Visitor<'de> for LayoutDeserializer<'de, '_> {
                             )
                         })
                         .collect(),
-                )
+                    loop_mode: animation.loop_mode,
+                    priority: animation.priority,
+                    markers: animation.markers,
+                }
             }));
         }
 
         Ok(Self::Value {
             resolution,
             canvas_size,
+            variables,
+            prefabs,
             nodes,
             animations: handles,
+            load_settings: Default::default(),
         })
     }
 }
@@ -303,16 +1719,67 @@ impl<'de> DeserializeSeed<'de> for LayoutDeserializer<'de, '_> {
     where
         D: serde::Deserializer<'de>,
     {
-        deserializer.deserialize_map(self)
+        deserializer.deserialize_struct("Layout", LayoutFieldId::NAMES, self)
     }
 }
 
-pub(super) fn deserialize_layout<'a>(
-    data: &'a [u8],
-    registry: &'a LayoutRegistryInner,
-    context: &'a mut LoadContext,
-) -> Result<Layout, serde_json::Error> {
-    let mut deserializer = serde_json::Deserializer::from_slice(data);
+pub(super) fn deserialize_layout(
+    data: &[u8],
+    format: LayoutFormat,
+    registry: &LayoutRegistryInner,
+    ignore_unknown_registry_data: bool,
+    context: &mut LoadContext,
+) -> Result<Layout, LayoutError> {
+    match format {
+        LayoutFormat::Json => {
+            let de_context =
+                LayoutDeserializeContext::new(registry, ignore_unknown_registry_data, Some(data));
+            let result = {
+                let mut deserializer = serde_json::Deserializer::from_reader(TrackingReader {
+                    reader: BufReader::new(data),
+                    offset: &de_context.offset,
+                });
+
+                LayoutDeserializer(&de_context, context)
+                    .deserialize(&mut deserializer)
+                    .map_err(ParseError::Json)
+            };
+
+            de_context.finish(result)
+        }
+        #[cfg(feature = "ron")]
+        LayoutFormat::Ron => {
+            let de_context =
+                LayoutDeserializeContext::new(registry, ignore_unknown_registry_data, None);
+            // Optional fields can be written without `Some`, the same as they are in JSON
+            let options = ron::Options::default()
+                .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+            let result = match ron::Deserializer::from_bytes_with_options(data, options) {
+                Ok(mut deserializer) => LayoutDeserializer(&de_context, context)
+                    .deserialize(&mut deserializer)
+                    .and_then(|layout| deserializer.end().map(|_| layout))
+                    .map_err(|error| ParseError::Ron(deserializer.span_error(error))),
+                Err(error) => Err(ParseError::Ron(error)),
+            };
 
-    LayoutDeserializer(registry, context).deserialize(&mut deserializer)
+            de_context.finish(result)
+        }
+        #[cfg(feature = "cooked")]
+        LayoutFormat::Cooked => {
+            let Some(data) = data.strip_prefix(COOKED_LAYOUT_HEADER) else {
+                return Err(LayoutError::InvalidCookedHeader);
+            };
+
+            let de_context =
+                LayoutDeserializeContext::new(registry, ignore_unknown_registry_data, None);
+            let result = {
+                let mut deserializer = rmp_serde::Deserializer::new(data);
+                LayoutDeserializer(&de_context, context)
+                    .deserialize(&mut deserializer)
+                    .map_err(ParseError::Cooked)
+            };
+
+            de_context.finish(result)
+        }
+    }
 }
@@ -1,65 +1,8 @@
-use bevy::utils::HashMap;
-use serde::de::{DeserializeSeed, Visitor};
-use serde::Deserialize;
+use serde::de::{Deserialize, DeserializeSeed, Visitor};
 use std::marker::PhantomData;
 
 pub(crate) struct PhantomVisitor<T>(pub PhantomData<T>);
 
-pub(crate) struct HashMapSeedPassthrough<'de, K, T>(T, PhantomData<&'de K>);
-
-impl<'de, K, T: Clone> Clone for HashMapSeedPassthrough<'de, K, T> {
-    fn clone(&self) -> Self {
-        Self(self.0.clone(), PhantomData)
-    }
-}
-
-impl<'de, K, T: Copy> Copy for HashMapSeedPassthrough<'de, K, T> {}
-
-impl<'de, K, T> HashMapSeedPassthrough<'de, K, T> {
-    pub fn new(seed: T) -> Self {
-        Self(seed, PhantomData)
-    }
-}
-
-impl<'de, K, T> Visitor<'de> for HashMapSeedPassthrough<'de, K, T>
-where
-    K: Deserialize<'de> + PartialEq + Eq + std::hash::Hash,
-    T: DeserializeSeed<'de> + Copy,
-{
-    type Value = HashMap<K, <T as DeserializeSeed<'de>>::Value>;
-
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("map of values")
-    }
-
-    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
-    where
-        A: serde::de::MapAccess<'de>,
-    {
-        let mut output = HashMap::with_capacity(map.size_hint().unwrap_or_default());
-        while let Some(next) = map.next_key::<K>()? {
-            output.insert(next, map.next_value_seed(self.0)?);
-        }
-
-        Ok(output)
-    }
-}
-
-impl<'de, K, T> DeserializeSeed<'de> for HashMapSeedPassthrough<'de, K, T>
-where
-    K: Deserialize<'de> + PartialEq + Eq + std::hash::Hash,
-    T: DeserializeSeed<'de> + Copy,
-{
-    type Value = HashMap<K, <T as DeserializeSeed<'de>>::Value>;
-
-    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        deserializer.deserialize_map(self)
-    }
-}
-
 #[derive(Copy, Clone)]
 pub(crate) struct VecSeedPassthrough<'de, T: DeserializeSeed<'de> + Copy + 'de>(
     T,
@@ -105,10 +48,40 @@ impl<'de, T: DeserializeSeed<'de> + Copy + 'de> DeserializeSeed<'de>
     }
 }
 
+/// The name of a field in a struct that is deserialized by hand
+///
+/// Unlike a `String`, this is read with `Deserializer::deserialize_str`, which formats that tell
+/// apart identifiers and strings (such as RON) use for the names of struct fields.
+pub(crate) struct FieldName(pub String);
+
+impl<'de> Visitor<'de> for PhantomVisitor<FieldName> {
+    type Value = FieldName;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("field identifier")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(FieldName(v.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(PhantomVisitor(PhantomData::<Self>))
+    }
+}
+
 macro_rules! decl_ident_parse {
     (variant $ty:ident($($name:ident),*)) => {
         paste::paste! {
-            #[derive(PartialEq, Eq)]
+            #[derive(Copy, Clone, PartialEq, Eq)]
             enum [<$ty VariantId>] {
                 $($name),*
             }
@@ -148,11 +121,17 @@ macro_rules! decl_ident_parse {
     };
     (field $ty:ident($($name:ident),*)) => {
         paste::paste! {
-            #[derive(PartialEq, Eq)]
+            #[derive(Copy, Clone, PartialEq, Eq)]
             enum [<$ty FieldId>] {
                 $($name),*
             }
 
+            #[allow(dead_code)]
+            impl [<$ty FieldId>] {
+                /// The names of every field, for `Deserializer::deserialize_struct`
+                const NAMES: &'static [&'static str] = &[$(stringify!([<$name:snake>])),*];
+            }
+
             impl<'de> Visitor<'de> for super::helpers::PhantomVisitor<[<$ty FieldId>]> {
                 type Value = [<$ty FieldId>];
 
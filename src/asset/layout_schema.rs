@@ -0,0 +1,621 @@
+use std::{any::TypeId, path::PathBuf};
+
+use bevy::{
+    math::{IVec2, IVec3, IVec4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4},
+    reflect::{TypeInfo, TypeRegistry, Typed, VariantInfo},
+    render::color::Color,
+    text::JustifyText,
+};
+use serde_json::{json, Map, Value};
+
+use crate::{node::Anchor, LayoutRegistryInner};
+
+/// The kinds of nodes, along with the definition of their `node_data`
+const NODE_KINDS: &[(&str, Option<&str>)] = &[
+    ("Null", None),
+    ("Image", Some("ImageNodeData")),
+    ("Text", Some("TextNodeData")),
+    ("Layout", Some("LayoutNodeData")),
+    ("Group", Some("GroupNodeData")),
+    ("Prefab", Some("PrefabNodeData")),
+    ("Repeat", Some("RepeatNodeData")),
+];
+
+fn definition(name: &str) -> Value {
+    json!({ "$ref": format!("#/definitions/{name}") })
+}
+
+/// A value that can also be written as a reference to a variable
+fn bound(schema: Value) -> Value {
+    json!({ "anyOf": [schema, definition("VariableReference")] })
+}
+
+fn vector(len: usize, item: Value) -> Value {
+    json!({
+        "type": "array",
+        "items": item,
+        "minItems": len,
+        "maxItems": len,
+    })
+}
+
+/// The schema of the types that are written differently than their reflected shape
+///
+/// Vectors are written as arrays, and colors are written as `[r, g, b, a]` like everywhere else in
+/// a layout.
+fn builtin_schema(type_id: TypeId) -> Option<Value> {
+    let number = || json!({ "type": "number" });
+    let integer = || json!({ "type": "integer" });
+    let unsigned = || json!({ "type": "integer", "minimum": 0 });
+
+    let schema = match type_id {
+        id if id == TypeId::of::<bool>() => json!({ "type": "boolean" }),
+        id if id == TypeId::of::<f32>() || id == TypeId::of::<f64>() => number(),
+        id if [
+            TypeId::of::<u8>(),
+            TypeId::of::<u16>(),
+            TypeId::of::<u32>(),
+            TypeId::of::<u64>(),
+            TypeId::of::<u128>(),
+            TypeId::of::<usize>(),
+        ]
+        .contains(&id) =>
+        {
+            unsigned()
+        }
+        id if [
+            TypeId::of::<i8>(),
+            TypeId::of::<i16>(),
+            TypeId::of::<i32>(),
+            TypeId::of::<i64>(),
+            TypeId::of::<i128>(),
+            TypeId::of::<isize>(),
+        ]
+        .contains(&id) =>
+        {
+            integer()
+        }
+        id if id == TypeId::of::<char>() => {
+            json!({ "type": "string", "minLength": 1, "maxLength": 1 })
+        }
+        id if id == TypeId::of::<String>() || id == TypeId::of::<PathBuf>() => {
+            json!({ "type": "string" })
+        }
+        id if id == TypeId::of::<Vec2>() => definition("Vec2"),
+        id if id == TypeId::of::<Vec3>() => vector(3, number()),
+        id if id == TypeId::of::<Vec4>() => vector(4, number()),
+        id if id == TypeId::of::<IVec2>() => vector(2, integer()),
+        id if id == TypeId::of::<IVec3>() => vector(3, integer()),
+        id if id == TypeId::of::<IVec4>() => vector(4, integer()),
+        id if id == TypeId::of::<UVec2>() => definition("UVec2"),
+        id if id == TypeId::of::<UVec3>() => vector(3, unsigned()),
+        id if id == TypeId::of::<UVec4>() => vector(4, unsigned()),
+        id if id == TypeId::of::<Color>() => definition("Color"),
+        _ => return None,
+    };
+
+    Some(schema)
+}
+
+/// The name that a reflected type is stored under in the definitions of the schema
+fn definition_name(type_path: &str) -> String {
+    type_path
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | ':' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Builds the schemas of reflected types, storing structs and enums as definitions so that they
+/// can refer to themselves
+struct ReflectedSchemas<'a> {
+    types: &'a TypeRegistry,
+    definitions: Map<String, Value>,
+}
+
+impl ReflectedSchemas<'_> {
+    /// The schema of the type `type_id`, which accepts anything if the type has not been
+    /// registered with the type registry
+    fn type_schema(&mut self, type_id: TypeId, type_path: &str) -> Value {
+        if let Some(schema) = builtin_schema(type_id) {
+            return schema;
+        }
+
+        match self.types.get_type_info(type_id) {
+            Some(info) => self.info_schema(info),
+            None => {
+                json!({ "description": format!("{type_path} (not registered for reflection)") })
+            }
+        }
+    }
+
+    fn info_schema(&mut self, info: &'static TypeInfo) -> Value {
+        if let Some(schema) = builtin_schema(info.type_id()) {
+            return schema;
+        }
+
+        match info {
+            TypeInfo::Struct(_) | TypeInfo::TupleStruct(_) | TypeInfo::Enum(_) => {
+                let name = definition_name(info.type_path());
+                if !self.definitions.contains_key(&name) {
+                    // Reserve the name first, in case the type contains itself
+                    self.definitions.insert(name.clone(), Value::Null);
+                    let schema = self.compound_schema(info);
+                    self.definitions.insert(name.clone(), schema);
+                }
+
+                definition(&name)
+            }
+            TypeInfo::Tuple(info) => {
+                let items: Vec<Value> = info
+                    .iter()
+                    .map(|field| self.type_schema(field.type_id(), field.type_path()))
+                    .collect();
+                json!({
+                    "type": "array",
+                    "items": items,
+                    "minItems": items.len(),
+                    "maxItems": items.len(),
+                })
+            }
+            TypeInfo::List(info) => json!({
+                "type": "array",
+                "items": self.type_schema(info.item_type_id(), info.item_type_path_table().path()),
+            }),
+            TypeInfo::Array(info) => vector(
+                info.capacity(),
+                self.type_schema(info.item_type_id(), info.item_type_path_table().path()),
+            ),
+            TypeInfo::Map(info) => json!({
+                "type": "object",
+                "additionalProperties":
+                    self.type_schema(info.value_type_id(), info.value_type_path_table().path()),
+            }),
+            TypeInfo::Value(info) => json!({ "description": info.type_path() }),
+        }
+    }
+
+    /// The schema of a struct or enum, matching how serde writes them by default
+    ///
+    /// Fields are not marked as required, since `#[serde(default)]` can't be seen through
+    /// reflection.
+    fn compound_schema(&mut self, info: &'static TypeInfo) -> Value {
+        let mut schema = match info {
+            TypeInfo::Struct(info) if info.field_len() == 0 => {
+                json!({ "type": ["object", "null"] })
+            }
+            TypeInfo::Struct(info) => {
+                let properties: Map<String, Value> = info
+                    .iter()
+                    .map(|field| {
+                        let schema = self.type_schema(field.type_id(), field.type_path());
+                        (field.name().to_string(), schema)
+                    })
+                    .collect();
+                json!({ "type": "object", "properties": properties })
+            }
+            TypeInfo::TupleStruct(info) if info.field_len() == 1 => {
+                let field = info.field_at(0).unwrap();
+                self.type_schema(field.type_id(), field.type_path())
+            }
+            TypeInfo::TupleStruct(info) => {
+                let items: Vec<Value> = info
+                    .iter()
+                    .map(|field| self.type_schema(field.type_id(), field.type_path()))
+                    .collect();
+                json!({
+                    "type": "array",
+                    "items": items,
+                    "minItems": items.len(),
+                    "maxItems": items.len(),
+                })
+            }
+            // Options are written as their value, or `null`
+            TypeInfo::Enum(info) if info.type_path().starts_with("core::option::Option<") => {
+                match info.variant("Some") {
+                    Some(VariantInfo::Tuple(some)) => {
+                        let field = some.field_at(0).unwrap();
+                        json!({
+                            "anyOf": [
+                                { "type": "null" },
+                                self.type_schema(field.type_id(), field.type_path()),
+                            ]
+                        })
+                    }
+                    _ => json!({}),
+                }
+            }
+            TypeInfo::Enum(info) => {
+                let variants: Vec<Value> = info
+                    .iter()
+                    .map(|variant| {
+                        let content = match variant {
+                            VariantInfo::Unit(unit) => return json!({ "const": unit.name() }),
+                            VariantInfo::Tuple(tuple) if tuple.field_len() == 1 => {
+                                let field = tuple.field_at(0).unwrap();
+                                self.type_schema(field.type_id(), field.type_path())
+                            }
+                            VariantInfo::Tuple(tuple) => {
+                                let items: Vec<Value> = tuple
+                                    .iter()
+                                    .map(|field| {
+                                        self.type_schema(field.type_id(), field.type_path())
+                                    })
+                                    .collect();
+                                json!({
+                                    "type": "array",
+                                    "items": items,
+                                    "minItems": items.len(),
+                                    "maxItems": items.len(),
+                                })
+                            }
+                            VariantInfo::Struct(fields) => {
+                                let properties: Map<String, Value> = fields
+                                    .iter()
+                                    .map(|field| {
+                                        let schema =
+                                            self.type_schema(field.type_id(), field.type_path());
+                                        (field.name().to_string(), schema)
+                                    })
+                                    .collect();
+                                json!({ "type": "object", "properties": properties })
+                            }
+                        };
+
+                        json!({
+                            "type": "object",
+                            "properties": { variant.name(): content },
+                            "required": [variant.name()],
+                            "additionalProperties": false,
+                        })
+                    })
+                    .collect();
+                json!({ "oneOf": variants })
+            }
+            _ => unreachable!(),
+        };
+
+        if let Some(object) = schema.as_object_mut() {
+            object.insert("title".to_string(), json!(info.type_path()));
+        }
+
+        schema
+    }
+}
+
+/// The schema of a map of registered data, such as the attributes of a node
+///
+/// Unregistered entries are only accepted if they are ignored when loading layouts.
+fn registered_map(
+    registry: &LayoutRegistryInner,
+    schemas: &mut ReflectedSchemas,
+    entries: impl Iterator<Item = (String, TypeId, &'static str)>,
+) -> Value {
+    let mut properties: Vec<(String, Value)> = entries
+        .map(|(name, type_id, type_path)| (name, bound(schemas.type_schema(type_id, type_path))))
+        .collect();
+    properties.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    json!({
+        "type": "object",
+        "properties": properties.into_iter().collect::<Map<String, Value>>(),
+        "additionalProperties": registry.ignore_unknown_registry_data,
+    })
+}
+
+/// Builds a JSON schema (draft 7) of the `layout.json` format, including the payloads of every
+/// attribute and animation target in `registry`
+pub(super) fn layout_schema(registry: &LayoutRegistryInner, types: &TypeRegistry) -> Value {
+    let mut schemas = ReflectedSchemas {
+        types,
+        definitions: Map::new(),
+    };
+
+    let attributes = registered_map(
+        registry,
+        &mut schemas,
+        registry
+            .attributes
+            .iter()
+            .map(|(name, data)| (name.clone(), data.type_id, data.type_path)),
+    );
+
+    let targets = registered_map(
+        registry,
+        &mut schemas,
+        registry
+            .animations
+            .iter()
+            .map(|(name, data)| (name.clone(), data.type_id, data.type_path)),
+    );
+
+    let anchor = schemas.info_schema(Anchor::type_info());
+    let alignment = schemas.info_schema(JustifyText::type_info());
+
+    let node_data_rules: Vec<Value> = NODE_KINDS
+        .iter()
+        .map(|(kind, data)| {
+            let then = match data {
+                Some(data) => json!({
+                    "properties": { "node_data": definition(data) },
+                    "required": ["node_data"],
+                }),
+                None => json!({ "not": { "required": ["node_data"] } }),
+            };
+
+            json!({
+                "if": { "properties": { "node_kind": { "const": kind } } },
+                "then": then,
+            })
+        })
+        .collect();
+
+    let variables = json!({
+        "type": "object",
+        "propertyNames": { "pattern": "^\\$[^$]" },
+    });
+
+    let overrides = json!({
+        "type": "object",
+        "additionalProperties": definition("NodeOverride"),
+    });
+
+    let mut definitions = Map::new();
+    definitions.extend([
+        (
+            "VariableReference".to_string(),
+            json!({
+                "description": "The name of a variable declared by the layout",
+                "type": "string",
+                "pattern": "^\\$[^$]",
+            }),
+        ),
+        ("Vec2".to_string(), vector(2, json!({ "type": "number" }))),
+        (
+            "UVec2".to_string(),
+            vector(2, json!({ "type": "integer", "minimum": 0 })),
+        ),
+        (
+            "Color".to_string(),
+            json!({
+                "description": "An RGBA color",
+                "type": "array",
+                "items": { "type": "number" },
+                "minItems": 4,
+                "maxItems": 4,
+            }),
+        ),
+        (
+            "TimeBezierCurve".to_string(),
+            json!({
+                "oneOf": [
+                    { "const": "Linear" },
+                    {
+                        "type": "object",
+                        "properties": { "Quadratic": definition("Vec2") },
+                        "required": ["Quadratic"],
+                        "additionalProperties": false,
+                    },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "Cubic": {
+                                "type": "array",
+                                "items": [definition("Vec2"), definition("Vec2")],
+                                "minItems": 2,
+                                "maxItems": 2,
+                            }
+                        },
+                        "required": ["Cubic"],
+                        "additionalProperties": false,
+                    },
+                ]
+            }),
+        ),
+        ("Attributes".to_string(), attributes),
+        ("AnimationTargets".to_string(), targets),
+        (
+            "NodeOverride".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "text": bound(json!({ "type": "string" })),
+                    "image": bound(json!({ "type": "string" })),
+                    "tint": bound(definition("Color")),
+                    "position": bound(definition("Vec2")),
+                    "attributes": definition("Attributes"),
+                },
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "Node".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string" },
+                    "position": bound(definition("Vec2")),
+                    "size": bound(definition("Vec2")),
+                    "rotation": bound(json!({ "type": "number" })),
+                    "anchor": bound(anchor.clone()),
+                    "node_kind": {
+                        "enum": NODE_KINDS.iter().map(|(kind, _)| *kind).collect::<Vec<_>>(),
+                    },
+                    "node_data": {},
+                    "attributes": definition("Attributes"),
+                },
+                "required": ["id", "position", "size", "anchor", "node_kind"],
+                "additionalProperties": false,
+                "allOf": node_data_rules,
+            }),
+        ),
+        (
+            "ImageNodeData".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": bound(json!({ "type": "string" })),
+                    "tint": bound(definition("Color")),
+                },
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "TextNodeData".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "text": bound(json!({ "type": "string" })),
+                    "size": bound(json!({ "type": "number" })),
+                    "color": bound(definition("Color")),
+                    "font": bound(json!({ "type": "string" })),
+                    "alignment": bound(alignment),
+                },
+                "required": ["text", "size", "color"],
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "LayoutNodeData".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": bound(json!({ "type": "string" })),
+                    "overrides": overrides.clone(),
+                    "variables": variables.clone(),
+                },
+                "required": ["path"],
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "GroupNodeData".to_string(),
+            json!({
+                "oneOf": [
+                    { "type": "array", "items": definition("Node") },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "child_anchor": bound(anchor),
+                            "nodes": { "type": "array", "items": definition("Node") },
+                        },
+                        "required": ["nodes"],
+                        "additionalProperties": false,
+                    },
+                ]
+            }),
+        ),
+        (
+            "PrefabNodeData".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "prefab": { "type": "string" },
+                    "variables": variables.clone(),
+                },
+                "required": ["prefab"],
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "RepeatNodeData".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "count": bound(json!({ "type": "integer", "minimum": 0 })),
+                    "stride": bound(definition("Vec2")),
+                    "columns": bound(json!({ "type": ["integer", "null"], "minimum": 0 })),
+                    "row_stride": bound(definition("Vec2")),
+                    "template": definition("Node"),
+                    "overrides": overrides,
+                },
+                "required": ["count", "stride", "template"],
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "AnimationLoopMode".to_string(),
+            json!({
+                "description": "What the animation does when it reaches its end, unless it is played with another loop mode",
+                "oneOf": [
+                    { "enum": ["Once", "Loop", "PingPong"] },
+                    {
+                        "type": "object",
+                        "properties": { "Repeat": { "type": "integer", "minimum": 0 } },
+                        "required": ["Repeat"],
+                        "additionalProperties": false,
+                    },
+                ],
+            }),
+        ),
+        (
+            "AnimationMarker".to_string(),
+            json!({
+                "description": "A named point in the animation that sends an event when playback crosses it",
+                "type": "object",
+                "properties": {
+                    "timestamp_ms": { "type": "integer", "minimum": 0 },
+                    "name": { "type": "string" },
+                },
+                "required": ["timestamp_ms", "name"],
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "Keyframe".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "timestamp_ms": { "type": "integer", "minimum": 0 },
+                    "time_scale": definition("TimeBezierCurve"),
+                    "targets": definition("AnimationTargets"),
+                },
+                "required": ["timestamp_ms", "targets"],
+                "additionalProperties": false,
+            }),
+        ),
+    ]);
+
+    // The reflected types of the attributes and animation targets
+    definitions.extend(schemas.definitions);
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "yabuil layout",
+        "type": "object",
+        "properties": {
+            "resolution": definition("UVec2"),
+            "canvas_size": definition("UVec2"),
+            "variables": variables,
+            "prefabs": {
+                "type": "object",
+                "additionalProperties": definition("GroupNodeData"),
+            },
+            "nodes": { "type": "array", "items": definition("Node") },
+            "animations": {
+                "description": "Animations keyed by their name, then by the path of the node they animate",
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "properties": {
+                        "@settings": {
+                            "type": "object",
+                            "properties": {
+                                "loop_mode": definition("AnimationLoopMode"),
+                                "priority": { "type": "integer" },
+                                "events": { "type": "array", "items": definition("AnimationMarker") },
+                            },
+                            "additionalProperties": false,
+                        },
+                    },
+                    "additionalProperties": { "type": "array", "items": definition("Keyframe") },
+                },
+            },
+        },
+        "required": ["canvas_size", "nodes"],
+        "additionalProperties": false,
+        "definitions": definitions,
+    })
+}
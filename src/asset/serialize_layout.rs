@@ -0,0 +1,548 @@
+use std::{borrow::Cow, collections::BTreeMap, path::Path, sync::Arc};
+
+use bevy::{
+    math::{UVec2, Vec2},
+    render::color::Color,
+    text::JustifyText,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::IndexMap;
+use serde::{ser::SerializeMap, Serialize};
+use serde_json::ser::PrettyFormatter;
+
+use crate::{
+    animation::{AnimationLoopMode, AnimationMarker, Keyframes, LayoutAnimation, TimeBezierCurve},
+    node::Anchor,
+    DynamicAttribute, LayoutRegistryInner,
+};
+
+use super::{
+    deserialize_animation::SETTINGS_KEY, BoundField, GroupNodeData, ImageNodeData, Layout,
+    LayoutNode, LayoutNodeInner, LayoutSerializeError, NodeOverride, TextNodeData,
+};
+
+/// Borrowed mirror of the `layout.json` format, in the order that the fields are written out
+#[derive(Serialize)]
+struct SerializedLayout<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolution: Option<UVec2>,
+    canvas_size: UVec2,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    variables: IndexMap<&'a str, serde_value::Value>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    prefabs: IndexMap<&'a str, SerializedNodeData<'a>>,
+    nodes: Vec<SerializedNode<'a>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    animations: BTreeMap<&'a str, SerializedAnimation<'a>>,
+}
+
+/// The keyframes of every node in an animation, with the settings of the animation written
+/// next to them
+struct SerializedAnimation<'a> {
+    settings: SerializedAnimationSettings<'a>,
+    nodes: BTreeMap<&'a str, Vec<SerializedKeyframe<'a>>>,
+}
+
+/// The settings of an animation, leaving out the ones that have their default value
+#[derive(Serialize)]
+struct SerializedAnimationSettings<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    loop_mode: Option<AnimationLoopMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    events: Option<&'a [AnimationMarker]>,
+}
+
+impl SerializedAnimationSettings<'_> {
+    fn is_empty(&self) -> bool {
+        self.loop_mode.is_none() && self.priority.is_none() && self.events.is_none()
+    }
+}
+
+impl Serialize for SerializedAnimation<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let has_settings = !self.settings.is_empty();
+        let len = self.nodes.len() + usize::from(has_settings);
+        let mut map = serializer.serialize_map(Some(len))?;
+        if has_settings {
+            map.serialize_entry(SETTINGS_KEY, &self.settings)?;
+        }
+
+        for (node, keyframes) in self.nodes.iter() {
+            map.serialize_entry(node, keyframes)?;
+        }
+
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct SerializedNode<'a> {
+    id: &'a str,
+    position: Bound<'a, Vec2>,
+    size: Bound<'a, Vec2>,
+    #[serde(skip_serializing_if = "Bound::is_zero")]
+    rotation: Bound<'a, f32>,
+    anchor: Anchor,
+    node_kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node_data: Option<SerializedNodeData<'a>>,
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_entries"
+    )]
+    attributes: Vec<(&'a str, serde_value::Value)>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SerializedNodeData<'a> {
+    Image {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<Cow<'a, str>>,
+        #[serde(skip_serializing_if = "Bound::is_none")]
+        tint: Bound<'a, Option<[f32; 4]>>,
+    },
+    Text {
+        text: Bound<'a, Cow<'a, str>>,
+        size: Bound<'a, f32>,
+        color: Bound<'a, [f32; 4]>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        font: Option<Cow<'a, str>>,
+        alignment: JustifyText,
+    },
+    Layout {
+        path: &'a Path,
+        #[serde(skip_serializing_if = "IndexMap::is_empty")]
+        overrides: IndexMap<&'a str, SerializedOverride<'a>>,
+        #[serde(skip_serializing_if = "IndexMap::is_empty")]
+        variables: IndexMap<&'a str, serde_value::Value>,
+    },
+    Group {
+        child_anchor: Anchor,
+        nodes: Vec<SerializedNode<'a>>,
+    },
+    Prefab {
+        prefab: &'a str,
+        #[serde(skip_serializing_if = "IndexMap::is_empty")]
+        variables: IndexMap<&'a str, serde_value::Value>,
+    },
+    Repeat {
+        count: usize,
+        stride: Vec2,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<usize>,
+        #[serde(skip_serializing_if = "is_zero")]
+        row_stride: Vec2,
+        template: Box<SerializedNode<'a>>,
+        #[serde(skip_serializing_if = "IndexMap::is_empty")]
+        overrides: IndexMap<&'a str, SerializedOverride<'a>>,
+    },
+}
+
+#[derive(Serialize)]
+struct SerializedOverride<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<Cow<'a, str>>,
+    #[serde(
+        serialize_with = "super::serialize_color_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    tint: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<Vec2>,
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_entries"
+    )]
+    attributes: Vec<(&'a str, serde_value::Value)>,
+}
+
+#[derive(Serialize)]
+struct SerializedKeyframe<'a> {
+    timestamp_ms: usize,
+    #[serde(skip_serializing_if = "is_linear")]
+    time_scale: TimeBezierCurve,
+    #[serde(serialize_with = "serialize_entries")]
+    targets: Vec<(&'a str, serde_value::Value)>,
+}
+
+/// A field that is written as the name of a variable, if the node binds it to one
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Bound<'a, T> {
+    Variable(&'a str),
+    Value(T),
+}
+
+impl<'a, T> Bound<'a, T> {
+    fn new(node: &'a LayoutNode, field: BoundField, value: T) -> Self {
+        match node.binding(field) {
+            Some(variable) => Self::Variable(variable),
+            None => Self::Value(value),
+        }
+    }
+}
+
+impl Bound<'_, f32> {
+    fn is_zero(&self) -> bool {
+        matches!(self, Self::Value(value) if *value == 0.0)
+    }
+}
+
+impl<T> Bound<'_, Option<T>> {
+    fn is_none(&self) -> bool {
+        matches!(self, Self::Value(None))
+    }
+}
+
+/// Escapes a string that starts with `$`, so that it isn't read back as a variable
+fn escape_str(string: &str) -> Cow<'_, str> {
+    if string.starts_with('$') {
+        Cow::Owned(format!("${string}"))
+    } else {
+        Cow::Borrowed(string)
+    }
+}
+
+fn escape_path(path: &Path) -> Cow<'_, str> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(path) => escape_str(path),
+        Cow::Owned(path) => Cow::Owned(escape_str(&path).into_owned()),
+    }
+}
+
+/// Escapes every string in `value` that starts with `$`, see [`escape_str`]
+fn escape_value(value: serde_value::Value) -> serde_value::Value {
+    use serde_value::Value;
+
+    match value {
+        Value::String(string) if string.starts_with('$') => Value::String(format!("${string}")),
+        Value::Seq(values) => Value::Seq(values.into_iter().map(escape_value).collect()),
+        Value::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(key, value)| (key, escape_value(value)))
+                .collect(),
+        ),
+        Value::Option(Some(value)) => Value::Option(Some(Box::new(escape_value(*value)))),
+        Value::Newtype(value) => Value::Newtype(Box::new(escape_value(*value))),
+        value => value,
+    }
+}
+
+fn serialize_variables(
+    variables: &IndexMap<String, serde_value::Value>,
+) -> IndexMap<&str, serde_value::Value> {
+    variables
+        .iter()
+        .map(|(name, value)| (name.as_str(), escape_value(value.clone())))
+        .collect()
+}
+
+fn is_zero(vector: &Vec2) -> bool {
+    *vector == Vec2::ZERO
+}
+
+fn is_linear(curve: &TimeBezierCurve) -> bool {
+    *curve == TimeBezierCurve::Linear
+}
+
+/// Serializes a list of named entries as a map, keeping their order
+fn serialize_entries<S: serde::Serializer>(
+    entries: &[(&str, serde_value::Value)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(entries.iter().map(|(name, value)| (name, value)))
+}
+
+fn serialize_attributes<'a>(
+    registry: &LayoutRegistryInner,
+    node_path: &Utf8Path,
+    attributes: &'a [Arc<DynamicAttribute>],
+) -> Result<Vec<(&'a str, serde_value::Value)>, LayoutSerializeError> {
+    attributes
+        .iter()
+        .map(|attribute| {
+            let Some(data) = registry.attributes.get(attribute.name()) else {
+                return Err(LayoutSerializeError::UnknownAttribute {
+                    node_path: node_path.to_path_buf(),
+                    attribute: attribute.name().to_string(),
+                });
+            };
+
+            (data.serialize)(attribute)
+                .map(|value| (attribute.name(), escape_value(value)))
+                .map_err(|error| LayoutSerializeError::InvalidAttribute {
+                    node_path: node_path.to_path_buf(),
+                    attribute: attribute.name().to_string(),
+                    error: Box::new(error),
+                })
+        })
+        .collect()
+}
+
+/// Serializes the overrides of a sublayout or repeated node, attributes are reported with the
+/// path of the node that they override
+fn serialize_overrides<'a>(
+    registry: &LayoutRegistryInner,
+    node_path: &Utf8Path,
+    overrides: &'a IndexMap<Utf8PathBuf, NodeOverride>,
+) -> Result<IndexMap<&'a str, SerializedOverride<'a>>, LayoutSerializeError> {
+    overrides
+        .iter()
+        .map(|(path, node_override)| {
+            let attributes =
+                serialize_attributes(registry, &node_path.join(path), &node_override.attributes)?;
+
+            Ok((
+                path.as_str(),
+                SerializedOverride {
+                    text: node_override.text.as_deref().map(escape_str),
+                    image: node_override.image.as_deref().map(escape_path),
+                    tint: node_override.tint,
+                    position: node_override.position,
+                    attributes,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn serialize_image<'a>(node: &'a LayoutNode, data: &'a ImageNodeData) -> SerializedNodeData<'a> {
+    SerializedNodeData::Image {
+        path: data.path.as_deref().map(escape_path),
+        tint: Bound::new(
+            node,
+            BoundField::Color,
+            data.tint.map(|tint| tint.as_rgba_f32()),
+        ),
+    }
+}
+
+fn serialize_text<'a>(node: &'a LayoutNode, data: &'a TextNodeData) -> SerializedNodeData<'a> {
+    SerializedNodeData::Text {
+        text: Bound::new(node, BoundField::Text, escape_str(&data.text)),
+        size: Bound::new(node, BoundField::TextSize, data.size),
+        color: Bound::new(node, BoundField::Color, data.color.as_rgba_f32()),
+        font: data.font.as_deref().map(escape_path),
+        alignment: data.alignment,
+    }
+}
+
+fn serialize_node<'a>(
+    registry: &LayoutRegistryInner,
+    parent: &Utf8Path,
+    node: &'a LayoutNode,
+) -> Result<SerializedNode<'a>, LayoutSerializeError> {
+    let node_path = parent.join(&node.id);
+    let attributes = serialize_attributes(registry, &node_path, &node.attributes)?;
+
+    let (node_kind, node_data) = match &node.inner {
+        LayoutNodeInner::Null => ("Null", None),
+        LayoutNodeInner::Image(data) => ("Image", Some(serialize_image(node, data))),
+        LayoutNodeInner::Text(data) => ("Text", Some(serialize_text(node, data))),
+        LayoutNodeInner::Layout(data) => (
+            "Layout",
+            Some(SerializedNodeData::Layout {
+                path: &data.path,
+                overrides: serialize_overrides(registry, &node_path, &data.overrides)?,
+                variables: serialize_variables(&data.variables),
+            }),
+        ),
+        LayoutNodeInner::Group(group) => match &group.prefab {
+            Some(instance) => (
+                "Prefab",
+                Some(SerializedNodeData::Prefab {
+                    prefab: &instance.prefab,
+                    variables: serialize_variables(&instance.variables),
+                }),
+            ),
+            None => ("Group", Some(serialize_group(registry, &node_path, group)?)),
+        },
+        LayoutNodeInner::Repeat(data) => (
+            "Repeat",
+            Some(SerializedNodeData::Repeat {
+                count: data.count,
+                stride: data.grid.stride,
+                columns: data.grid.columns,
+                row_stride: data.grid.row_stride,
+                template: Box::new(serialize_node(registry, &node_path, &data.template)?),
+                overrides: serialize_overrides(registry, &node_path, &data.overrides)?,
+            }),
+        ),
+    };
+
+    Ok(SerializedNode {
+        id: &node.id,
+        position: Bound::new(node, BoundField::Position, node.position),
+        size: Bound::new(node, BoundField::Size, node.size),
+        rotation: Bound::new(node, BoundField::Rotation, node.rotation),
+        anchor: node.anchor,
+        node_kind,
+        node_data,
+        attributes,
+    })
+}
+
+fn serialize_group<'a>(
+    registry: &LayoutRegistryInner,
+    node_path: &Utf8Path,
+    group: &'a GroupNodeData,
+) -> Result<SerializedNodeData<'a>, LayoutSerializeError> {
+    Ok(SerializedNodeData::Group {
+        child_anchor: group.child_anchor,
+        nodes: serialize_nodes(registry, node_path, &group.nodes)?,
+    })
+}
+
+fn serialize_nodes<'a>(
+    registry: &LayoutRegistryInner,
+    parent: &Utf8Path,
+    nodes: &'a [LayoutNode],
+) -> Result<Vec<SerializedNode<'a>>, LayoutSerializeError> {
+    nodes
+        .iter()
+        .map(|node| serialize_node(registry, parent, node))
+        .collect()
+}
+
+/// Turns the channels of an animated node back into the keyframes that they were flattened from
+///
+/// Targets that share a timestamp and time scale are merged into the same keyframe.
+fn serialize_keyframes<'a>(
+    registry: &LayoutRegistryInner,
+    animation: &str,
+    keyframes: &'a Keyframes,
+) -> Result<Vec<SerializedKeyframe<'a>>, LayoutSerializeError> {
+    let mut output: Vec<SerializedKeyframe<'a>> = vec![];
+
+    for keyframe in keyframes
+        .channels()
+        .iter()
+        .flat_map(|channel| channel.keyframes.iter())
+    {
+        let target = &keyframe.target;
+        let Some(data) = registry.animations.get(target.name()) else {
+            return Err(LayoutSerializeError::UnknownAnimationTarget {
+                animation: animation.to_string(),
+                target: target.name().to_string(),
+            });
+        };
+
+        let value = (data.serialize)(target)
+            .map(escape_value)
+            .map_err(|error| LayoutSerializeError::InvalidAnimationTarget {
+                animation: animation.to_string(),
+                target: target.name().to_string(),
+                error: Box::new(error),
+            })?;
+
+        match output.iter_mut().find(|existing| {
+            existing.timestamp_ms == keyframe.timestamp_ms
+                && existing.time_scale == keyframe.time_scale
+        }) {
+            Some(existing) => existing.targets.push((target.name(), value)),
+            None => output.push(SerializedKeyframe {
+                timestamp_ms: keyframe.timestamp_ms,
+                time_scale: keyframe.time_scale,
+                targets: vec![(target.name(), value)],
+            }),
+        }
+    }
+
+    // Channels are stored in no particular order, so sort everything to keep the output stable
+    output.sort_by_key(|keyframe| keyframe.timestamp_ms);
+    for keyframe in output.iter_mut() {
+        keyframe.targets.sort_by_key(|(name, _)| *name);
+    }
+
+    Ok(output)
+}
+
+fn serialized_layout<'a>(
+    layout: &'a Layout,
+    registry: &LayoutRegistryInner,
+    animations: &[(&'a str, &'a LayoutAnimation)],
+) -> Result<SerializedLayout<'a>, LayoutSerializeError> {
+    let mut serialized_animations = BTreeMap::new();
+    for (name, animation) in animations.iter().copied() {
+        let mut nodes = BTreeMap::new();
+        for (node, keyframes) in animation.iter() {
+            nodes.insert(
+                node.as_str(),
+                serialize_keyframes(registry, name, keyframes)?,
+            );
+        }
+
+        serialized_animations.insert(
+            name,
+            SerializedAnimation {
+                settings: SerializedAnimationSettings {
+                    loop_mode: Some(animation.loop_mode())
+                        .filter(|mode| *mode != AnimationLoopMode::Once),
+                    priority: Some(animation.priority()).filter(|priority| *priority != 0),
+                    events: Some(animation.markers()).filter(|markers| !markers.is_empty()),
+                },
+                nodes,
+            },
+        );
+    }
+
+    Ok(SerializedLayout {
+        resolution: layout.resolution,
+        canvas_size: layout.canvas_size,
+        variables: serialize_variables(&layout.variables),
+        prefabs: layout
+            .prefabs
+            .iter()
+            .map(|(name, group)| {
+                let node_path = Utf8Path::new("prefabs").join(name);
+                Ok((name.as_str(), serialize_group(registry, &node_path, group)?))
+            })
+            .collect::<Result<_, LayoutSerializeError>>()?,
+        nodes: serialize_nodes(registry, Utf8Path::new(""), &layout.nodes)?,
+        animations: serialized_animations,
+    })
+}
+
+/// Serializes a layout into the same format that [`LayoutLoader`](super::LayoutLoader) reads
+///
+/// `animations` are the loaded animations of the layout, along with the names they are
+/// stored under
+pub(super) fn serialize_json(
+    layout: &Layout,
+    registry: &LayoutRegistryInner,
+    animations: &[(&str, &LayoutAnimation)],
+) -> Result<String, LayoutSerializeError> {
+    let layout = serialized_layout(layout, registry, animations)?;
+
+    // Match the indentation that the layout files are written with
+    let mut output = vec![];
+    let mut serializer =
+        serde_json::Serializer::with_formatter(&mut output, PrettyFormatter::with_indent(b"    "));
+    layout.serialize(&mut serializer)?;
+
+    Ok(String::from_utf8(output).expect("serde_json should only write UTF-8"))
+}
+
+/// Serializes a layout into the format that [`CookedLayoutLoader`](super::CookedLayoutLoader)
+/// reads
+///
+/// This is MessagePack with named fields, so that it can be read with the same deserializers as
+/// the text formats
+#[cfg(feature = "cooked")]
+pub(super) fn serialize_cooked(
+    layout: &Layout,
+    registry: &LayoutRegistryInner,
+    animations: &[(&str, &LayoutAnimation)],
+) -> Result<Vec<u8>, LayoutSerializeError> {
+    let layout = serialized_layout(layout, registry, animations)?;
+
+    let mut output = super::COOKED_LAYOUT_HEADER.to_vec();
+    layout.serialize(&mut rmp_serde::Serializer::new(&mut output).with_struct_map())?;
+
+    Ok(output)
+}
@@ -1,26 +1,55 @@
+use std::ops::{Add, Mul};
+
 use bevy::prelude::*;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     animation::{LayoutAnimationTarget, ResourceRestrictedWorld},
+    asset::{deserialize_color, serialize_color},
     node::Node,
     views::NodeMut,
 };
 
-fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
-    let [r, g, b, a] = <[f32; 4]>::deserialize(deserializer)?;
-
-    Ok(Color::rgba(r, g, b, a))
-}
-
-#[derive(Deserialize, Serialize, Reflect)]
-pub struct ColorAnimation(#[serde(deserialize_with = "deserialize_color")] Color);
+#[derive(Deserialize, Serialize, Reflect, Clone)]
+pub struct ColorAnimation(
+    #[serde(
+        deserialize_with = "deserialize_color",
+        serialize_with = "serialize_color"
+    )]
+    Color,
+);
 
 fn linear_and_bright(color: Color) -> (Vec4, f32) {
     let [r, g, b, a] = color.as_linear_rgba_f32();
     (Vec4::new(r, g, b, a), (r + g + b + a).powf(0.43))
 }
 
+fn interpolate_color(previous: Option<Color>, next: Color, progress: f32) -> Color {
+    let Some(previous) = previous else {
+        return next;
+    };
+
+    let (linear_a, bright_a) = linear_and_bright(previous);
+    let (linear_b, bright_b) = linear_and_bright(next);
+    let intensity = (bright_a * (1.0 - progress) + bright_b * progress).powf(0.43f32.recip());
+    let mut color = linear_a * (1.0 - progress) + linear_b * progress;
+    let sum = color.x + color.y + color.z + color.w;
+    if sum != 0.0 {
+        color = color * intensity / sum;
+    }
+    Color::rgba_linear(color.x, color.y, color.z, color.w)
+}
+
+fn interpolate_value<T>(previous: Option<T>, next: T, progress: f32) -> T
+where
+    T: Mul<f32, Output = T> + Add<Output = T>,
+{
+    match previous {
+        Some(previous) => previous * (1.0 - progress) + next * progress,
+        None => next,
+    }
+}
+
 impl LayoutAnimationTarget for ColorAnimation {
     const NAME: &'static str = "Color";
 
@@ -31,21 +60,7 @@ impl LayoutAnimationTarget for ColorAnimation {
         mut world: ResourceRestrictedWorld,
         progress: f32,
     ) {
-        let color = match previous {
-            Some(Self(prev_color)) => {
-                let (linear_a, bright_a) = linear_and_bright(*prev_color);
-                let (linear_b, bright_b) = linear_and_bright(self.0);
-                let intensity =
-                    (bright_a * (1.0 - progress) + bright_b * progress).powf(0.43f32.recip());
-                let mut color = linear_a * (1.0 - progress) + linear_b * progress;
-                let sum = color.x + color.y + color.z + color.w;
-                if sum != 0.0 {
-                    color = color * intensity / sum;
-                }
-                Color::rgba_linear(color.x, color.y, color.z, color.w)
-            }
-            None => self.0,
-        };
+        let color = interpolate_color(previous.map(|prev| prev.0), self.0, progress);
 
         if let Some(mut image) = node.get_image() {
             image.sprite_data_mut().color = color;
@@ -59,15 +74,23 @@ impl LayoutAnimationTarget for ColorAnimation {
                 .color = color;
         }
     }
+
+    fn sample(&self, previous: Option<&Self>, progress: f32) -> Option<Self> {
+        Some(Self(interpolate_color(
+            previous.map(|prev| prev.0),
+            self.0,
+            progress,
+        )))
+    }
 }
 
-#[derive(Deserialize, Serialize, Reflect)]
+#[derive(Deserialize, Serialize, Reflect, Clone)]
 pub struct PositionAnimation(Vec2);
 
-#[derive(Deserialize, Serialize, Reflect)]
+#[derive(Deserialize, Serialize, Reflect, Clone)]
 pub struct SizeAnimation(Vec2);
 
-#[derive(Deserialize, Serialize, Reflect)]
+#[derive(Deserialize, Serialize, Reflect, Clone)]
 pub struct ScaleAnimation(Vec2);
 
 impl LayoutAnimationTarget for PositionAnimation {
@@ -80,13 +103,18 @@ impl LayoutAnimationTarget for PositionAnimation {
         _: ResourceRestrictedWorld,
         progress: f32,
     ) {
-        let pos = match previous {
-            Some(Self(pos)) => *pos * (1.0 - progress) + self.0 * progress,
-            None => self.0,
-        };
+        let pos = interpolate_value(previous.map(|prev| prev.0), self.0, progress);
 
         node.get_mut::<Node>().unwrap().position = pos;
     }
+
+    fn sample(&self, previous: Option<&Self>, progress: f32) -> Option<Self> {
+        Some(Self(interpolate_value(
+            previous.map(|prev| prev.0),
+            self.0,
+            progress,
+        )))
+    }
 }
 
 impl LayoutAnimationTarget for SizeAnimation {
@@ -99,13 +127,18 @@ impl LayoutAnimationTarget for SizeAnimation {
         _: ResourceRestrictedWorld,
         progress: f32,
     ) {
-        let size = match previous {
-            Some(Self(size)) => *size * (1.0 - progress) + self.0 * progress,
-            None => self.0,
-        };
+        let size = interpolate_value(previous.map(|prev| prev.0), self.0, progress);
 
         node.get_mut::<Node>().unwrap().size = size;
     }
+
+    fn sample(&self, previous: Option<&Self>, progress: f32) -> Option<Self> {
+        Some(Self(interpolate_value(
+            previous.map(|prev| prev.0),
+            self.0,
+            progress,
+        )))
+    }
 }
 
 impl LayoutAnimationTarget for ScaleAnimation {
@@ -118,16 +151,21 @@ impl LayoutAnimationTarget for ScaleAnimation {
         _world: ResourceRestrictedWorld<'_>,
         progress: f32,
     ) {
-        let scale = match previous {
-            Some(Self(scale)) => *scale * (1.0 - progress) + self.0 * progress,
-            None => self.0,
-        };
+        let scale = interpolate_value(previous.map(|prev| prev.0), self.0, progress);
 
         node.get_mut::<Node>().unwrap().scale = scale;
     }
+
+    fn sample(&self, previous: Option<&Self>, progress: f32) -> Option<Self> {
+        Some(Self(interpolate_value(
+            previous.map(|prev| prev.0),
+            self.0,
+            progress,
+        )))
+    }
 }
 
-#[derive(Deserialize, Serialize, Reflect)]
+#[derive(Deserialize, Serialize, Reflect, Clone)]
 pub struct RotationAnimation(f32);
 
 impl LayoutAnimationTarget for RotationAnimation {
@@ -140,11 +178,16 @@ impl LayoutAnimationTarget for RotationAnimation {
         _: ResourceRestrictedWorld,
         progress: f32,
     ) {
-        let rotation = match previous {
-            Some(Self(angle)) => *angle * (1.0 - progress) + self.0 * progress,
-            None => self.0,
-        };
+        let rotation = interpolate_value(previous.map(|prev| prev.0), self.0, progress);
 
         node.get_mut::<Node>().unwrap().rotation = rotation;
     }
+
+    fn sample(&self, previous: Option<&Self>, progress: f32) -> Option<Self> {
+        Some(Self(interpolate_value(
+            previous.map(|prev| prev.0),
+            self.0,
+            progress,
+        )))
+    }
 }
@@ -1,34 +1,79 @@
 use std::path::{Path, PathBuf};
 
 use bevy::{
-    asset::{LoadState, RecursiveDependencyLoadState},
+    asset::{
+        AssetLoadFailedEvent, LoadState, RecursiveDependencyLoadState, UntypedAssetId,
+        VisitAssetDependencies,
+    },
     ecs::query::{QueryData, WorldQuery},
     prelude::*,
     render::{
         camera::{ManualTextureViews, RenderTarget},
         view::RenderLayers,
     },
+    utils::HashMap,
     window::{PrimaryWindow, WindowRef},
 };
+use camino::Utf8PathBuf;
 use smallvec::SmallVec;
 use thiserror::Error;
 
-use crate::{asset::Layout, views::NodeEntityMut};
+use crate::{
+    animation::{send_interrupted, LayoutAnimation, LayoutAnimationPlaybackState},
+    asset::{resolve_node_path, Layout, LayoutNode, NodeOverride, NodePathTarget},
+    node::{LayoutHandle, Node},
+    views::NodeEntityMut,
+};
 
 use self::spawning::spawn_layout;
 
+pub(crate) mod despawning;
 pub mod spawning;
 
+/// Sent after a layout root has been spawned successfully
 #[derive(Event)]
 pub struct LoadedLayout {
     pub id: LayoutId,
     pub handle: Handle<Layout>,
 }
 
+/// Sent when a layout root could not be spawned
+///
+/// The root will be retried automatically once its layout asset, or any of its dependencies, is
+/// loaded again (i.e. when hot-reloading) or when it is retried explicitly with
+/// [`LayoutBundle::retry`]
+#[derive(Event)]
+pub struct LayoutLoadFailed {
+    pub entity: Entity,
+    pub handle: Handle<Layout>,
+    pub reason: LayoutLoadError,
+}
+
+#[derive(Error, Debug)]
+pub enum LayoutLoadError {
+    #[error("The load state of the layout asset is gone")]
+    MissingLoadState,
+
+    #[error("Failed to load the layout asset, check asset loader logs")]
+    AssetFailed,
+
+    #[error("One or more dependencies of the layout failed to load, check asset loader logs")]
+    DependencyFailed,
+
+    #[error("Animation '{animation}' targets node `{node}` which does not exist in the layout or its sublayouts")]
+    UnknownAnimationNode {
+        animation: String,
+        node: Utf8PathBuf,
+    },
+
+    #[error(transparent)]
+    Spawn(#[from] SpawnLayoutError),
+}
+
 #[derive(Copy, Clone, Component, Reflect)]
 pub struct LayoutId(pub Entity);
 
-#[derive(Clone, Component, Reflect)]
+#[derive(Clone, PartialEq, Eq, Hash, Component, Reflect)]
 pub struct LayoutNodeId(PathBuf);
 
 impl LayoutNodeId {
@@ -57,6 +102,7 @@ pub enum NodeKind {
     Text,
     Layout,
     Group,
+    Repeat,
 }
 
 #[derive(Component)]
@@ -65,17 +111,36 @@ pub struct ActiveLayout;
 #[derive(Component, PartialEq, Eq)]
 pub(crate) enum PendingStatus {
     AwaitingCreation,
+    /// The layout failed and a reload of its asset has been requested, the root waits until
+    /// the asset server has finished reloading before trying again
+    AwaitingReload,
     Failed,
 }
 
+/// The dependencies of a failed layout root that failed to load themselves
+///
+/// The root is retried once any of them has been loaded again, whatever type of asset it is
+#[derive(Component)]
+pub(crate) struct FailedDependencies(SmallVec<[UntypedAssetId; 4]>);
+
 #[derive(Component)]
 pub struct RootNode {
     handle: Handle<Layout>,
 }
 
+/// Marker for spawned layout roots whose layout (or one of its sublayouts) has been modified
+/// and needs to be respawned
+#[derive(Component)]
+pub(crate) struct PendingReload;
+
 #[derive(Component)]
 struct OnLoadCallback(Option<Box<dyn FnOnce(NodeEntityMut) + Send + Sync + 'static>>);
 
+type ReloadCallback = Box<dyn FnMut(NodeEntityMut) + Send + Sync + 'static>;
+
+#[derive(Component)]
+struct OnReloadCallback(Option<ReloadCallback>);
+
 #[derive(Bundle)]
 pub struct LayoutBundle {
     root: RootNode,
@@ -83,6 +148,7 @@ pub struct LayoutBundle {
     visibility: VisibilityBundle,
     transform: TransformBundle,
     on_load: OnLoadCallback,
+    on_reload: OnReloadCallback,
 }
 
 impl LayoutBundle {
@@ -96,6 +162,7 @@ impl LayoutBundle {
             },
             transform: TransformBundle::default(),
             on_load: OnLoadCallback(None),
+            on_reload: OnReloadCallback(None),
         }
     }
 
@@ -106,6 +173,44 @@ impl LayoutBundle {
         self.on_load.0 = Some(Box::new(f));
         self
     }
+
+    /// Sets a callback to run every time this layout is respawned because its layout asset (or
+    /// one of its sublayouts) was modified, i.e. when hot-reloading
+    ///
+    /// The on-load callback only runs once, so any state set up in it that does not come from
+    /// attributes should also be set up here.
+    pub fn with_on_reload_callback(
+        mut self,
+        f: impl FnMut(NodeEntityMut) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_reload.0 = Some(Box::new(f));
+        self
+    }
+
+    /// Retries spawning a layout root that has failed to load or spawn
+    ///
+    /// The layout asset is reloaded so that dependencies which have been fixed since are picked
+    /// up. Nodes are never left behind by a spawn that failed part of the way through the tree,
+    /// so the retry starts from an empty root. Returns `false` if the root has not failed.
+    pub fn retry(mut root: EntityWorldMut) -> bool {
+        let Some(handle) = root.get::<RootNode>().map(|root| root.handle.clone()) else {
+            return false;
+        };
+
+        if root.get::<PendingStatus>() != Some(&PendingStatus::Failed) {
+            return false;
+        }
+
+        let status = match handle.path() {
+            Some(path) => {
+                root.world().resource::<AssetServer>().reload(path.clone());
+                PendingStatus::AwaitingReload
+            }
+            None => PendingStatus::AwaitingCreation,
+        };
+        root.insert(status).remove::<FailedDependencies>();
+        true
+    }
 }
 
 #[derive(QueryData)]
@@ -121,64 +226,84 @@ pub(crate) struct PendingRootQuery {
 pub enum SpawnLayoutError {
     #[error("Failed to spawn layout because the asset data does not exist/isn't loaded")]
     NotLoaded,
+
+    #[error("Failed to spawn node because the entity {0:?} is not part of a layout tree")]
+    NotANode(Entity),
+
+    #[error("Failed to spawn node because the node {0:?} already has a child with the name {1}")]
+    DuplicateChild(Entity, String),
 }
 
 pub(crate) fn spawn_layout_system(world: &mut World) {
     let assets = world.resource::<AssetServer>().clone();
 
     let mut ready: SmallVec<[(Entity, Handle<Layout>); 4]> = SmallVec::new();
+    let mut failed: SmallVec<[LayoutLoadFailed; 4]> = SmallVec::new();
     for mut root in world.query::<PendingRootQuery>().iter_mut(world) {
-        if *root.status == PendingStatus::Failed {
+        if *root.status != PendingStatus::AwaitingCreation {
             continue;
         }
 
         let root_handle = root.root.handle.clone();
         let handle_id = root.root.handle.id();
 
-        match assets.get_load_state(handle_id) {
-            None => {
-                log::error!("Failed to load layout because the handle state is gone");
-                *root.status = PendingStatus::Failed;
+        let reason = match (
+            assets.get_load_state(handle_id),
+            assets.get_recursive_dependency_load_state(handle_id),
+        ) {
+            (None, _) | (_, None) => LayoutLoadError::MissingLoadState,
+            (Some(LoadState::Failed), _) => LayoutLoadError::AssetFailed,
+            (_, Some(RecursiveDependencyLoadState::Failed)) => LayoutLoadError::DependencyFailed,
+            (_, Some(RecursiveDependencyLoadState::Loaded)) => {
+                ready.push((root.entity, root_handle));
                 continue;
             }
-            Some(LoadState::Failed) => {
-                log::error!("Failed to load layout, check asset loader logs");
-                *root.status = PendingStatus::Failed;
-                continue;
-            }
-            _ => {}
-        }
-
-        match assets.get_recursive_dependency_load_state(handle_id) {
-            None => {
-                log::error!("Failed to load layout because the handle state is gone");
-                *root.status = PendingStatus::Failed;
-                continue;
-            }
-            Some(RecursiveDependencyLoadState::Failed) => {
-                log::error!("Failed to load layout because one or more dependencies failed to load, check asset loader logs");
-                *root.status = PendingStatus::Failed;
-                continue;
-            }
-            Some(RecursiveDependencyLoadState::Loaded) => {}
             _ => continue,
-        }
+        };
 
-        ready.push((root.entity, root_handle));
+        log::error!("Failed to load layout: {reason}");
+        *root.status = PendingStatus::Failed;
+        failed.push(LayoutLoadFailed {
+            entity: root.entity,
+            handle: root_handle,
+            reason,
+        });
     }
 
+    for event in failed.iter() {
+        if let LayoutLoadError::DependencyFailed = event.reason {
+            let mut dependencies = SmallVec::new();
+            collect_failed_dependencies(
+                world.resource(),
+                &assets,
+                event.handle.id(),
+                &mut dependencies,
+            );
+            world
+                .entity_mut(event.entity)
+                .insert(FailedDependencies(dependencies));
+        }
+    }
+
+    world.send_event_batch(failed);
+
     for (entity, root_handle) in ready {
-        let result = spawn_layout(world, entity, root_handle.clone(), |node, mut child| {
-            for attribute in node.attributes.iter() {
-                attribute.apply(child.reborrow());
-            }
-        });
+        let result = validate_animation_nodes(world.resource(), world.resource(), root_handle.id())
+            .and_then(|_| {
+                spawn_layout(world, entity, root_handle.clone(), apply_attributes)
+                    .map_err(LayoutLoadError::from)
+            });
 
         let mut root = world.entity_mut(entity);
 
         if let Err(e) = result {
             log::error!("Failed to load layout: {e}");
             *root.get_mut::<PendingStatus>().unwrap() = PendingStatus::Failed;
+            world.send_event(LayoutLoadFailed {
+                entity,
+                handle: root_handle,
+                reason: e,
+            });
         } else {
             root.remove::<PendingStatus>();
             let callback = root
@@ -188,6 +313,316 @@ pub(crate) fn spawn_layout_system(world: &mut World) {
             if let Some(cb) = callback {
                 cb(NodeEntityMut::from_entity_world_mut(root));
             }
+
+            world.send_event(LoadedLayout {
+                id: LayoutId(entity),
+                handle: root_handle,
+            });
+        }
+    }
+}
+
+/// Checks that every node animated by the layout `id` and its sublayouts exists
+///
+/// Animated nodes inside of sublayouts can only be checked once the sublayouts have been loaded,
+/// the rest are already checked when loading the layout
+fn validate_animation_nodes(
+    layouts: &Assets<Layout>,
+    animations: &Assets<LayoutAnimation>,
+    id: AssetId<Layout>,
+) -> Result<(), LayoutLoadError> {
+    let Some(layout) = layouts.get(id) else {
+        return Ok(());
+    };
+
+    for handle in layout.animations.iter() {
+        let Some(animation) = animations.get(handle) else {
+            continue;
+        };
+
+        for node in animation.keys() {
+            let mut target = resolve_node_path(&layout.nodes, node);
+            let is_missing = loop {
+                match target {
+                    NodePathTarget::Node => break false,
+                    NodePathTarget::Missing => break true,
+                    NodePathTarget::Sublayout { handle, rest } => match layouts.get(handle) {
+                        Some(sublayout) => target = resolve_node_path(&sublayout.nodes, &rest),
+                        None => break false,
+                    },
+                }
+            };
+
+            if is_missing {
+                return Err(LayoutLoadError::UnknownAnimationNode {
+                    animation: handle
+                        .path()
+                        .and_then(|path| path.label())
+                        .unwrap_or_default()
+                        .to_string(),
+                    node: node.clone(),
+                });
+            }
+        }
+    }
+
+    let mut sublayouts: SmallVec<[AssetId<Layout>; 4]> = SmallVec::new();
+    layout.visit_dependencies(&mut |dep| {
+        if let Ok(sublayout) = dep.try_typed::<Layout>() {
+            sublayouts.push(sublayout);
+        }
+    });
+
+    sublayouts
+        .into_iter()
+        .try_for_each(|sublayout| validate_animation_nodes(layouts, animations, sublayout))
+}
+
+/// Checks that the layout `id` and all of its sublayouts are loaded, so that it can be spawned
+fn is_layout_loaded(layouts: &Assets<Layout>, id: AssetId<Layout>) -> bool {
+    let Some(layout) = layouts.get(id) else {
+        return false;
+    };
+
+    let mut sublayouts: SmallVec<[AssetId<Layout>; 4]> = SmallVec::new();
+    layout.visit_dependencies(&mut |dep| {
+        if let Ok(sublayout) = dep.try_typed::<Layout>() {
+            sublayouts.push(sublayout);
+        }
+    });
+
+    sublayouts
+        .into_iter()
+        .all(|sublayout| is_layout_loaded(layouts, sublayout))
+}
+
+/// Collects the dependencies of the layout `id`, and of its sublayouts, that failed to load
+fn collect_failed_dependencies(
+    layouts: &Assets<Layout>,
+    assets: &AssetServer,
+    id: AssetId<Layout>,
+    failed: &mut SmallVec<[UntypedAssetId; 4]>,
+) {
+    let Some(layout) = layouts.get(id) else {
+        return;
+    };
+
+    let mut sublayouts: SmallVec<[AssetId<Layout>; 4]> = SmallVec::new();
+    layout.visit_dependencies(&mut |dep| {
+        if let Some(LoadState::Failed) = assets.get_load_state(dep) {
+            if !failed.contains(&dep) {
+                failed.push(dep);
+            }
+        } else if let Ok(sublayout) = dep.try_typed::<Layout>() {
+            sublayouts.push(sublayout);
+        }
+    });
+
+    for sublayout in sublayouts {
+        collect_failed_dependencies(layouts, assets, sublayout, failed);
+    }
+}
+
+pub(crate) fn retry_failed_layouts(
+    mut commands: Commands,
+    mut layout_events: EventReader<AssetEvent<Layout>>,
+    mut layout_failed_events: EventReader<AssetLoadFailedEvent<Layout>>,
+    mut roots: Query<(
+        Entity,
+        &RootNode,
+        &mut PendingStatus,
+        Option<&FailedDependencies>,
+    )>,
+    assets: Res<AssetServer>,
+) {
+    let reloaded: SmallVec<[AssetId<Layout>; 4]> = layout_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .chain(layout_failed_events.read().map(|event| event.id))
+        .collect();
+
+    for (entity, root, mut status, failed_dependencies) in roots.iter_mut() {
+        if *status == PendingStatus::AwaitingCreation {
+            continue;
+        }
+
+        if reloaded.contains(&root.handle.id()) {
+            // The layout asset itself has changed, so the load states are up to date again
+            *status = PendingStatus::AwaitingCreation;
+            commands.entity(entity).remove::<FailedDependencies>();
+        } else if *status == PendingStatus::Failed
+            && failed_dependencies.is_some_and(|dependencies| {
+                dependencies.0.iter().any(|id| {
+                    matches!(
+                        assets.get_recursive_dependency_load_state(*id),
+                        Some(RecursiveDependencyLoadState::Loaded)
+                    )
+                })
+            })
+        {
+            // The recursive dependency load state of the layout is only recalculated when it
+            // gets loaded, so it has to be reloaded to pick up the fixed dependency
+            *status = match root.handle.path() {
+                Some(path) => {
+                    assets.reload(path.clone());
+                    PendingStatus::AwaitingReload
+                }
+                None => PendingStatus::AwaitingCreation,
+            };
+            commands.entity(entity).remove::<FailedDependencies>();
+        }
+    }
+}
+
+fn apply_attributes(node: &LayoutNode, overrides: &[&NodeOverride], mut child: NodeEntityMut) {
+    for attribute in node.attributes_with_overrides(overrides) {
+        attribute.apply(child.reborrow());
+    }
+}
+
+pub(crate) fn detect_modified_layouts(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Layout>>,
+    nodes: Query<(&LayoutHandle, &LayoutId)>,
+    roots: Query<(), (With<RootNode>, Without<PendingStatus>)>,
+) {
+    let modified: SmallVec<[AssetId<Layout>; 4]> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    if modified.is_empty() {
+        return;
+    }
+
+    for (handle, layout_id) in nodes.iter() {
+        if modified.contains(&handle.0.id()) && roots.contains(layout_id.0) {
+            commands.entity(layout_id.0).insert(PendingReload);
+        }
+    }
+}
+
+pub(crate) fn reload_layout_system(world: &mut World) {
+    let assets = world.resource::<AssetServer>().clone();
+
+    let mut ready: SmallVec<[(Entity, Handle<Layout>); 4]> = SmallVec::new();
+    let mut failed: SmallVec<[Entity; 4]> = SmallVec::new();
+    let mut pending = world.query_filtered::<(Entity, &RootNode), With<PendingReload>>();
+    let layouts = world.resource::<Assets<Layout>>();
+    let animations = world.resource::<Assets<LayoutAnimation>>();
+    for (entity, root) in pending.iter(world) {
+        match assets.get_recursive_dependency_load_state(root.handle.id()) {
+            None | Some(RecursiveDependencyLoadState::Failed) => {
+                log::error!("Failed to reload layout because it or one or more dependencies failed to load, check asset loader logs");
+                failed.push(entity);
+            }
+            // The old tree is only torn down once the new one is known to spawn, otherwise the
+            // layout is kept as it was
+            Some(RecursiveDependencyLoadState::Loaded)
+                if !is_layout_loaded(layouts, root.handle.id()) =>
+            {
+                log::error!("Failed to reload layout because it or one of its sublayouts is no longer loaded");
+                failed.push(entity);
+            }
+            Some(RecursiveDependencyLoadState::Loaded) => {
+                match validate_animation_nodes(layouts, animations, root.handle.id()) {
+                    Ok(()) => ready.push((entity, root.handle.clone())),
+                    Err(e) => {
+                        log::error!("Failed to reload layout: {e}");
+                        failed.push(entity);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for entity in failed {
+        world.entity_mut(entity).remove::<PendingReload>();
+    }
+
+    for (entity, root_handle) in ready {
+        // Keep the playback state of every layout node so that animations which are still
+        // around after the reload can continue where they left off
+        let mut playback_states: HashMap<LayoutNodeId, (Entity, LayoutAnimationPlaybackState)> =
+            world
+                .query::<(
+                    Entity,
+                    &LayoutId,
+                    &LayoutNodeId,
+                    &mut LayoutAnimationPlaybackState,
+                )>()
+                .iter_mut(world)
+                .filter(|(_, layout_id, _, _)| layout_id.0 == entity)
+                .map(|(node, _, node_id, mut state)| {
+                    (node_id.clone(), (node, std::mem::take(&mut *state)))
+                })
+                .collect();
+
+        let children: SmallVec<[Entity; 8]> = world
+            .entity(entity)
+            .get::<Children>()
+            .iter()
+            .flat_map(|children| children.iter().copied())
+            .filter(|child| world.entity(*child).contains::<Node>())
+            .collect();
+
+        // The old layout asset has already been replaced, but the nodes are torn down with the
+        // attributes that they were spawned with
+        for child in children {
+            despawning::despawn_node_tree(world, child);
+        }
+
+        if let Err(e) = spawn_layout(world, entity, root_handle, apply_attributes) {
+            log::error!("Failed to reload layout: {e}");
+            world.entity_mut(entity).remove::<PendingReload>();
+            continue;
+        }
+
+        let mut interrupted: Vec<(Entity, Vec<String>)> = vec![];
+        world
+            .query::<(
+                Entity,
+                &LayoutId,
+                &LayoutNodeId,
+                &mut LayoutAnimationPlaybackState,
+            )>()
+            .iter_mut(world)
+            .filter(|(_, layout_id, _, _)| layout_id.0 == entity)
+            .for_each(|(node, _, node_id, mut state)| {
+                if let Some((_, previous)) = playback_states.remove(node_id) {
+                    interrupted.push((node, state.restore_from(previous)));
+                }
+            });
+
+        // Animations of nodes that are gone after the reload were interrupted as well
+        interrupted.extend(playback_states.into_values().map(|(node, state)| {
+            (
+                node,
+                state.active_animations().map(str::to_string).collect(),
+            )
+        }));
+        for (node, animations) in interrupted {
+            send_interrupted(world, node, animations);
+        }
+
+        let mut root = world.entity_mut(entity);
+        root.remove::<PendingReload>();
+
+        let callback = root
+            .get_mut::<OnReloadCallback>()
+            .and_then(|mut cb| cb.0.take());
+        if let Some(mut cb) = callback {
+            cb(NodeEntityMut::new(world, entity));
+            if let Some(mut slot) = world.get_mut::<OnReloadCallback>(entity) {
+                slot.0 = Some(cb);
+            }
         }
     }
 }
@@ -0,0 +1,86 @@
+use bevy::{ecs::system::Command, prelude::*};
+use smallvec::SmallVec;
+
+use crate::{
+    animation::{send_interrupted, LayoutAnimationPlaybackState},
+    input_detection::release_input_detection,
+    node::{SpawnedAttributes, ZIndex},
+    views::NodeEntityMut,
+    LayoutId,
+};
+
+use super::RootNode;
+
+/// Command that tears down and despawns a layout root, see [`LayoutCommands::despawn_layout`](crate::LayoutCommands::despawn_layout)
+pub(crate) struct DespawnLayout(pub Entity);
+
+impl Command for DespawnLayout {
+    fn apply(self, world: &mut World) {
+        let Some(entity) = world.get_entity(self.0) else {
+            log::warn!(
+                "Attempted to despawn layout {:?} which does not exist",
+                self.0
+            );
+            return;
+        };
+
+        if !entity.contains::<RootNode>() {
+            log::error!(
+                "Attempted to despawn {:?} as a layout, but it is not a layout root",
+                self.0
+            );
+            return;
+        }
+
+        despawn_node_tree(world, self.0);
+    }
+}
+
+fn teardown_node(world: &mut World, entity: Entity) {
+    release_input_detection(world, entity);
+
+    if let Some(state) = world.get::<LayoutAnimationPlaybackState>(entity) {
+        let active: SmallVec<[String; 2]> = state.active_animations().map(str::to_string).collect();
+        send_interrupted(world, entity, active);
+    }
+
+    // The attributes are the ones the node was spawned with, the layout may have changed since
+    if let Some(SpawnedAttributes(attributes)) = world.get::<SpawnedAttributes>(entity).cloned() {
+        for attribute in attributes {
+            attribute.on_despawn(NodeEntityMut::new(world, entity));
+        }
+    }
+
+    let children: SmallVec<[Entity; 8]> = world
+        .get::<Children>(entity)
+        .iter()
+        .flat_map(|children| children.iter().copied())
+        .filter(|child| world.get::<crate::node::Node>(*child).is_some())
+        .collect();
+
+    for child in children {
+        teardown_node(world, child);
+    }
+}
+
+/// Notifies every attribute, input handler and animation in the tree starting at `entity` that
+/// the nodes are going away, and then despawns the tree
+///
+/// If the tree is part of a larger layout, then the [`ZIndex`] of the rest of the layout will
+/// be recalculated
+pub(crate) fn despawn_node_tree(world: &mut World, entity: Entity) {
+    let root = world
+        .get::<LayoutId>(entity)
+        .copied()
+        .filter(|root| root.0 != entity);
+
+    teardown_node(world, entity);
+
+    if let Some(entity) = world.get_entity_mut(entity) {
+        entity.despawn_recursive();
+    }
+
+    if let Some(mut z_index) = root.and_then(|root| world.get_mut::<ZIndex>(root.0)) {
+        *z_index = ZIndex::NeedsRecalculation;
+    }
+}
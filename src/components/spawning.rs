@@ -5,10 +5,19 @@ use bevy::{
     text::{Text2dBounds, TextLayoutInfo},
 };
 
+use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
+use serde_value::{DeserializerError, ValueDeserializer};
+use smallvec::SmallVec;
+
 use crate::{
     animation::LayoutAnimationPlaybackState,
-    asset::{GroupNodeData, ImageNodeData, Layout, LayoutNode, TextNodeData},
-    node::{LayoutHandle, LayoutInfo, ZIndex},
+    asset::{
+        resolve_node_path, BoundField, GroupNodeData, ImageNodeData, Layout, LayoutNode,
+        NodeOverride, NodePathTarget, RepeatNodeData, TextNodeData,
+    },
+    node::{LayoutHandle, LayoutInfo, RepeatInfo, SpawnedAttributes, ZIndex},
     views::NodeEntityMut,
     LayoutId, LayoutNodeId,
 };
@@ -17,17 +26,29 @@ use crate::{
     node::Node,
 };
 
-use super::{NodeKind, SpawnLayoutError};
+use super::{despawning::despawn_node_tree, NodeKind, SpawnLayoutError};
+
+/// Called for every spawned node once its children have been spawned, with the overrides that
+/// the enclosing layouts have for it
+pub(crate) type SpawnVisitor<'a> = dyn FnMut(&LayoutNode, &[&NodeOverride], NodeEntityMut) + 'a;
 
 pub(crate) struct SpawnNodeContext<'a> {
     pub world: &'a mut World,
     pub assets: &'a Assets<Layout>,
-    pub visitor: &'a mut dyn FnMut(&LayoutNode, NodeEntityMut),
+    pub visitor: &'a mut SpawnVisitor<'a>,
     pub root: LayoutId,
     pub parent: LayoutNodeId,
     pub layers: RenderLayers,
 
     pub parent_layout: &'a Layout,
+
+    /// The overrides of every sublayout that is being spawned, keyed by the id of the node they
+    /// apply to and ordered from the innermost sublayout to the outermost
+    pub overrides: &'a [(LayoutNodeId, &'a NodeOverride)],
+
+    /// The values that the enclosing layouts give to the variables of `parent_layout`, the
+    /// first map that has a variable decides its value
+    pub variables: &'a [&'a IndexMap<String, serde_value::Value>],
 }
 
 impl<'a> SpawnNodeContext<'a> {
@@ -40,10 +61,18 @@ impl<'a> SpawnNodeContext<'a> {
             parent: self.parent.join(id),
             layers: self.layers,
             parent_layout: self.parent_layout,
+            overrides: self.overrides,
+            variables: self.variables,
         }
     }
 
-    fn reborrow_with_layout(&mut self, id: &str, layout: &'a Layout) -> SpawnNodeContext<'_> {
+    fn reborrow_with_layout<'b>(
+        &'b mut self,
+        id: &str,
+        layout: &'a Layout,
+        overrides: &'b [(LayoutNodeId, &'b NodeOverride)],
+        variables: &'b [&'b IndexMap<String, serde_value::Value>],
+    ) -> SpawnNodeContext<'b> {
         SpawnNodeContext {
             world: self.world,
             assets: self.assets,
@@ -52,20 +81,178 @@ impl<'a> SpawnNodeContext<'a> {
             layers: self.layers,
             parent: self.parent.join(id),
             parent_layout: layout,
+            overrides,
+            variables,
+        }
+    }
+}
+
+/// Records the attributes that `node` is spawned with on `child`, see [`SpawnedAttributes`]
+fn record_attributes(node: &LayoutNode, overrides: &[&NodeOverride], mut child: NodeEntityMut) {
+    let attributes: Vec<_> = node.attributes_with_overrides(overrides).cloned().collect();
+    if !attributes.is_empty() {
+        child.insert(SpawnedAttributes(attributes));
+    }
+}
+
+/// Despawns a node whose children could only be spawned in part, so that a failed spawn doesn't
+/// leave any nodes behind
+fn despawn_partial_node(
+    world: &mut World,
+    entity: Entity,
+    error: SpawnLayoutError,
+) -> SpawnLayoutError {
+    despawn_node_tree(world, entity);
+    error
+}
+
+/// Finds the overrides that apply to the node `id`, from the innermost layout to the outermost
+pub(crate) fn overrides_for<'a>(
+    overrides: &[(LayoutNodeId, &'a NodeOverride)],
+    id: &LayoutNodeId,
+) -> SmallVec<[&'a NodeOverride; 2]> {
+    overrides
+        .iter()
+        .filter(|(target, _)| target == id)
+        .map(|(_, node_override)| *node_override)
+        .collect()
+}
+
+/// Adds the overrides that the sublayout or repeated node `id` has for the nodes below it in
+/// front of the overrides of the layouts that contain it
+pub(crate) fn with_node_overrides<'a>(
+    id: &LayoutNodeId,
+    overrides: &'a IndexMap<Utf8PathBuf, NodeOverride>,
+    outer: &[(LayoutNodeId, &'a NodeOverride)],
+) -> Vec<(LayoutNodeId, &'a NodeOverride)> {
+    overrides
+        .iter()
+        .map(|(path, node_override)| (id.join(path.as_str()), node_override))
+        .chain(outer.iter().cloned())
+        .collect()
+}
+
+/// Adds the variables of the sublayout node `layout` and of the layout that contains it in
+/// front of the variables of the enclosing layouts
+///
+/// `outer` are the variables that apply to `parent_layout`, which take priority over the values
+/// it declares
+fn with_sublayout_variables<'a>(
+    layout: &'a LayoutNodeData,
+    parent_layout: &'a Layout,
+    outer: &[&'a IndexMap<String, serde_value::Value>],
+) -> Vec<&'a IndexMap<String, serde_value::Value>> {
+    std::iter::once(&layout.variables)
+        .chain(outer.iter().copied())
+        .chain(std::iter::once(&parent_layout.variables))
+        .collect()
+}
+
+/// The fields of a node that are replaced by the variables and overrides of the enclosing
+/// layouts
+#[derive(Default)]
+struct NodePatch<'a> {
+    position: Option<Vec2>,
+    size: Option<Vec2>,
+    rotation: Option<f32>,
+    text: Option<String>,
+    text_size: Option<f32>,
+    color: Option<Color>,
+    image: Option<&'a Handle<Image>>,
+}
+
+impl<'a> NodePatch<'a> {
+    /// Looks up the variables that `node` is bound to in `variables`, moves the node by `offset`
+    /// and then applies `overrides` on top of them
+    fn new(
+        id: &LayoutNodeId,
+        node: &LayoutNode,
+        offset: Vec2,
+        variables: &[&IndexMap<String, serde_value::Value>],
+        overrides: &[&'a NodeOverride],
+    ) -> Self {
+        let mut patch = Self::default();
+
+        for binding in node.bindings.iter() {
+            let Some(value) = variables
+                .iter()
+                .find_map(|variables| variables.get(&binding.variable))
+            else {
+                continue;
+            };
+
+            match binding.field {
+                BoundField::Position => patch.position = bound_value(id, &binding.variable, value),
+                BoundField::Size => patch.size = bound_value(id, &binding.variable, value),
+                BoundField::Rotation => patch.rotation = bound_value(id, &binding.variable, value),
+                BoundField::Text => patch.text = bound_value(id, &binding.variable, value),
+                BoundField::TextSize => patch.text_size = bound_value(id, &binding.variable, value),
+                BoundField::Color => {
+                    patch.color = bound_value(id, &binding.variable, value)
+                        .map(|[r, g, b, a]: [f32; 4]| Color::rgba(r, g, b, a))
+                }
+            }
+        }
+
+        if offset != Vec2::ZERO {
+            patch.position = Some(patch.position.unwrap_or(node.position) + offset);
         }
+
+        for node_override in overrides.iter() {
+            patch.position = node_override.position.or(patch.position);
+            patch.text = node_override.text.clone().or(patch.text);
+            patch.color = node_override.tint.or(patch.color);
+            if node_override.image.is_some() {
+                patch.image = Some(&node_override.image_handle);
+            }
+        }
+
+        patch
     }
 }
 
-fn spawn_null_node(context: SpawnNodeContext<'_>, node: &LayoutNode) -> Entity {
+/// Converts the value of a variable for a field of the node `id`, warning if it is the wrong
+/// type for the field
+fn bound_value<T: DeserializeOwned>(
+    id: &LayoutNodeId,
+    variable: &str,
+    value: &serde_value::Value,
+) -> Option<T> {
+    match T::deserialize(ValueDeserializer::<DeserializerError>::new(value.clone())) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            log::warn!(
+                "Ignoring the value of variable {variable} for node {}: {error}",
+                id.qualified().display()
+            );
+            None
+        }
+    }
+}
+
+fn node_component(node: &LayoutNode, patch: &NodePatch) -> Node {
+    let mut component = Node::new_from_layout_node(node);
+    component.position = patch.position.unwrap_or(component.position);
+    component.size = patch.size.unwrap_or(component.size);
+    component.rotation = patch.rotation.unwrap_or(component.rotation);
+    component
+}
+
+fn spawn_null_node(
+    context: SpawnNodeContext<'_>,
+    node: &LayoutNode,
+    name: &str,
+    patch: &NodePatch,
+) -> Entity {
     context
         .world
         .spawn((
             TransformBundle::default(),
             VisibilityBundle::default(),
-            Node::new_from_layout_node(node),
+            node_component(node, patch),
             NodeKind::Null,
             context.root,
-            context.parent.join(node.id.as_str()),
+            context.parent.join(name),
             context.layers,
             ZIndex::default(),
         ))
@@ -75,25 +262,27 @@ fn spawn_null_node(context: SpawnNodeContext<'_>, node: &LayoutNode) -> Entity {
 fn spawn_image_node(
     context: SpawnNodeContext<'_>,
     node: &LayoutNode,
+    name: &str,
     image: &ImageNodeData,
+    patch: &NodePatch,
 ) -> Entity {
     context
         .world
         .spawn((
             TransformBundle::default(),
             VisibilityBundle::default(),
-            Node::new_from_layout_node(node),
+            node_component(node, patch),
             NodeKind::Image,
             context.root,
-            context.parent.join(node.id.as_str()),
+            context.parent.join(name),
             context.layers,
             ZIndex::default(),
             Sprite {
-                color: image.tint.unwrap_or(Color::WHITE),
-                custom_size: Some(node.size),
+                color: patch.color.or(image.tint).unwrap_or(Color::WHITE),
+                custom_size: Some(patch.size.unwrap_or(node.size)),
                 ..default()
             },
-            image.handle.clone(),
+            patch.image.unwrap_or(&image.handle).clone(),
         ))
         .id()
 }
@@ -101,7 +290,9 @@ fn spawn_image_node(
 fn spawn_text_node(
     context: SpawnNodeContext<'_>,
     node: &LayoutNode,
+    name: &str,
     text: &TextNodeData,
+    patch: &NodePatch,
 ) -> Entity {
     let text_anchor = match text.alignment {
         JustifyText::Left => Anchor::CenterLeft,
@@ -114,22 +305,24 @@ fn spawn_text_node(
         .spawn((
             TransformBundle::default(),
             VisibilityBundle::default(),
-            Node::new_from_layout_node(node),
+            node_component(node, patch),
             NodeKind::Text,
             context.root,
-            context.parent.join(node.id.as_str()),
+            context.parent.join(name),
             context.layers,
             ZIndex::default(),
             Text::from_section(
-                text.text.clone(),
+                patch.text.as_deref().unwrap_or(&text.text),
                 TextStyle {
                     font: text.handle.clone(),
-                    font_size: text.size,
-                    color: text.color,
+                    font_size: patch.text_size.unwrap_or(text.size),
+                    color: patch.color.unwrap_or(text.color),
                 },
             ),
             text_anchor,
-            Text2dBounds { size: node.size },
+            Text2dBounds {
+                size: patch.size.unwrap_or(node.size),
+            },
             TextLayoutInfo::default(),
         ))
         .id()
@@ -138,13 +331,40 @@ fn spawn_text_node(
 fn spawn_layout_node(
     mut context: SpawnNodeContext<'_>,
     node: &LayoutNode,
+    name: &str,
     layout: &LayoutNodeData,
+    patch: &NodePatch,
 ) -> Result<Entity, SpawnLayoutError> {
     let asset = context
         .assets
         .get(layout.handle.id())
         .ok_or(SpawnLayoutError::NotLoaded)?;
 
+    let id = context.parent.join(name);
+    for path in layout.overrides.keys() {
+        if let NodePathTarget::Missing = resolve_node_path(&asset.nodes, path) {
+            log::warn!(
+                "Layout node {} overrides node `{path}`, which is not in {}",
+                id.qualified().display(),
+                layout.path.display()
+            );
+        }
+    }
+
+    for variable in layout.variables.keys() {
+        if !asset.variables.contains_key(variable) {
+            log::warn!(
+                "Layout node {} sets variable {variable}, which is not declared by {}",
+                id.qualified().display(),
+                layout.path.display()
+            );
+        }
+    }
+
+    let sublayout_overrides = with_node_overrides(&id, &layout.overrides, context.overrides);
+    let sublayout_variables =
+        with_sublayout_variables(layout, context.parent_layout, context.variables);
+
     let playback_state = LayoutAnimationPlaybackState::new(
         context.world.resource::<AssetServer>(),
         asset.animations.iter().map(|handle| handle.id()),
@@ -155,10 +375,10 @@ fn spawn_layout_node(
         .spawn((
             TransformBundle::default(),
             VisibilityBundle::default(),
-            Node::new_from_layout_node(node),
+            node_component(node, patch),
             NodeKind::Layout,
             context.root,
-            context.parent.join(node.id.as_str()),
+            id.clone(),
             context.layers,
             ZIndex::default(),
             LayoutInfo {
@@ -174,17 +394,20 @@ fn spawn_layout_node(
 
     let mut children = vec![];
 
-    let parent_id = node.id.as_str();
-
     for node in asset.nodes.iter() {
-        let child = spawn_node(context.reborrow_with_layout(parent_id, asset), node)?;
+        let child = spawn_node(
+            context.reborrow_with_layout(name, asset, &sublayout_overrides, &sublayout_variables),
+            node,
+        )
+        .map_err(|e| despawn_partial_node(context.world, parent, e))?;
         context.world.entity_mut(parent).add_child(child);
         children.push(child);
     }
 
     for (node, child) in asset.nodes.iter().zip(children.into_iter()) {
+        let overrides = overrides_for(&sublayout_overrides, &id.join(node.id.as_str()));
         let child = NodeEntityMut::new(context.world, child);
-        (context.visitor)(node, child);
+        (context.visitor)(node, &overrides, child);
     }
 
     Ok(parent)
@@ -193,17 +416,20 @@ fn spawn_layout_node(
 fn spawn_group_node(
     mut context: SpawnNodeContext<'_>,
     node: &LayoutNode,
+    name: &str,
     group: &GroupNodeData,
+    patch: &NodePatch,
 ) -> Result<Entity, SpawnLayoutError> {
+    let id = context.parent.join(name);
     let parent = context
         .world
         .spawn((
             TransformBundle::default(),
             VisibilityBundle::default(),
-            Node::new_from_layout_node(node),
+            node_component(node, patch),
             NodeKind::Group,
             context.root,
-            context.parent.join(node.id.as_str()),
+            id.clone(),
             context.layers,
             ZIndex::default(),
             LayoutInfo {
@@ -216,17 +442,86 @@ fn spawn_group_node(
 
     let mut children = vec![];
 
-    let parent_id = node.id.as_str();
-
     for node in group.nodes.iter() {
-        let child = spawn_node(context.reborrow(parent_id), node)?;
+        let child = spawn_node(context.reborrow(name), node)
+            .map_err(|e| despawn_partial_node(context.world, parent, e))?;
         context.world.entity_mut(parent).add_child(child);
         children.push(child);
     }
 
     for (node, child) in group.nodes.iter().zip(children.into_iter()) {
+        let overrides = overrides_for(context.overrides, &id.join(node.id.as_str()));
+        let child = NodeEntityMut::new(context.world, child);
+        (context.visitor)(node, &overrides, child);
+    }
+
+    Ok(parent)
+}
+
+fn spawn_repeat_node(
+    mut context: SpawnNodeContext<'_>,
+    node: &LayoutNode,
+    name: &str,
+    repeat: &RepeatNodeData,
+    patch: &NodePatch,
+) -> Result<Entity, SpawnLayoutError> {
+    let id = context.parent.join(name);
+    for path in repeat.overrides.keys() {
+        let path = Utf8Path::new(&node.id).join(path);
+        if let NodePathTarget::Missing = resolve_node_path(std::slice::from_ref(node), &path) {
+            log::warn!(
+                "Repeat node {} overrides node `{path}`, which is not in any of its items",
+                id.qualified().display()
+            );
+        }
+    }
+
+    let item_overrides = with_node_overrides(&id, &repeat.overrides, context.overrides);
+
+    let parent = context
+        .world
+        .spawn((
+            TransformBundle::default(),
+            VisibilityBundle::default(),
+            node_component(node, patch),
+            NodeKind::Repeat,
+            context.root,
+            id.clone(),
+            context.layers,
+            ZIndex::default(),
+            LayoutInfo {
+                resolution_scale: Vec2::ONE,
+                canvas_size: node.size,
+                child_anchor: crate::node::Anchor::TopLeft,
+            },
+            RepeatInfo {
+                count: repeat.count,
+                grid: repeat.grid,
+                template: repeat.template.clone(),
+            },
+        ))
+        .id();
+
+    let layout = context.parent_layout;
+    let variables = context.variables;
+    let mut children = vec![];
+
+    for index in 0..repeat.count {
+        let child = spawn_named_node(
+            context.reborrow_with_layout(name, layout, &item_overrides, variables),
+            &repeat.template,
+            &repeat.item_id(index),
+            repeat.grid.item_offset(index),
+        )
+        .map_err(|e| despawn_partial_node(context.world, parent, e))?;
+        context.world.entity_mut(parent).add_child(child);
+        children.push(child);
+    }
+
+    for (index, child) in children.into_iter().enumerate() {
+        let overrides = overrides_for(&item_overrides, &id.join(&repeat.item_id(index)));
         let child = NodeEntityMut::new(context.world, child);
-        (context.visitor)(node, child);
+        (context.visitor)(&repeat.template, &overrides, child);
     }
 
     Ok(parent)
@@ -236,23 +531,160 @@ pub(crate) fn spawn_node(
     context: SpawnNodeContext<'_>,
     node: &LayoutNode,
 ) -> Result<Entity, SpawnLayoutError> {
+    spawn_named_node(context, node, &node.id, Vec2::ZERO)
+}
+
+/// Spawns `node` with the id `name`, moved by `offset` from its position
+///
+/// The items of a `Repeat` node are all spawned from its template this way.
+fn spawn_named_node(
+    context: SpawnNodeContext<'_>,
+    node: &LayoutNode,
+    name: &str,
+    offset: Vec2,
+) -> Result<Entity, SpawnLayoutError> {
+    let id = context.parent.join(name);
+    let overrides = overrides_for(context.overrides, &id);
+
+    let kind = node.inner.node_kind();
+    for node_override in overrides.iter() {
+        let mismatched = if node_override.text.is_some() && kind != NodeKind::Text {
+            Some("text")
+        } else if node_override.image.is_some() && kind != NodeKind::Image {
+            Some("image")
+        } else if node_override.tint.is_some() && !matches!(kind, NodeKind::Image | NodeKind::Text)
+        {
+            Some("tint")
+        } else {
+            None
+        };
+
+        if let Some(field) = mismatched {
+            log::warn!(
+                "Ignoring the {field} override of node {}, which is a {kind:?} node",
+                id.qualified().display()
+            );
+        }
+    }
+
+    let patch = NodePatch::new(&id, node, offset, context.variables, &overrides);
     let entity = match &node.inner {
-        LayoutNodeInner::Null => spawn_null_node(context, node),
-        LayoutNodeInner::Image(image) => spawn_image_node(context, node, image),
-        LayoutNodeInner::Text(text) => spawn_text_node(context, node, text),
-        LayoutNodeInner::Layout(layout) => spawn_layout_node(context, node, layout)?,
-        LayoutNodeInner::Group(group) => spawn_group_node(context, node, group)?,
+        LayoutNodeInner::Null => spawn_null_node(context, node, name, &patch),
+        LayoutNodeInner::Image(image) => spawn_image_node(context, node, name, image, &patch),
+        LayoutNodeInner::Text(text) => spawn_text_node(context, node, name, text, &patch),
+        LayoutNodeInner::Layout(layout) => spawn_layout_node(context, node, name, layout, &patch)?,
+        LayoutNodeInner::Group(group) => spawn_group_node(context, node, name, group, &patch)?,
+        LayoutNodeInner::Repeat(repeat) => spawn_repeat_node(context, node, name, repeat, &patch)?,
     };
 
     Ok(entity)
 }
 
+/// Spawns `node` with the id `name` as the last child of the `parent` node, which must already be
+/// part of a spawned layout tree. The node is moved by `offset` from its position.
+///
+/// The new node (and all of its descendants) inherit the [`LayoutId`], [`RenderLayers`] and
+/// [`LayoutNodeId`] path of the parent, and have all of their attributes applied. Fails with
+/// [`SpawnLayoutError::DuplicateChild`] if the parent already has a child named `name`.
+pub(crate) fn spawn_child_node(
+    world: &mut World,
+    parent: Entity,
+    node: &LayoutNode,
+    name: &str,
+    offset: Vec2,
+) -> Result<Entity, SpawnLayoutError> {
+    let parent_ref = world.entity(parent);
+    let root = *parent_ref
+        .get::<LayoutId>()
+        .ok_or(SpawnLayoutError::NotANode(parent))?;
+    let parent_id = parent_ref
+        .get::<LayoutNodeId>()
+        .cloned()
+        .ok_or(SpawnLayoutError::NotANode(parent))?;
+    let layers = parent_ref
+        .get::<RenderLayers>()
+        .copied()
+        .unwrap_or_default();
+
+    // Paths have to point at a single node, so siblings can't share a name
+    let is_duplicate = parent_ref
+        .get::<Children>()
+        .into_iter()
+        .flat_map(|children| children.iter())
+        .any(|child| {
+            world
+                .get::<LayoutNodeId>(*child)
+                .is_some_and(|id| id.name() == name)
+        });
+    if is_duplicate {
+        return Err(SpawnLayoutError::DuplicateChild(parent, name.to_string()));
+    }
+
+    // Resolution scaling for sublayouts is relative to the closest layout that contains
+    // the parent, which may be the parent itself
+    let mut current = parent;
+    let layout_handle = loop {
+        let entity = world.entity(current);
+        if let Some(handle) = entity.get::<LayoutHandle>() {
+            break handle.0.clone();
+        }
+
+        current = entity
+            .get::<Parent>()
+            .ok_or(SpawnLayoutError::NotANode(current))?
+            .get();
+    };
+
+    world.resource_scope::<Assets<Layout>, _>(|world, assets| {
+        let parent_layout = assets
+            .get(layout_handle.id())
+            .ok_or(SpawnLayoutError::NotLoaded)?;
+
+        let mut visitor =
+            |node: &LayoutNode, overrides: &[&NodeOverride], mut child: NodeEntityMut| {
+                record_attributes(node, overrides, child.reborrow());
+                for attribute in node.attributes_with_overrides(overrides) {
+                    attribute.apply(child.reborrow());
+                }
+            };
+
+        let child = spawn_named_node(
+            SpawnNodeContext {
+                world,
+                assets: &assets,
+                visitor: &mut visitor,
+                root,
+                parent: parent_id,
+                layers,
+                parent_layout,
+                overrides: &[],
+                variables: &[],
+            },
+            node,
+            name,
+            offset,
+        )?;
+
+        world.entity_mut(parent).add_child(child);
+        visitor(node, &[], NodeEntityMut::new(world, child));
+
+        Ok(child)
+    })
+}
+
 pub fn spawn_layout(
     world: &mut World,
     root: Entity,
     handle: Handle<Layout>,
-    mut visitor: impl FnMut(&LayoutNode, NodeEntityMut),
+    mut visitor: impl FnMut(&LayoutNode, &[&NodeOverride], NodeEntityMut),
 ) -> Result<(), SpawnLayoutError> {
+    // Attributes are recorded whatever the visitor does with them, so that every node is torn
+    // down with the attributes it was spawned with
+    let mut visitor = |node: &LayoutNode, overrides: &[&NodeOverride], mut child: NodeEntityMut| {
+        record_attributes(node, overrides, child.reborrow());
+        visitor(node, overrides, child);
+    };
+
     world.resource_scope::<Assets<Layout>, _>(|world, assets| {
         let asset = assets.get(handle.id()).ok_or(SpawnLayoutError::NotLoaded)?;
 
@@ -299,9 +731,23 @@ pub fn spawn_layout(
                     parent: LayoutNodeId::root(),
                     layers,
                     parent_layout: asset,
+                    overrides: &[],
+                    variables: &[],
                 },
                 node,
-            )?;
+            );
+
+            // The nodes that were already spawned would be spawned again on a retry
+            let child = match child {
+                Ok(child) => child,
+                Err(e) => {
+                    for child in children {
+                        despawn_node_tree(world, child);
+                    }
+
+                    return Err(e);
+                }
+            };
 
             world.entity_mut(root).add_child(child);
             children.push(child);
@@ -309,7 +755,7 @@ pub fn spawn_layout(
 
         for (node, child) in asset.nodes.iter().zip(children.into_iter()) {
             let child = NodeEntityMut::new(world, child);
-            (visitor)(node, child);
+            (visitor)(node, &[], child);
         }
 
         Ok(())
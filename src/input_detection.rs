@@ -6,6 +6,7 @@ use bevy::{
     window::{PrimaryWindow, WindowRef},
 };
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 
 use crate::{
     components::RootNode,
@@ -20,7 +21,7 @@ const fn default_true() -> bool {
     true
 }
 
-#[derive(Serialize, Deserialize, Reflect)]
+#[derive(Serialize, Deserialize, Reflect, Clone)]
 pub struct InputDetection {
     #[serde(default = "default_true")]
     use_camera_window: bool,
@@ -197,6 +198,57 @@ impl EntityCommand for CallEventHandlerCommand {
     }
 }
 
+/// Runs the release handlers (unhover/unclick) for every cursor and button that is currently
+/// active on the node, and resets the input detection state
+///
+/// This is used when a node is despawned so that handlers which track state across
+/// press/release pairs are not left dangling
+pub(crate) fn release_input_detection(world: &mut World, entity: Entity) {
+    let Some(mut detection) = world.get_mut::<LayoutNodeInputDetection>(entity) else {
+        return;
+    };
+
+    let mut events: SmallVec<[(EventKind, Cursor); 4]> = SmallVec::new();
+    for (cursor, state) in detection.state.iter_mut() {
+        let state = std::mem::take(state);
+        if state.is_hover {
+            events.push((EventKind::Unhover, *cursor));
+        }
+        if state.is_left {
+            events.push((EventKind::Unclick, *cursor));
+        }
+        if state.is_right {
+            events.push((EventKind::RightUnclick, *cursor));
+        }
+        if state.is_middle {
+            events.push((EventKind::MiddleUnclick, *cursor));
+        }
+    }
+
+    let global_state = std::mem::take(&mut detection.global_state);
+    let mut global_events: SmallVec<[EventKind; 4]> = SmallVec::new();
+    if global_state.hover_count > 0 {
+        global_events.push(EventKind::Unhover);
+    }
+    if global_state.left_count > 0 {
+        global_events.push(EventKind::Unclick);
+    }
+    if global_state.right_count > 0 {
+        global_events.push(EventKind::RightUnclick);
+    }
+    if global_state.middle_count > 0 {
+        global_events.push(EventKind::MiddleUnclick);
+    }
+
+    for (event, cursor) in events {
+        CallEventHandlerCommand::new(event, cursor).apply(entity, world);
+    }
+
+    for event in global_events {
+        CallGlobalEventHandlerCommand(event).apply(entity, world);
+    }
+}
+
 type EventHandlerList =
     Vec<Box<dyn FnMut(EventKind, Cursor, NodeEntityMut) + Send + Sync + 'static>>;
 
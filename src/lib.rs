@@ -1,25 +1,30 @@
 use animation::{DynamicAnimationTarget, LayoutAnimation, LayoutAnimationTarget, StaticTypeInfo};
-use asset::{Layout, LayoutLoader};
+use asset::{Layout, LayoutLoader, LayoutSaver};
 use bevy::{
     app::App,
-    asset::{meta::Settings, Asset, AssetApp, AssetPath, Handle, LoadContext, UntypedAssetId},
+    asset::{
+        meta::Settings, processor::LoadAndSave, Asset, AssetApp, AssetPath, ErasedLoadedAsset,
+        Handle, LoadContext, LoadDirectError, ReadAssetBytesError, UntypedAssetId,
+    },
     ecs::{schedule::ScheduleLabel, system::Resource},
     prelude::*,
     render::view::VisibilitySystems,
     transform::TransformSystem,
-    utils::HashMap,
+    utils::{BoxedFuture, HashMap},
 };
 use builtin::{
     ColorAnimation, PositionAnimation, RotationAnimation, ScaleAnimation, SizeAnimation,
 };
-use components::{LoadedLayout, NodeKind};
+use components::{despawning::DespawnLayout, LayoutLoadFailed, LoadedLayout, NodeKind};
 use input_detection::{controller::UiInputMap, InputDetection};
 use node::LayoutInfo;
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, ser::Error as _, Serialize};
 use std::{
     any::TypeId,
+    path::Path,
     sync::{Arc, RwLock},
 };
+use thiserror::Error;
 use views::NodeEntityMut;
 
 pub mod animation;
@@ -43,8 +48,25 @@ pub struct DynamicAttribute {
     //          exclusive reference on the data passed in
     initialize_dependencies: unsafe fn(*mut (), &mut RestrictedLoadContext),
     // SAFETY: The caller must ensure that the data provided to this function via pointer
+    //          is the same type as what was used to create the function, and also that it has an
+    //          exclusive reference on the data passed in for as long as the future is alive
+    load_dependencies: for<'a> unsafe fn(
+        *mut (),
+        &'a mut RestrictedLoadContext,
+    ) -> BoxedFuture<'a, Result<(), AttributeLoadError>>,
+    // SAFETY: The caller must ensure that the data provided to this function via pointer
     //          is the same type as what was used to create the function
     visit_dependencies: unsafe fn(*const (), &mut dyn FnMut(UntypedAssetId)),
+    // SAFETY: The caller must ensure that the data provided to this function via pointer
+    //          is the same type as what was used to create the function
+    on_despawn: unsafe fn(*const (), NodeEntityMut),
+    // SAFETY: The caller must ensure that the data provided to this function via pointer
+    //          is the same type as what was used to create the function
+    clone: Option<unsafe fn(*const ()) -> *mut ()>,
+    // SAFETY: The caller must ensure that the data provided to this function via pointer
+    //          is the same type as what was used to create the function, and that the data is
+    //          not used again afterwards
+    drop: unsafe fn(*mut ()),
 }
 
 unsafe impl Send for DynamicAttribute {}
@@ -58,7 +80,7 @@ impl DynamicAttribute {
                 type_path: T::short_type_path(),
                 type_id: TypeId::of::<T>(),
             },
-            data: (Box::leak(Box::new(data)) as *mut T).cast(),
+            data: Box::into_raw(Box::new(data)).cast(),
             // We cannot create unsafe closures, but this gets coerced from {{closure}} -> fn(...)
             // -> unsafe fn(...)
             apply: |data, node| unsafe {
@@ -69,13 +91,33 @@ impl DynamicAttribute {
                 let data = &mut *data.cast::<T>();
                 data.initialize_dependencies(context)
             },
+            load_dependencies: |data, context| unsafe {
+                let data = &mut *data.cast::<T>();
+                data.load_dependencies(context)
+            },
             visit_dependencies: |data, visit_fn| unsafe {
                 let data = &*data.cast::<T>();
                 data.visit_dependencies(visit_fn)
             },
+            on_despawn: |data, node| unsafe {
+                let data = &*data.cast::<T>();
+                data.on_despawn(node)
+            },
+            clone: None,
+            drop: |data| unsafe { drop(Box::from_raw(data.cast::<T>())) },
         }
     }
 
+    /// Creates an attribute that can be copied with [`try_clone`](Self::try_clone)
+    pub(crate) fn new_cloneable<T: LayoutAttribute + Clone>(data: T) -> Self {
+        let mut attribute = Self::new(data);
+        attribute.clone = Some(|data| unsafe {
+            let data = &*data.cast::<T>();
+            Box::into_raw(Box::new(data.clone())).cast()
+        });
+        attribute
+    }
+
     pub fn name(&self) -> &str {
         self.type_info.name
     }
@@ -88,6 +130,12 @@ impl DynamicAttribute {
         self.type_info.type_path
     }
 
+    /// Gets a reference to the attribute's data, if it is of type `T`
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        // SAFETY: The type ids match, so the data was created from a `T`
+        (self.type_info.type_id == TypeId::of::<T>()).then(|| unsafe { &*self.data.cast::<T>() })
+    }
+
     pub fn apply(&self, node: NodeEntityMut) {
         // SAFETY: We are using the data that we created when we made this object, so it will be
         // the same type
@@ -101,21 +149,73 @@ impl DynamicAttribute {
         unsafe { (self.initialize_dependencies)(self.data, context) }
     }
 
+    pub fn load_dependencies<'a>(
+        &'a mut self,
+        context: &'a mut RestrictedLoadContext,
+    ) -> BoxedFuture<'a, Result<(), AttributeLoadError>> {
+        // SAFETY: See same safety comments as above, the future borrows this attribute
+        //          exclusively for as long as it is alive
+        unsafe { (self.load_dependencies)(self.data, context) }
+    }
+
     pub fn visit_dependencies(&self, visit_fn: &mut dyn FnMut(UntypedAssetId)) {
         // SAFETY: See same safety comments as above
         unsafe { (self.visit_dependencies)(self.data, visit_fn) }
     }
+
+    pub fn on_despawn(&self, node: NodeEntityMut) {
+        // SAFETY: See same safety comments as above
+        unsafe { (self.on_despawn)(self.data, node) }
+    }
+
+    /// Whether the attribute's type was registered as cloneable, see
+    /// [`LayoutRegistry::register_cloneable_attribute`]
+    pub fn is_cloneable(&self) -> bool {
+        self.clone.is_some()
+    }
+
+    /// Copies this attribute, if its type was registered as cloneable
+    pub fn try_clone(&self) -> Option<Self> {
+        let clone = self.clone?;
+        Some(Self {
+            type_info: self.type_info,
+            // SAFETY: See same safety comments as above
+            data: unsafe { clone(self.data) },
+            apply: self.apply,
+            initialize_dependencies: self.initialize_dependencies,
+            load_dependencies: self.load_dependencies,
+            visit_dependencies: self.visit_dependencies,
+            on_despawn: self.on_despawn,
+            clone: self.clone,
+            drop: self.drop,
+        })
+    }
 }
 
-/// Manages registered deserialization methods for attributes
+impl Drop for DynamicAttribute {
+    fn drop(&mut self) {
+        // SAFETY: We own the data that we created when we made this object, and nothing can use it
+        //          once we are dropped
+        unsafe { (self.drop)(self.data) }
+    }
+}
+
+/// Manages registered (de)serialization methods for attributes
 pub(crate) struct RegisteredAttributeData {
+    type_id: TypeId,
+    type_path: &'static str,
     deserialize: fn(serde_value::Value) -> Result<DynamicAttribute, serde_value::DeserializerError>,
+    serialize: fn(&DynamicAttribute) -> Result<serde_value::Value, serde_value::SerializerError>,
 }
 
-/// Manages registered deserialization methods for animations
+/// Manages registered (de)serialization methods for animations
 pub(crate) struct RegisteredAnimationData {
+    type_id: TypeId,
+    type_path: &'static str,
     deserialize:
         fn(serde_value::Value) -> Result<DynamicAnimationTarget, serde_value::DeserializerError>,
+    serialize:
+        fn(&DynamicAnimationTarget) -> Result<serde_value::Value, serde_value::SerializerError>,
 }
 
 /// Internal registry of layout animations/attributes
@@ -160,6 +260,120 @@ impl LayoutRegistry {
     }
 }
 
+impl RegisteredAttributeData {
+    fn new<A: LayoutAttribute + Serialize + DeserializeOwned>() -> Self {
+        Self {
+            type_id: TypeId::of::<A>(),
+            type_path: A::type_path(),
+            deserialize: |value| {
+                A::deserialize(serde_value::ValueDeserializer::<
+                    serde_value::DeserializerError,
+                >::new(value))
+                .map(|v| DynamicAttribute::new(v))
+            },
+            serialize: |attribute| match attribute.downcast_ref::<A>() {
+                Some(attribute) => serde_value::to_value(attribute),
+                None => Err(serde_value::SerializerError::custom(format!(
+                    "attribute '{}' is not a {}",
+                    attribute.name(),
+                    A::short_type_path()
+                ))),
+            },
+        }
+    }
+
+    fn cloneable<A: LayoutAttribute + Serialize + DeserializeOwned + Clone>() -> Self {
+        Self {
+            deserialize: |value| {
+                A::deserialize(serde_value::ValueDeserializer::<
+                    serde_value::DeserializerError,
+                >::new(value))
+                .map(|v| DynamicAttribute::new_cloneable(v))
+            },
+            ..Self::new::<A>()
+        }
+    }
+}
+
+impl RegisteredAnimationData {
+    fn new<A: LayoutAnimationTarget + Serialize + DeserializeOwned>() -> Self {
+        Self {
+            type_id: TypeId::of::<A>(),
+            type_path: A::type_path(),
+            deserialize: |value| {
+                A::deserialize(serde_value::ValueDeserializer::<
+                    serde_value::DeserializerError,
+                >::new(value))
+                .map(|v| DynamicAnimationTarget::new(v))
+            },
+            serialize: |target| match target.downcast_ref::<A>() {
+                Some(target) => serde_value::to_value(target),
+                None => Err(serde_value::SerializerError::custom(format!(
+                    "animation target '{}' is not a {}",
+                    target.name(),
+                    A::short_type_path()
+                ))),
+            },
+        }
+    }
+
+    fn cloneable<A: LayoutAnimationTarget + Serialize + DeserializeOwned + Clone>() -> Self {
+        Self {
+            deserialize: |value| {
+                A::deserialize(serde_value::ValueDeserializer::<
+                    serde_value::DeserializerError,
+                >::new(value))
+                .map(|v| DynamicAnimationTarget::new_cloneable(v))
+            },
+            ..Self::new::<A>()
+        }
+    }
+}
+
+/// A type that is registered with the [`LayoutRegistry`], under the name it is written with in
+/// layout files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredType {
+    pub name: String,
+    pub type_path: &'static str,
+}
+
+/// An error that occurred while registering a type with the [`LayoutRegistry`]
+#[derive(Error, Debug)]
+pub enum LayoutRegistryError {
+    #[error(
+        "Attribute '{name}' is already registered by {registered}, so {rejected} can't be registered with the same name"
+    )]
+    AttributeCollision {
+        name: String,
+        registered: &'static str,
+        rejected: &'static str,
+    },
+
+    #[error(
+        "Animation target '{name}' is already registered by {registered}, so {rejected} can't be registered with the same name"
+    )]
+    AnimationCollision {
+        name: String,
+        registered: &'static str,
+        rejected: &'static str,
+    },
+}
+
+/// Lists registered types, sorted by name
+fn registered_types<'a>(
+    entries: impl Iterator<Item = (&'a String, &'static str)>,
+) -> Vec<RegisteredType> {
+    let mut types: Vec<RegisteredType> = entries
+        .map(|(name, type_path)| RegisteredType {
+            name: name.clone(),
+            type_path,
+        })
+        .collect();
+    types.sort_by(|a, b| a.name.cmp(&b.name));
+    types
+}
+
 impl LayoutRegistry {
     /// Registers an attribute for use with a layout asset
     ///
@@ -167,19 +381,121 @@ impl LayoutRegistry {
     /// If the deserializer encounters an attribute name that it does not recognize, it will produce
     /// an error in the deserializer and the asset will fail to load.
     ///
+    /// Attributes are also serialized when a layout is written back out with [`Layout::to_json`],
+    /// which is why they need to implement [`Serialize`]. Attributes that only implemented
+    /// [`Deserialize`](serde::Deserialize) before have to derive it as well.
+    ///
+    /// If another type is already registered with the same [`NAME`](LayoutAttribute::NAME), it is
+    /// replaced and a warning is logged. Use [`try_register_attribute`](Self::try_register_attribute)
+    /// to keep the type that was registered first instead.
+    ///
     /// For more information, see the [`LayoutAttribute`] trait.
-    pub fn register_attribute<A: LayoutAttribute + DeserializeOwned>(&self) {
-        self.inner.write().unwrap().attributes.insert(
-            A::NAME.to_string(),
-            RegisteredAttributeData {
-                deserialize: |value| {
-                    A::deserialize(serde_value::ValueDeserializer::<
-                        serde_value::DeserializerError,
-                    >::new(value))
-                    .map(|v| DynamicAttribute::new(v))
-                },
-            },
-        );
+    pub fn register_attribute<A: LayoutAttribute + Serialize + DeserializeOwned>(&self) {
+        self.insert_attribute(A::NAME, RegisteredAttributeData::new::<A>());
+    }
+
+    /// Registers an attribute whose loaded instances can be copied with
+    /// [`DynamicAttribute::try_clone`]
+    ///
+    /// See [`register_attribute`](Self::register_attribute) for more information.
+    pub fn register_cloneable_attribute<
+        A: LayoutAttribute + Serialize + DeserializeOwned + Clone,
+    >(
+        &self,
+    ) {
+        self.insert_attribute(A::NAME, RegisteredAttributeData::cloneable::<A>());
+    }
+
+    fn insert_attribute(&self, name: &str, data: RegisteredAttributeData) {
+        let type_id = data.type_id;
+        let type_path = data.type_path;
+        let previous = self
+            .inner
+            .write()
+            .unwrap()
+            .attributes
+            .insert(name.to_string(), data);
+
+        if let Some(previous) = previous.filter(|data| data.type_id != type_id) {
+            log::warn!(
+                "Attribute '{}' was registered by {}, it is now registered by {}",
+                name,
+                previous.type_path,
+                type_path
+            );
+        }
+    }
+
+    /// Registers an attribute, failing if another type is already registered with the same
+    /// [`NAME`](LayoutAttribute::NAME)
+    ///
+    /// Registering the same type again replaces its registration. See
+    /// [`register_attribute`](Self::register_attribute) for more information.
+    pub fn try_register_attribute<A: LayoutAttribute + Serialize + DeserializeOwned>(
+        &self,
+    ) -> Result<(), LayoutRegistryError> {
+        self.try_insert_attribute(A::NAME, RegisteredAttributeData::new::<A>())
+    }
+
+    /// Registers a cloneable attribute, failing if another type is already registered with the
+    /// same [`NAME`](LayoutAttribute::NAME)
+    ///
+    /// See [`try_register_attribute`](Self::try_register_attribute) and
+    /// [`register_cloneable_attribute`](Self::register_cloneable_attribute) for more information.
+    pub fn try_register_cloneable_attribute<
+        A: LayoutAttribute + Serialize + DeserializeOwned + Clone,
+    >(
+        &self,
+    ) -> Result<(), LayoutRegistryError> {
+        self.try_insert_attribute(A::NAME, RegisteredAttributeData::cloneable::<A>())
+    }
+
+    fn try_insert_attribute(
+        &self,
+        name: &str,
+        data: RegisteredAttributeData,
+    ) -> Result<(), LayoutRegistryError> {
+        let mut inner = self.inner.write().unwrap();
+        match inner.attributes.get(name) {
+            Some(registered) if registered.type_id != data.type_id => {
+                Err(LayoutRegistryError::AttributeCollision {
+                    name: name.to_string(),
+                    registered: registered.type_path,
+                    rejected: data.type_path,
+                })
+            }
+            _ => {
+                inner.attributes.insert(name.to_string(), data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Unregisters an attribute, so that layouts loaded from now on can't use it
+    ///
+    /// Nothing happens if another type is registered under its name. Returns whether the
+    /// attribute was registered.
+    pub fn unregister_attribute<A: LayoutAttribute>(&self) -> bool {
+        let mut inner = self.inner.write().unwrap();
+        match inner.attributes.get(A::NAME) {
+            Some(data) if data.type_id == TypeId::of::<A>() => {
+                inner.attributes.remove(A::NAME);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Every registered attribute, sorted by name
+    pub fn attributes(&self) -> Vec<RegisteredType> {
+        registered_types(
+            self.inner
+                .read()
+                .unwrap()
+                .attributes
+                .iter()
+                .map(|(name, data)| (name, data.type_path)),
+        )
     }
 
     /// Registers an animation for use with a layout asset
@@ -188,19 +504,121 @@ impl LayoutRegistry {
     /// If the deserializer encounters an animation name that it does not recognize, it will produce
     /// an error in the deserializer and the asset will fail to load.
     ///
+    /// Animation targets are also serialized when a layout is written back out with
+    /// [`Layout::to_json`], so like attributes they need to implement [`Serialize`].
+    ///
+    /// If another type is already registered with the same
+    /// [`NAME`](LayoutAnimationTarget::NAME), it is replaced and a warning is logged. Use
+    /// [`try_register_animation`](Self::try_register_animation) to keep the type that was
+    /// registered first instead.
+    ///
     /// For more information, see the [`LayoutAnimation`] trait.
-    pub fn register_animation<A: LayoutAnimationTarget + DeserializeOwned>(&self) {
-        self.inner.write().unwrap().animations.insert(
-            A::NAME.to_string(),
-            RegisteredAnimationData {
-                deserialize: |value| {
-                    A::deserialize(serde_value::ValueDeserializer::<
-                        serde_value::DeserializerError,
-                    >::new(value))
-                    .map(|v| DynamicAnimationTarget::new(v))
-                },
-            },
-        );
+    pub fn register_animation<A: LayoutAnimationTarget + Serialize + DeserializeOwned>(&self) {
+        self.insert_animation(A::NAME, RegisteredAnimationData::new::<A>());
+    }
+
+    /// Registers an animation whose loaded keyframe targets can be copied with
+    /// [`DynamicAnimationTarget::try_clone`]
+    ///
+    /// See [`register_animation`](Self::register_animation) for more information.
+    pub fn register_cloneable_animation<
+        A: LayoutAnimationTarget + Serialize + DeserializeOwned + Clone,
+    >(
+        &self,
+    ) {
+        self.insert_animation(A::NAME, RegisteredAnimationData::cloneable::<A>());
+    }
+
+    fn insert_animation(&self, name: &str, data: RegisteredAnimationData) {
+        let type_id = data.type_id;
+        let type_path = data.type_path;
+        let previous = self
+            .inner
+            .write()
+            .unwrap()
+            .animations
+            .insert(name.to_string(), data);
+
+        if let Some(previous) = previous.filter(|data| data.type_id != type_id) {
+            log::warn!(
+                "Animation target '{}' was registered by {}, it is now registered by {}",
+                name,
+                previous.type_path,
+                type_path
+            );
+        }
+    }
+
+    /// Registers an animation, failing if another type is already registered with the same
+    /// [`NAME`](LayoutAnimationTarget::NAME)
+    ///
+    /// Registering the same type again replaces its registration. See
+    /// [`register_animation`](Self::register_animation) for more information.
+    pub fn try_register_animation<A: LayoutAnimationTarget + Serialize + DeserializeOwned>(
+        &self,
+    ) -> Result<(), LayoutRegistryError> {
+        self.try_insert_animation(A::NAME, RegisteredAnimationData::new::<A>())
+    }
+
+    /// Registers a cloneable animation, failing if another type is already registered with the
+    /// same [`NAME`](LayoutAnimationTarget::NAME)
+    ///
+    /// See [`try_register_animation`](Self::try_register_animation) and
+    /// [`register_cloneable_animation`](Self::register_cloneable_animation) for more information.
+    pub fn try_register_cloneable_animation<
+        A: LayoutAnimationTarget + Serialize + DeserializeOwned + Clone,
+    >(
+        &self,
+    ) -> Result<(), LayoutRegistryError> {
+        self.try_insert_animation(A::NAME, RegisteredAnimationData::cloneable::<A>())
+    }
+
+    fn try_insert_animation(
+        &self,
+        name: &str,
+        data: RegisteredAnimationData,
+    ) -> Result<(), LayoutRegistryError> {
+        let mut inner = self.inner.write().unwrap();
+        match inner.animations.get(name) {
+            Some(registered) if registered.type_id != data.type_id => {
+                Err(LayoutRegistryError::AnimationCollision {
+                    name: name.to_string(),
+                    registered: registered.type_path,
+                    rejected: data.type_path,
+                })
+            }
+            _ => {
+                inner.animations.insert(name.to_string(), data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Unregisters an animation, so that layouts loaded from now on can't use it
+    ///
+    /// Nothing happens if another type is registered under its name. Returns whether the
+    /// animation was registered.
+    pub fn unregister_animation<A: LayoutAnimationTarget>(&self) -> bool {
+        let mut inner = self.inner.write().unwrap();
+        match inner.animations.get(A::NAME) {
+            Some(data) if data.type_id == TypeId::of::<A>() => {
+                inner.animations.remove(A::NAME);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Every registered animation target, sorted by name
+    pub fn animations(&self) -> Vec<RegisteredType> {
+        registered_types(
+            self.inner
+                .read()
+                .unwrap()
+                .animations
+                .iter()
+                .map(|(name, data)| (name, data.type_path)),
+        )
     }
 }
 
@@ -210,6 +628,9 @@ pub struct RestrictedLoadContext<'a, 'b> {
     pub(crate) load_context: &'a mut LoadContext<'b>,
 }
 
+/// An error returned by [`LayoutAttribute::load_dependencies`]
+pub type AttributeLoadError = Box<dyn std::error::Error + Send + Sync>;
+
 impl<'a, 'b> RestrictedLoadContext<'a, 'b> {
     /// Loads an asset by path and with the default settings.
     ///
@@ -228,6 +649,48 @@ impl<'a, 'b> RestrictedLoadContext<'a, 'b> {
     ) -> Handle<A> {
         self.load_context.load_with_settings(path, update)
     }
+
+    /// The path of the layout that is being loaded
+    pub fn path(&self) -> &Path {
+        self.load_context.path()
+    }
+
+    /// The full asset path of the layout that is being loaded, including its source
+    pub fn asset_path(&self) -> &AssetPath<'static> {
+        self.load_context.asset_path()
+    }
+
+    /// Reads the bytes of the file at `path`, such as a sidecar file of the layout.
+    ///
+    /// The layout is reloaded when the file changes. For more context, see the
+    /// `read_asset_bytes` method on [`LoadContext`]
+    pub async fn read_asset_bytes<'c>(
+        &mut self,
+        path: impl Into<AssetPath<'c>>,
+    ) -> Result<Vec<u8>, ReadAssetBytesError> {
+        self.load_context.read_asset_bytes(path).await
+    }
+
+    /// Loads the asset at `path` immediately, instead of returning a handle to it.
+    ///
+    /// The layout is reloaded when the asset changes. For more context, see the `load_direct`
+    /// method on [`LoadContext`]
+    pub async fn load_direct<'c>(
+        &mut self,
+        path: impl Into<AssetPath<'c>>,
+    ) -> Result<ErasedLoadedAsset, LoadDirectError> {
+        self.load_context.load_direct(path).await
+    }
+
+    /// Adds `asset` as a labeled sub-asset of the layout, returning a handle to it.
+    ///
+    /// The layout uses the names of its animations as labels, so the label is prefixed with
+    /// `attributes/` to keep them apart (the asset can be loaded as
+    /// `path/to/file.layout.json#attributes/label`).
+    pub fn add_labeled_asset<A: Asset>(&mut self, label: impl AsRef<str>, asset: A) -> Handle<A> {
+        self.load_context
+            .add_labeled_asset(format!("attributes/{}", label.as_ref()), asset)
+    }
 }
 
 /// Due to the strict siloing of layout logic, and the callback based system,
@@ -249,9 +712,21 @@ pub enum LayoutSystems {
     /// layout is attached to) and will recursively spawn in a UI layout once the layout has been
     /// successfully loaded.
     ///
+    /// Layouts that have failed are retried once their layout asset, or one of its dependencies,
+    /// is loaded again.
+    ///
     /// This runs in the [`LayoutSchedule`]
     SpawnLayouts,
 
+    /// Respawns layouts that have already been spawned when their layout asset, or the asset of
+    /// any of their sublayouts, is modified (i.e. when hot-reloading is enabled).
+    ///
+    /// The state of layout animations is kept for every node that still exists after the reload,
+    /// and the on-reload callback of the [`LayoutBundle`] is run once the layout is respawned.
+    ///
+    /// This runs in the [`LayoutSchedule`]
+    ReloadLayouts,
+
     /// Detects changes made to [`ZIndex`] components, and will regenerate a [`ZIndex`] for every
     /// node in the tree.
     ///
@@ -318,6 +793,11 @@ pub enum LayoutSystems {
 /// Plugin to add to an [`App`] that enables support for yabuil layouts
 #[derive(Default)]
 pub struct LayoutPlugin {
+    /// Skips attributes and animation targets that have not been registered instead of failing
+    /// to load the layout
+    ///
+    /// When this is set, layouts that have other problems in them (e.g. invalid nodes) are also
+    /// still loaded without the offending parts, and the problems are logged as warnings.
     pub ignore_unknown_registry_data: bool,
 }
 
@@ -325,12 +805,12 @@ impl Plugin for LayoutPlugin {
     fn build(&self, app: &mut App) {
         let registry = LayoutRegistry::new(self.ignore_unknown_registry_data);
 
-        registry.register_attribute::<InputDetection>();
-        registry.register_animation::<PositionAnimation>();
-        registry.register_animation::<SizeAnimation>();
-        registry.register_animation::<ScaleAnimation>();
-        registry.register_animation::<ColorAnimation>();
-        registry.register_animation::<RotationAnimation>();
+        registry.register_cloneable_attribute::<InputDetection>();
+        registry.register_cloneable_animation::<PositionAnimation>();
+        registry.register_cloneable_animation::<SizeAnimation>();
+        registry.register_cloneable_animation::<ScaleAnimation>();
+        registry.register_cloneable_animation::<ColorAnimation>();
+        registry.register_cloneable_animation::<RotationAnimation>();
 
         // Register the types so that they can be used in reflection (also debugging with bevy_inspector_egui)
         app.register_type::<node::Node>()
@@ -346,11 +826,42 @@ impl Plugin for LayoutPlugin {
             .register_type::<RotationAnimation>()
             .register_type::<InputDetection>()
             .add_event::<LoadedLayout>()
+            .add_event::<LayoutLoadFailed>()
+            .add_event::<animation::LayoutAnimationEvent>()
             .init_resource::<UiInputMap>();
 
         // Register the asset/asset loader
         app.register_asset_loader(LayoutLoader(registry.inner.clone()))
-            .insert_resource(registry)
+            .register_asset_processor(LoadAndSave::<LayoutLoader, _>::from(LayoutSaver(
+                registry.inner.clone(),
+            )));
+
+        #[cfg(feature = "ron")]
+        app.register_asset_loader(asset::RonLayoutLoader(registry.inner.clone()));
+
+        // Cook every layout when the asset processor is enabled
+        #[cfg(feature = "cooked")]
+        {
+            use asset::{CookedLayoutLoader, CookedLayoutSaver};
+
+            app.register_asset_loader(CookedLayoutLoader(registry.inner.clone()))
+                .register_asset_processor(LoadAndSave::<LayoutLoader, _>::from(CookedLayoutSaver(
+                    registry.inner.clone(),
+                )))
+                .set_default_asset_processor::<LoadAndSave<LayoutLoader, CookedLayoutSaver>>(
+                    "layout.json",
+                );
+
+            #[cfg(feature = "ron")]
+            app.register_asset_processor(LoadAndSave::<asset::RonLayoutLoader, _>::from(
+                CookedLayoutSaver(registry.inner.clone()),
+            ))
+            .set_default_asset_processor::<LoadAndSave<asset::RonLayoutLoader, CookedLayoutSaver>>(
+                "layout.ron",
+            );
+        }
+
+        app.insert_resource(registry)
             .init_asset::<Layout>()
             .init_asset::<LayoutAnimation>();
 
@@ -362,6 +873,7 @@ impl Plugin for LayoutPlugin {
             sched.configure_sets(
                 (
                     LayoutSystems::SpawnLayouts,
+                    LayoutSystems::ReloadLayouts,
                     LayoutSystems::FocusDetection,
                     LayoutSystems::InputDetection,
                     LayoutSystems::AnimateLayouts,
@@ -370,7 +882,18 @@ impl Plugin for LayoutPlugin {
             );
 
             sched.add_systems((
-                components::spawn_layout_system.in_set(LayoutSystems::SpawnLayouts),
+                (
+                    components::retry_failed_layouts,
+                    components::spawn_layout_system,
+                )
+                    .chain()
+                    .in_set(LayoutSystems::SpawnLayouts),
+                (
+                    components::detect_modified_layouts,
+                    components::reload_layout_system,
+                )
+                    .chain()
+                    .in_set(LayoutSystems::ReloadLayouts),
                 input_detection::controller::update_focus_nodes
                     .in_set(LayoutSystems::FocusDetection),
                 input_detection::controller::update_input_detection
@@ -423,29 +946,90 @@ pub trait LayoutAttribute: TypePath + Send + Sync + 'static {
     #[allow(unused_variables)]
     fn initialize_dependencies(&mut self, context: &mut RestrictedLoadContext) {}
 
+    /// Runs during asset loading, after [`LayoutAttribute::initialize_dependencies`] has been
+    /// called for every attribute in the layout
+    ///
+    /// Unlike `initialize_dependencies`, this can read other files and load assets directly
+    /// through the [`RestrictedLoadContext`], for attributes that need their contents while
+    /// the layout is loading (e.g. a localization table). If this returns an error, the layout
+    /// fails to load.
+    #[allow(unused_variables)]
+    fn load_dependencies<'a>(
+        &'a mut self,
+        context: &'a mut RestrictedLoadContext,
+    ) -> BoxedFuture<'a, Result<(), AttributeLoadError>> {
+        Box::pin(async { Ok(()) })
+    }
+
     /// Used to help ensure that the [recursive load state](bevy::asset::RecursiveDependencyLoadState)
     /// is accurate and reflects the state of all attributes
     #[allow(unused_variables)]
     fn visit_dependencies(&self, visit_fn: &mut dyn FnMut(UntypedAssetId)) {}
+
+    /// Runs right before a node that has this attribute gets despawned through
+    /// [`LayoutCommands::despawn_layout`] or [`NodeEntityMut::despawn_node`], or when hot
+    /// reloading respawns the layout it is in
+    ///
+    /// This should be used to tear down any state that [`LayoutAttribute::apply`] set up outside
+    /// of the node's entity, such as resources
+    #[allow(unused_variables)]
+    fn on_despawn(&self, world: NodeEntityMut) {}
 }
 
+/// Extension trait for [`App`] to register attributes and animations with the [`LayoutRegistry`]
+///
+/// Both need to implement [`Serialize`] so that layouts can be written back out, see
+/// [`LayoutRegistry::register_attribute`].
 pub trait LayoutApp {
-    fn register_layout_attribute<A: LayoutAttribute + DeserializeOwned>(&mut self) -> &mut Self;
+    fn register_layout_attribute<A: LayoutAttribute + Serialize + DeserializeOwned>(
+        &mut self,
+    ) -> &mut Self;
 
-    fn register_layout_animation<A: LayoutAnimationTarget + DeserializeOwned>(
+    fn register_layout_animation<A: LayoutAnimationTarget + Serialize + DeserializeOwned>(
         &mut self,
     ) -> &mut Self;
 }
 
+/// Extension trait for [`Commands`] to manage spawned layouts
+pub trait LayoutCommands {
+    /// Despawns a layout root that was spawned with a [`LayoutBundle`], along with every node in
+    /// its tree
+    ///
+    /// Unlike `despawn_recursive`, this will call [`LayoutAttribute::on_despawn`] for every
+    /// attribute that the nodes were spawned with (even if the layout asset has changed or is
+    /// gone since) and release any active input detection state before the entities are
+    /// despawned.
+    fn despawn_layout(&mut self, entity: Entity);
+
+    /// Retries spawning a layout root that has failed, see [`LayoutBundle::retry`]
+    fn retry_layout(&mut self, entity: Entity);
+}
+
+impl LayoutCommands for Commands<'_, '_> {
+    fn despawn_layout(&mut self, entity: Entity) {
+        self.add(DespawnLayout(entity));
+    }
+
+    fn retry_layout(&mut self, entity: Entity) {
+        self.add(move |world: &mut World| {
+            if let Some(root) = world.get_entity_mut(entity) {
+                LayoutBundle::retry(root);
+            }
+        });
+    }
+}
+
 impl LayoutApp for App {
-    fn register_layout_attribute<A: LayoutAttribute + DeserializeOwned>(&mut self) -> &mut Self {
+    fn register_layout_attribute<A: LayoutAttribute + Serialize + DeserializeOwned>(
+        &mut self,
+    ) -> &mut Self {
         self.world
             .resource::<LayoutRegistry>()
             .register_attribute::<A>();
         self
     }
 
-    fn register_layout_animation<A: LayoutAnimationTarget + DeserializeOwned>(
+    fn register_layout_animation<A: LayoutAnimationTarget + Serialize + DeserializeOwned>(
         &mut self,
     ) -> &mut Self {
         self.world
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use bevy::{
     ecs::query::{QueryData, WorldQuery},
     math::vec2,
@@ -7,9 +9,9 @@ use bevy::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    asset::{Layout, LayoutNode},
+    asset::{Layout, LayoutNode, RepeatGrid},
     components::{NodeKind, RootNode},
-    LayoutId,
+    DynamicAttribute, LayoutId,
 };
 
 /// The Z Index of a node.
@@ -191,6 +193,26 @@ impl ComputedBoundingBox {
 #[derive(Component, Clone, Reflect)]
 pub struct LayoutHandle(pub Handle<Layout>);
 
+/// Component that holds what is needed to spawn more items of a `Repeat` node at runtime
+#[derive(Component, Clone)]
+pub struct RepeatInfo {
+    /// How many items are currently spawned
+    pub(crate) count: usize,
+
+    /// Where each item is placed, relative to the position of the template
+    pub(crate) grid: RepeatGrid,
+
+    /// The node that each item is spawned from
+    pub(crate) template: Arc<LayoutNode>,
+}
+
+/// Component that holds the attributes that were applied to a node when it was spawned
+///
+/// These are the attributes that are notified when the node is despawned, even if the layout
+/// that the node was spawned from has been modified or unloaded since.
+#[derive(Component, Clone, Default)]
+pub(crate) struct SpawnedAttributes(pub(crate) Vec<Arc<DynamicAttribute>>);
+
 /// Component that contains information about a layout
 #[derive(Component, Copy, Clone, Reflect)]
 pub struct LayoutInfo {
@@ -393,7 +415,10 @@ pub(crate) fn refresh_z_index(
     fn handle_node(query: &Query<RefreshQuery>, entity: Entity, z_value: &mut usize) {
         let mut node = unsafe { query.get_unchecked(entity).unwrap() };
 
-        if matches!(node.kind, NodeKind::Layout | NodeKind::Group) {
+        if matches!(
+            node.kind,
+            NodeKind::Layout | NodeKind::Group | NodeKind::Repeat
+        ) {
             *node.z_index = ZIndex::Calculated(0);
         } else {
             *node.z_index = ZIndex::Calculated(*z_value);
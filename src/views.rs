@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bevy::{
     ecs::{
         archetype::Archetype,
@@ -9,6 +11,7 @@ use bevy::{
     },
     prelude::*,
     ptr::OwningPtr,
+    render::view::RenderLayers,
 };
 
 use camino::Utf8Path;
@@ -16,10 +19,18 @@ use smallvec::SmallVec;
 use thiserror::Error;
 
 use crate::{
-    animation::{LayoutAnimationPlaybackState, PlaybackState},
-    asset::Layout,
-    components::NodeKind,
-    node::Node,
+    animation::{
+        AnimationClock, AnimationPlayOptions, LayoutAnimationPlaybackState, PlaybackState,
+    },
+    asset::{
+        item_id, GroupNodeData, ImageNodeData, Layout, LayoutNode, LayoutNodeData, LayoutNodeInner,
+        TextNodeData,
+    },
+    components::{
+        despawning::despawn_node_tree, spawning::spawn_child_node, NodeKind, RootNode,
+        SpawnLayoutError,
+    },
+    node::{Anchor, Node, RepeatInfo, ZIndex},
     LayoutId, LayoutNodeId,
 };
 
@@ -42,6 +53,18 @@ pub enum NodeEntityError {
 
     #[error("The node {0:?} is missing the root layout id")]
     NoRootId(Entity),
+
+    #[error("The node {0:?} is the root of a layout and cannot be removed or moved")]
+    IsRoot(Entity),
+
+    #[error("The node {0:?} cannot contain child nodes")]
+    NotAContainer(Entity),
+
+    #[error("The node {0:?} already has a child with the name {1}")]
+    DuplicateChild(Entity, String),
+
+    #[error("The node {0:?} cannot be moved into one of its own descendants")]
+    MoveIntoDescendant(Entity),
 }
 
 /// Mutable entity accessor with layout tree traversal capabilities
@@ -279,6 +302,18 @@ impl<'w> NodeEntityMut<'w> {
         self.get_group().expect("node should be a group node")
     }
 
+    pub fn get_repeat<'a>(&'a mut self) -> Option<RepeatNodeMut<'a>> {
+        (*self.get::<NodeKind>().unwrap() == NodeKind::Repeat).then_some(RepeatNodeMut {
+            world: self.world,
+            id: self.id,
+        })
+    }
+
+    #[track_caller]
+    pub fn repeat<'a>(&'a mut self) -> RepeatNodeMut<'a> {
+        self.get_repeat().expect("node should be a repeat node")
+    }
+
     pub fn world(&self) -> &World {
         // SAFETY: We acquire an exclusive reference to the world on construction of this type,
         //          or any of it's parents. Rust's borrow checker will restrict using more than one
@@ -406,6 +441,179 @@ impl<'w> NodeEntityMut<'w> {
         let world = unsafe { world.world_mut() };
         world.entity_mut(id)
     }
+
+    /// Despawns this node and all of its descendants, removing it from the layout tree
+    ///
+    /// Before despawning, [`LayoutAttribute::on_despawn`](crate::LayoutAttribute::on_despawn) is
+    /// called for the attributes of every node in the tree and any active input detection state
+    /// is released. The remaining nodes in the layout will have their
+    /// [`ZIndex`](crate::node::ZIndex) recalculated.
+    ///
+    /// This will fail if this node is the root of a layout, use
+    /// [`LayoutCommands::despawn_layout`](crate::LayoutCommands::despawn_layout) for those instead.
+    pub fn despawn_node(self) -> Result<(), NodeEntityError> {
+        if self.get::<RootNode>().is_some() {
+            return Err(NodeEntityError::IsRoot(self.id));
+        }
+
+        let Self { world, id } = self;
+        // SAFETY: See Self::world_mut
+        let world = unsafe { world.world_mut() };
+        despawn_node_tree(world, id);
+
+        Ok(())
+    }
+
+    /// Removes this node (and all of its descendants) from the layout tree without despawning it
+    ///
+    /// Detached nodes are hidden and keep their [`LayoutId`] so that they can be placed back into
+    /// the same layout tree with [`NodeEntityMut::move_to`]. This will fail if this node is the
+    /// root of a layout.
+    ///
+    /// The detached node is left as an entity without a parent at the top level of the world.
+    /// It is not despawned along with the layout by
+    /// [`LayoutCommands::despawn_layout`](crate::LayoutCommands::despawn_layout), so it must
+    /// either be moved back into the tree or despawned by the caller.
+    pub fn detach(&mut self) -> Result<(), NodeEntityError> {
+        if self.get::<RootNode>().is_some() {
+            return Err(NodeEntityError::IsRoot(self.id));
+        }
+
+        if self.get::<DetachedNode>().is_some() {
+            return Ok(());
+        }
+
+        let root = self.get::<LayoutId>().copied();
+        let visibility = self.get::<Visibility>().copied().unwrap_or_default();
+
+        let id = self.id;
+        let world = self.world_mut();
+        world
+            .entity_mut(id)
+            .remove_parent()
+            .insert((DetachedNode(visibility), Visibility::Hidden));
+
+        if let Some(mut z_index) = root.and_then(|root| world.get_mut::<ZIndex>(root.0)) {
+            *z_index = ZIndex::NeedsRecalculation;
+        }
+
+        Ok(())
+    }
+
+    /// Moves this node (and all of its descendants) so that it is the child at `index` of the node
+    /// found at `group_path`, relative to the root of the layout
+    ///
+    /// The target must be a group or layout node. The [`LayoutNodeId`] and [`RenderLayers`] of
+    /// the moved nodes are updated to reflect the new position in the tree, and the
+    /// [`ZIndex`](crate::node::ZIndex) of the layout is recalculated.
+    ///
+    /// This can also be used to reinsert a node that was removed with [`NodeEntityMut::detach`].
+    pub fn move_to(
+        &mut self,
+        group_path: impl AsRef<Utf8Path>,
+        index: usize,
+    ) -> Result<(), NodeEntityError> {
+        if self.get::<RootNode>().is_some() {
+            return Err(NodeEntityError::IsRoot(self.id));
+        }
+
+        let id = self.id;
+        let name = self.get::<LayoutNodeId>().unwrap().name().to_string();
+        let root = *self
+            .get::<LayoutId>()
+            .ok_or(NodeEntityError::NoRootId(id))?;
+
+        let world = self.world_mut();
+        let target = find_child_id(world, root.0, group_path.as_ref())?;
+        let target_ref = world.entity(target);
+        if !matches!(
+            target_ref.get::<NodeKind>(),
+            Some(NodeKind::Group | NodeKind::Layout)
+        ) {
+            return Err(NodeEntityError::NotAContainer(target));
+        }
+
+        let mut current = Some(target);
+        while let Some(entity) = current {
+            if entity == id {
+                return Err(NodeEntityError::MoveIntoDescendant(id));
+            }
+
+            current = world.entity(entity).get::<Parent>().map(Parent::get);
+        }
+
+        let siblings: SmallVec<[Entity; 8]> = target_ref
+            .get::<Children>()
+            .iter()
+            .flat_map(|children| children.iter().copied())
+            .filter(|child| *child != id)
+            .collect();
+
+        for sibling in siblings.iter().copied() {
+            if world
+                .entity(sibling)
+                .get::<LayoutNodeId>()
+                .is_some_and(|node_id| node_id.name() == name)
+            {
+                return Err(NodeEntityError::DuplicateChild(target, name));
+            }
+        }
+
+        let parent_id = target_ref.get::<LayoutNodeId>().unwrap().clone();
+        let layers = target_ref
+            .get::<RenderLayers>()
+            .copied()
+            .unwrap_or_default();
+
+        world
+            .entity_mut(target)
+            .insert_children(index.min(siblings.len()), &[id]);
+
+        if let Some(DetachedNode(visibility)) = world.entity_mut(id).take::<DetachedNode>() {
+            world.entity_mut(id).insert(visibility);
+        }
+
+        update_subtree_node_ids(world, id, &parent_id, layers);
+
+        let mut entity = world.entity_mut(id);
+        entity.get_mut::<Node>().unwrap().set_changed();
+        *entity.get_mut::<ZIndex>().unwrap() = ZIndex::NeedsRecalculation;
+
+        Ok(())
+    }
+}
+
+/// Marker for nodes that have been removed from the layout tree via [`NodeEntityMut::detach`]
+///
+/// This keeps track of the visibility of the node before it was detached so that it can be
+/// restored when it is placed back into the tree
+#[derive(Component)]
+pub(crate) struct DetachedNode(Visibility);
+
+fn update_subtree_node_ids(
+    world: &mut World,
+    entity: Entity,
+    parent: &LayoutNodeId,
+    layers: RenderLayers,
+) {
+    let mut entity = world.entity_mut(entity);
+    let Some(mut node_id) = entity.get_mut::<LayoutNodeId>() else {
+        return;
+    };
+
+    let new_id = parent.join(node_id.name());
+    *node_id = new_id.clone();
+    entity.insert(layers);
+
+    let children: SmallVec<[Entity; 8]> = entity
+        .get::<Children>()
+        .iter()
+        .flat_map(|children| children.iter().copied())
+        .collect();
+
+    for child in children {
+        update_subtree_node_ids(world, child, &new_id, layers);
+    }
 }
 
 impl<'w> NodeRef<'w> {
@@ -830,6 +1038,23 @@ impl LayoutNodeMut<'_> {
             .ok_or_else(|| LayoutAnimationError::NoAnimation(name.to_string()))
     }
 
+    /// Plays the animation from the start with the given options, if it exists
+    ///
+    /// The options only apply until the animation is played again.
+    pub fn play_animation_with(
+        &mut self,
+        name: impl AsRef<str>,
+        options: AnimationPlayOptions,
+    ) -> Result<(), LayoutAnimationError> {
+        let name = name.as_ref();
+        self.0
+            .get_mut::<LayoutAnimationPlaybackState>()
+            .expect("LayoutNode should have playback state")
+            .play_animation_with(name, options)
+            .then_some(())
+            .ok_or_else(|| LayoutAnimationError::NoAnimation(name.to_string()))
+    }
+
     pub fn stop_animation(&mut self, name: impl AsRef<str>) -> Result<(), LayoutAnimationError> {
         let name = name.as_ref();
         self.0
@@ -909,6 +1134,76 @@ impl LayoutNodeMut<'_> {
             .then_some(())
             .ok_or_else(|| LayoutAnimationError::NoAnimation(name.to_string()))
     }
+
+    /// Moves an animation to `ms` milliseconds into it, pausing it there if it is stopped
+    pub fn seek(&mut self, name: impl AsRef<str>, ms: f32) -> Result<(), LayoutAnimationError> {
+        let name = name.as_ref();
+        self.0
+            .get_mut::<LayoutAnimationPlaybackState>()
+            .expect("LayoutNode should have playback state")
+            .seek(name, ms)
+            .then_some(())
+            .ok_or_else(|| LayoutAnimationError::NoAnimation(name.to_string()))
+    }
+
+    /// Moves an animation to a fraction of its length, pausing it there if it is stopped
+    pub fn set_progress(
+        &mut self,
+        name: impl AsRef<str>,
+        fraction: f32,
+    ) -> Result<(), LayoutAnimationError> {
+        let name = name.as_ref();
+        self.0
+            .get_mut::<LayoutAnimationPlaybackState>()
+            .expect("LayoutNode should have playback state")
+            .set_progress(name, fraction)
+            .then_some(())
+            .ok_or_else(|| LayoutAnimationError::NoAnimation(name.to_string()))
+    }
+
+    /// Changes how fast an animation plays until it is played again, stopped animations are left
+    /// as they are
+    pub fn set_animation_speed(
+        &mut self,
+        name: impl AsRef<str>,
+        speed: f32,
+    ) -> Result<(), LayoutAnimationError> {
+        let name = name.as_ref();
+        self.0
+            .get_mut::<LayoutAnimationPlaybackState>()
+            .expect("LayoutNode should have playback state")
+            .set_speed(name, speed)
+            .then_some(())
+            .ok_or_else(|| LayoutAnimationError::NoAnimation(name.to_string()))
+    }
+
+    /// Gets the time that drives the animations of this layout
+    pub fn animation_clock(&self) -> AnimationClock {
+        self.0
+            .get::<LayoutAnimationPlaybackState>()
+            .expect("LayoutNode should have playback state")
+            .clock()
+    }
+
+    /// Changes the time that drives the animations of this layout, except for animations
+    /// that were played with their own clock
+    ///
+    /// Layouts nested inside of this one keep their own clock.
+    pub fn set_animation_clock(&mut self, clock: AnimationClock) {
+        self.0
+            .get_mut::<LayoutAnimationPlaybackState>()
+            .expect("LayoutNode should have playback state")
+            .set_clock(clock)
+    }
+
+    /// Advances the animations of this layout that use [`AnimationClock::Manual`] by `delta`
+    /// on the next update
+    pub fn step_animations(&mut self, delta: Duration) {
+        self.0
+            .get_mut::<LayoutAnimationPlaybackState>()
+            .expect("LayoutNode should have playback state")
+            .step(delta)
+    }
 }
 
 pub struct GroupNodeMut<'w> {
@@ -916,7 +1211,39 @@ pub struct GroupNodeMut<'w> {
     id: Entity,
 }
 
-pub struct NodeArgs {}
+/// Common arguments for every node created at runtime via [`GroupNodeMut`]
+#[derive(Default)]
+pub struct NodeArgs {
+    /// The id of the node, this must be unique amongst its siblings
+    pub id: String,
+
+    /// The position of the node, relative to the parent group in the layout's resolution
+    pub position: Vec2,
+
+    /// The size of the node in the layout's resolution
+    pub size: Vec2,
+
+    /// The rotation of the node in degrees
+    pub rotation: f32,
+
+    /// Which part of the node to attach to the position
+    pub anchor: Anchor,
+}
+
+impl NodeArgs {
+    fn into_layout_node(self, inner: LayoutNodeInner) -> LayoutNode {
+        LayoutNode {
+            id: self.id,
+            position: self.position,
+            size: self.size,
+            rotation: self.rotation,
+            anchor: self.anchor,
+            inner,
+            attributes: vec![],
+            bindings: vec![],
+        }
+    }
+}
 
 pub struct ImageNodeArgs {
     pub node: NodeArgs,
@@ -930,6 +1257,7 @@ pub struct TextNodeArgs {
     pub font_size: f32,
     pub font: Handle<Font>,
     pub color: Color,
+    pub alignment: JustifyText,
 }
 
 pub struct LayoutNodeArgs {
@@ -937,21 +1265,225 @@ pub struct LayoutNodeArgs {
     pub layout: Handle<Layout>,
 }
 
+pub struct GroupNodeArgs {
+    pub node: NodeArgs,
+    pub child_anchor: Anchor,
+}
+
 impl<'w> GroupNodeMut<'w> {
-    pub fn add_image_node<'a>(&'a mut self, args: ImageNodeArgs) -> NodeEntityMut<'a> {
-        todo!()
+    fn world_mut(&mut self) -> &mut World {
+        // SAFETY: See NodeEntityMut::world_mut
+        unsafe { self.world.world_mut() }
+    }
+
+    fn add_node(&mut self, node: LayoutNode) -> Result<Entity, SpawnLayoutError> {
+        let id = self.id;
+        spawn_child_node(self.world_mut(), id, &node, &node.id, Vec2::ZERO)
+    }
+
+    /// Spawns a new image node as the last child of this group
+    ///
+    /// This will return [`SpawnLayoutError::NotLoaded`] if the layout containing this group is
+    /// no longer loaded, and [`SpawnLayoutError::DuplicateChild`] if the group already has a child
+    /// with the same id
+    pub fn add_image_node<'a>(
+        &'a mut self,
+        args: ImageNodeArgs,
+    ) -> Result<NodeEntityMut<'a>, SpawnLayoutError> {
+        let node = args
+            .node
+            .into_layout_node(LayoutNodeInner::Image(ImageNodeData {
+                path: None,
+                tint: args.tint,
+                handle: args.image,
+            }));
+
+        let child = self.add_node(node)?;
+
+        Ok(NodeEntityMut {
+            world: self.world,
+            id: child,
+        })
+    }
+
+    /// Spawns a new text node as the last child of this group
+    ///
+    /// This will return [`SpawnLayoutError::NotLoaded`] if the layout containing this group is
+    /// no longer loaded, and [`SpawnLayoutError::DuplicateChild`] if the group already has a child
+    /// with the same id
+    pub fn add_text_node<'a>(
+        &'a mut self,
+        args: TextNodeArgs,
+    ) -> Result<NodeEntityMut<'a>, SpawnLayoutError> {
+        let node = args
+            .node
+            .into_layout_node(LayoutNodeInner::Text(TextNodeData {
+                text: args.text,
+                size: args.font_size,
+                color: args.color,
+                font: None,
+                handle: args.font,
+                alignment: args.alignment,
+            }));
+
+        let child = self.add_node(node)?;
+
+        Ok(NodeEntityMut {
+            world: self.world,
+            id: child,
+        })
+    }
+
+    /// Spawns a new sublayout node as the last child of this group
+    ///
+    /// The layout (and all of the layouts it depends on) must already be loaded, otherwise
+    /// this will return [`SpawnLayoutError::NotLoaded`]. Like the other nodes, its id must not
+    /// be used by another child of the group.
+    pub fn add_layout_node<'a>(
+        &'a mut self,
+        args: LayoutNodeArgs,
+    ) -> Result<NodeEntityMut<'a>, SpawnLayoutError> {
+        let path = self
+            .world_mut()
+            .resource::<AssetServer>()
+            .get_path(args.layout.id())
+            .map(|path| path.path().to_path_buf())
+            .unwrap_or_default();
+
+        let node = args
+            .node
+            .into_layout_node(LayoutNodeInner::Layout(LayoutNodeData {
+                path,
+                handle: args.layout,
+                ..Default::default()
+            }));
+
+        let child = self.add_node(node)?;
+
+        Ok(NodeEntityMut {
+            world: self.world,
+            id: child,
+        })
+    }
+
+    /// Spawns a new, empty group node as the last child of this group
+    ///
+    /// This will return [`SpawnLayoutError::NotLoaded`] if the layout containing this group is
+    /// no longer loaded, and [`SpawnLayoutError::DuplicateChild`] if the group already has a child
+    /// with the same id
+    pub fn add_group_node<'a>(
+        &'a mut self,
+        args: GroupNodeArgs,
+    ) -> Result<GroupNodeMut<'a>, SpawnLayoutError> {
+        let node = args
+            .node
+            .into_layout_node(LayoutNodeInner::Group(GroupNodeData {
+                child_anchor: args.child_anchor,
+                ..Default::default()
+            }));
+
+        let child = self.add_node(node)?;
+
+        Ok(GroupNodeMut {
+            world: self.world,
+            id: child,
+        })
+    }
+
+    pub fn into_node(self) -> NodeEntityMut<'w> {
+        NodeEntityMut {
+            world: self.world,
+            id: self.id,
+        }
+    }
+}
+
+/// Provides access to the items of a `Repeat` node
+pub struct RepeatNodeMut<'w> {
+    world: UnsafeWorldCell<'w>,
+    id: Entity,
+}
+
+impl<'w> RepeatNodeMut<'w> {
+    fn world(&self) -> &World {
+        // SAFETY: See NodeEntityMut::world
+        unsafe { self.world.world() }
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        // SAFETY: See NodeEntityMut::world_mut
+        unsafe { self.world.world_mut() }
     }
 
-    pub fn add_text_node<'a>(&'a mut self, args: TextNodeArgs) -> NodeEntityMut<'a> {
-        todo!()
+    fn info(&self) -> &RepeatInfo {
+        self.world()
+            .get::<RepeatInfo>(self.id)
+            .expect("repeat node should have RepeatInfo")
     }
 
-    pub fn add_layout_node<'a>(&'a mut self, args: LayoutNodeArgs) -> NodeEntityMut<'a> {
-        todo!()
+    /// The number of items that are spawned
+    pub fn count(&self) -> usize {
+        self.info().count
     }
 
-    pub fn add_group_node<'a>(&'a mut self, args: NodeArgs) -> GroupNodeMut<'a> {
-        todo!()
+    /// The id of the item at `index`
+    pub fn item_id(&self, index: usize) -> String {
+        item_id(&self.info().template.id, index)
+    }
+
+    /// Gets the item at `index`, if there are that many items
+    pub fn get_item<'a>(&'a mut self, index: usize) -> Option<NodeEntityMut<'a>> {
+        let item =
+            find_child_id(self.world(), self.id, Utf8Path::new(&self.item_id(index))).ok()?;
+        Some(NodeEntityMut {
+            world: self.world,
+            id: item,
+        })
+    }
+
+    #[track_caller]
+    pub fn item<'a>(&'a mut self, index: usize) -> NodeEntityMut<'a> {
+        self.get_item(index)
+            .expect("repeat node should have an item at the index")
+    }
+
+    /// Spawns or despawns items from the end of the node so that it has `count` items
+    ///
+    /// New items are spawned from the template of the node and have all of its attributes
+    /// applied, but the overrides and variables that the layouts containing this node have for
+    /// the items are not applied to them.
+    pub fn set_count(&mut self, count: usize) -> Result<(), SpawnLayoutError> {
+        let id = self.id;
+        let RepeatInfo {
+            count: current,
+            grid,
+            template,
+        } = self.info().clone();
+
+        let world = self.world_mut();
+        for index in current..count {
+            spawn_child_node(
+                world,
+                id,
+                &template,
+                &item_id(&template.id, index),
+                grid.item_offset(index),
+            )?;
+
+            // Keep the count accurate if one of the items fails to spawn
+            world.get_mut::<RepeatInfo>(id).unwrap().count = index + 1;
+        }
+
+        for index in (count..current).rev() {
+            let name = item_id(&template.id, index);
+            if let Ok(item) = find_child_id(world, id, Utf8Path::new(&name)) {
+                despawn_node_tree(world, item);
+            }
+
+            world.get_mut::<RepeatInfo>(id).unwrap().count = index;
+        }
+
+        Ok(())
     }
 
     pub fn into_node(self) -> NodeEntityMut<'w> {
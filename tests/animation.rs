@@ -0,0 +1,730 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{asset::io::memory::Dir, prelude::*};
+use serde_json::Value;
+use yabuil::{
+    animation::{
+        AnimationClock, AnimationLoopMode, AnimationPlayOptions, LayoutAnimation,
+        LayoutAnimationEvent, LayoutAnimationEventKind, PlaybackState,
+    },
+    asset::Layout,
+    views::NodeEntityMut,
+    LayoutRegistry,
+};
+
+use common::{advance_frames, app, load_layouts, spawn_source, to_json, ROUND_TRIP_SOURCE};
+
+const ANIMATION_JSON: &str = r#"{
+    "canvas_size": [200, 100],
+    "nodes": [
+        {
+            "id": "box",
+            "position": [0.0, 0.0],
+            "size": [10.0, 10.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null"
+        }
+    ],
+    "animations": {
+        "slide": {
+            "@settings": { "loop_mode": "PingPong" },
+            "box": [
+                {
+                    "timestamp_ms": 0,
+                    "targets": {
+                        "Position": [0.0, 0.0]
+                    }
+                },
+                {
+                    "timestamp_ms": 100,
+                    "targets": {
+                        "Position": [100.0, 0.0]
+                    }
+                }
+            ]
+        }
+    }
+}"#;
+
+/// Loads and spawns `source`, then makes every frame after that take `frame_ms`
+fn spawn_animated_layout(source: &str, frame_ms: u64) -> (App, Entity, Handle<Layout>) {
+    let (mut app, root, handle) = spawn_source(source);
+    app.insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+        std::time::Duration::from_millis(frame_ms),
+    ));
+
+    (app, root, handle)
+}
+
+#[track_caller]
+fn assert_box_position(app: &mut App, root: Entity, expected: f32) {
+    let position = NodeEntityMut::new(&mut app.world, root)
+        .child("box")
+        .get::<yabuil::node::Node>()
+        .unwrap()
+        .position
+        .x;
+    assert!(
+        (position - expected).abs() < 1e-3,
+        "box is at {position}, expected {expected}"
+    );
+}
+
+#[test]
+fn animations_loop_ping_pong_and_repeat() {
+    let (mut app, root, handle) = spawn_animated_layout(ANIMATION_JSON, 20);
+    let is_playing = |app: &mut App| {
+        matches!(
+            NodeEntityMut::new(&mut app.world, root)
+                .layout()
+                .animation_state("slide"),
+            Some(PlaybackState::Playing)
+        )
+    };
+
+    // The animation ping-pongs by default, as declared in the layout
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation("slide")
+        .unwrap();
+    advance_frames(&mut app, 7);
+    assert_box_position(&mut app, root, 60.0);
+    advance_frames(&mut app, 20);
+    assert_box_position(&mut app, root, 60.0);
+    assert!(is_playing(&mut app));
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with(
+            "slide",
+            AnimationPlayOptions::default().with_loop_mode(AnimationLoopMode::Repeat(2)),
+        )
+        .unwrap();
+    advance_frames(&mut app, 7);
+    assert_box_position(&mut app, root, 40.0);
+    advance_frames(&mut app, 2);
+    assert!(is_playing(&mut app));
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 100.0);
+    assert!(!is_playing(&mut app));
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with(
+            "slide",
+            AnimationPlayOptions::default().with_loop_mode(AnimationLoopMode::Once),
+        )
+        .unwrap();
+    advance_frames(&mut app, 5);
+    assert_box_position(&mut app, root, 100.0);
+    assert!(!is_playing(&mut app));
+
+    let written: Value = serde_json::from_str(&to_json(&app, &handle)).unwrap();
+    assert_eq!(
+        written["animations"]["slide"]["@settings"]["loop_mode"],
+        "PingPong"
+    );
+}
+
+/// Progress keeps sub-millisecond frame times instead of truncating them
+#[test]
+fn animations_keep_time_at_high_frame_rates() {
+    let source = ANIMATION_JSON
+        .replace(r#""PingPong""#, r#""Once""#)
+        .replace(r#""timestamp_ms": 100"#, r#""timestamp_ms": 1000"#);
+    let (mut app, root, _) = spawn_animated_layout(&source, 20);
+    app.insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+        std::time::Duration::from_secs_f64(1.0 / 240.0),
+    ));
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation("slide")
+        .unwrap();
+
+    let mut frames = 0;
+    while matches!(
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .animation_state("slide"),
+        Some(PlaybackState::Playing)
+    ) {
+        app.update();
+        frames += 1;
+        assert!(frames <= 250, "animation did not finish in time");
+    }
+
+    // One second at 240 Hz, give or take a frame of rounding
+    assert!((240..=241).contains(&frames), "took {frames} frames");
+    assert_box_position(&mut app, root, 100.0);
+}
+
+#[test]
+fn animations_seek_and_change_speed() {
+    let source = ANIMATION_JSON.replace(r#""PingPong""#, r#""Once""#);
+    let (mut app, root, _) = spawn_animated_layout(&source, 20);
+    let state = |app: &mut App| {
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .animation_state("slide")
+    };
+
+    // Seeking a stopped animation pauses it at that point
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .seek("slide", 25.5)
+        .unwrap();
+    advance_frames(&mut app, 2);
+    assert_box_position(&mut app, root, 25.5);
+    assert!(matches!(state(&mut app), Some(PlaybackState::Paused)));
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .set_progress("slide", 0.5)
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 50.0);
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .resume_animation("slide")
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 70.0);
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .set_animation_speed("slide", 0.5)
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 80.0);
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with("slide", AnimationPlayOptions::default().with_speed(2.0))
+        .unwrap();
+    advance_frames(&mut app, 2);
+    assert_box_position(&mut app, root, 80.0);
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 100.0);
+    assert!(matches!(state(&mut app), Some(PlaybackState::Stopped)));
+}
+
+#[test]
+fn out_of_range_seeks_and_speeds_do_not_panic() {
+    let source = ANIMATION_JSON.replace(r#""PingPong""#, r#""Once""#);
+    let (mut app, root, _) = spawn_animated_layout(&source, 20);
+    let state = |app: &mut App| {
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .animation_state("slide")
+    };
+
+    // Speeds only last until the animation is played, so stopped animations keep their state
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .set_animation_speed("slide", 2.0)
+        .unwrap();
+    assert!(matches!(state(&mut app), Some(PlaybackState::Stopped)));
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .seek("slide", f32::INFINITY)
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 100.0);
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .set_progress("slide", f32::NAN)
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 0.0);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut layout = node.layout();
+    layout.resume_animation("slide").unwrap();
+    layout.set_animation_speed("slide", f32::INFINITY).unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 100.0);
+    assert!(matches!(state(&mut app), Some(PlaybackState::Stopped)));
+
+    // Looping animations go over a bounded number of loops instead of looping forever
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with(
+            "slide",
+            AnimationPlayOptions::default()
+                .with_loop_mode(AnimationLoopMode::Loop)
+                .with_speed(f32::INFINITY),
+        )
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert!(matches!(state(&mut app), Some(PlaybackState::Playing)));
+}
+
+#[derive(Default)]
+struct MenuTime;
+
+#[test]
+fn animations_follow_their_clock() {
+    let source = ANIMATION_JSON.replace(r#""PingPong""#, r#""Once""#);
+    let (mut app, root, _) = spawn_animated_layout(&source, 20);
+    let play = |app: &mut App, options: AnimationPlayOptions| {
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .play_animation_with("slide", options)
+            .unwrap();
+    };
+
+    // Pausing the game stops animations on virtual time but not on real time
+    app.world.resource_mut::<Time<Virtual>>().pause();
+    play(&mut app, AnimationPlayOptions::default());
+    advance_frames(&mut app, 2);
+    assert_box_position(&mut app, root, 0.0);
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .set_animation_clock(AnimationClock::Real);
+    advance_frames(&mut app, 2);
+    assert_box_position(&mut app, root, 40.0);
+
+    // Animations played with their own clock ignore the clock of the layout
+    app.insert_resource(Time::<MenuTime>::default());
+    play(
+        &mut app,
+        AnimationPlayOptions::default().with_clock(AnimationClock::custom::<MenuTime>()),
+    );
+    advance_frames(&mut app, 2);
+    assert_box_position(&mut app, root, 0.0);
+    app.world
+        .resource_mut::<Time<MenuTime>>()
+        .advance_by(std::time::Duration::from_millis(15));
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 15.0);
+
+    // Manual clocks only move when they are stepped, and steps add up
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .set_animation_clock(AnimationClock::Manual);
+    play(&mut app, AnimationPlayOptions::default());
+    advance_frames(&mut app, 3);
+    assert_box_position(&mut app, root, 0.0);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut layout = node.layout();
+    layout.step_animations(std::time::Duration::from_millis(25));
+    layout.step_animations(std::time::Duration::from_millis(5));
+    assert_eq!(layout.animation_clock(), AnimationClock::Manual);
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 30.0);
+    advance_frames(&mut app, 2);
+    assert_box_position(&mut app, root, 30.0);
+}
+
+#[test]
+fn animations_layer_by_priority_and_crossfade() {
+    let source = ANIMATION_JSON
+        .replace(r#""PingPong""#, r#""Once""#)
+        .replace(
+            r#""animations": {"#,
+            r#""animations": {
+        "park": {
+            "@settings": { "loop_mode": "Loop", "priority": 1 },
+            "box": [
+                { "timestamp_ms": 0, "targets": { "Position": [200.0, 0.0] } },
+                { "timestamp_ms": 100, "targets": { "Position": [200.0, 0.0] } }
+            ]
+        },"#,
+        );
+    let (mut app, root, handle) = spawn_animated_layout(&source, 20);
+    let play = |app: &mut App, name: &str, options: AnimationPlayOptions| {
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .play_animation_with(name, options)
+            .unwrap();
+    };
+
+    // The declared priority wins over playing an animation later
+    play(&mut app, "park", AnimationPlayOptions::default());
+    play(&mut app, "slide", AnimationPlayOptions::default());
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 200.0);
+
+    // Animations with the same priority are applied in the order they were played
+    play(&mut app, "slide", AnimationPlayOptions::default());
+    play(
+        &mut app,
+        "park",
+        AnimationPlayOptions::default().with_priority(0),
+    );
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 200.0);
+    play(&mut app, "slide", AnimationPlayOptions::default());
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 20.0);
+
+    // A crossfade blends over the animation below it, then stops it
+    play(&mut app, "park", AnimationPlayOptions::default());
+    play(
+        &mut app,
+        "slide",
+        AnimationPlayOptions::default()
+            .with_priority(1)
+            .with_crossfade(std::time::Duration::from_millis(40)),
+    );
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 110.0);
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 40.0);
+    assert!(matches!(
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .animation_state("park"),
+        Some(PlaybackState::Stopped)
+    ));
+
+    let written: Value = serde_json::from_str(&to_json(&app, &handle)).unwrap();
+    assert_eq!(written["animations"]["park"]["@settings"]["priority"], 1);
+    assert!(written["animations"]["slide"].get("@settings").is_none());
+}
+
+#[test]
+fn crossfades_fade_from_finished_animations_and_can_be_stopped() {
+    let source = ANIMATION_JSON
+        .replace(r#""PingPong""#, r#""Once""#)
+        .replace(
+            r#""animations": {"#,
+            r#""animations": {
+        "park": {
+            "box": [
+                { "timestamp_ms": 0, "targets": { "Position": [200.0, 0.0] } },
+                { "timestamp_ms": 100, "targets": { "Position": [200.0, 0.0] } }
+            ]
+        },"#,
+        );
+    let (mut app, root, _) = spawn_animated_layout(&source, 20);
+    let crossfade =
+        AnimationPlayOptions::default().with_crossfade(std::time::Duration::from_millis(40));
+    let state = |app: &mut App, name: &str| {
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .animation_state(name)
+    };
+
+    // Crossfades fade from where a finished animation left its nodes
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation("slide")
+        .unwrap();
+    advance_frames(&mut app, 6);
+    assert_box_position(&mut app, root, 100.0);
+    assert!(matches!(
+        state(&mut app, "slide"),
+        Some(PlaybackState::Stopped)
+    ));
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with("park", crossfade)
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 150.0);
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 200.0);
+
+    // Stopping an animation in the middle of its crossfade leaves the animations it was fading
+    // over playing
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation("slide")
+        .unwrap();
+    advance_frames(&mut app, 1);
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with("park", crossfade)
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 120.0);
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .stop_animation("park")
+        .unwrap();
+    advance_frames(&mut app, 1);
+    assert_box_position(&mut app, root, 60.0);
+    assert!(matches!(
+        state(&mut app, "slide"),
+        Some(PlaybackState::Playing)
+    ));
+    advance_frames(&mut app, 2);
+    assert_box_position(&mut app, root, 100.0);
+    assert!(matches!(
+        state(&mut app, "slide"),
+        Some(PlaybackState::Stopped)
+    ));
+}
+
+/// Loop modes with data survive being written out in every format
+#[cfg(feature = "cooked")]
+#[test]
+fn animation_loop_modes_round_trip_cooked() {
+    let source = ANIMATION_JSON.replace(r#""PingPong""#, r#"{ "Repeat": 3 }"#);
+    let (app, _, handle) = spawn_animated_layout(&source, 20);
+
+    let cooked = app
+        .world
+        .resource::<Assets<Layout>>()
+        .get(handle.id())
+        .unwrap()
+        .to_cooked(
+            app.world.resource::<LayoutRegistry>(),
+            app.world.resource::<Assets<LayoutAnimation>>(),
+        )
+        .unwrap();
+    let json = to_json(&app, &handle);
+    let written: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        written["animations"]["slide"]["@settings"]["loop_mode"]["Repeat"],
+        3
+    );
+
+    let round_trip = Dir::default();
+    round_trip.insert_asset(Path::new("layouts/animated.layout.bin"), cooked);
+    let mut app = self::app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/animated.layout.bin")],
+    );
+    assert_eq!(to_json(&app, &handles[0]), json);
+}
+
+/// Advances `frames` frames and collects the kinds of every animation event sent by `root`
+fn animation_events(app: &mut App, root: Entity, frames: usize) -> Vec<LayoutAnimationEventKind> {
+    let mut kinds = vec![];
+    for _ in 0..frames {
+        app.update();
+        kinds.extend(
+            app.world
+                .resource_mut::<Events<LayoutAnimationEvent>>()
+                .drain()
+                .filter(|event| event.entity == root && event.animation == "slide")
+                .map(|event| event.kind),
+        );
+    }
+
+    kinds
+}
+
+/// [`ANIMATION_JSON`] with markers at the start, middle and end of `slide`
+fn with_markers() -> String {
+    ANIMATION_JSON.replace(
+        r#""@settings": { "loop_mode": "PingPong" },"#,
+        r#""@settings": {
+                "loop_mode": "PingPong",
+                "events": [
+                    { "timestamp_ms": 100, "name": "end" },
+                    { "timestamp_ms": 0, "name": "start" },
+                    { "timestamp_ms": 50, "name": "middle" }
+                ]
+            },"#,
+    )
+}
+
+fn marker(name: &str) -> LayoutAnimationEventKind {
+    LayoutAnimationEventKind::Marker(name.to_string())
+}
+
+#[test]
+fn animations_send_lifecycle_and_marker_events() {
+    let (mut app, root, handle) = spawn_animated_layout(&with_markers(), 20);
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with(
+            "slide",
+            AnimationPlayOptions::default().with_loop_mode(AnimationLoopMode::Once),
+        )
+        .unwrap();
+    assert_eq!(
+        animation_events(&mut app, root, 6),
+        [
+            LayoutAnimationEventKind::Started,
+            marker("start"),
+            marker("middle"),
+            marker("end"),
+            LayoutAnimationEventKind::Finished,
+        ]
+    );
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with(
+            "slide",
+            AnimationPlayOptions::default().with_loop_mode(AnimationLoopMode::Once),
+        )
+        .unwrap();
+    assert_eq!(
+        animation_events(&mut app, root, 3),
+        [
+            LayoutAnimationEventKind::Started,
+            marker("start"),
+            marker("middle"),
+        ]
+    );
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .reverse_animation("slide")
+        .unwrap();
+    assert_eq!(
+        animation_events(&mut app, root, 4),
+        [
+            marker("middle"),
+            marker("start"),
+            LayoutAnimationEventKind::ReversedToStart,
+        ]
+    );
+
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation_with(
+            "slide",
+            AnimationPlayOptions::default().with_loop_mode(AnimationLoopMode::Loop),
+        )
+        .unwrap();
+    assert_eq!(
+        animation_events(&mut app, root, 7),
+        [
+            LayoutAnimationEventKind::Started,
+            marker("start"),
+            marker("middle"),
+            marker("end"),
+            LayoutAnimationEventKind::Looped,
+            marker("start"),
+        ]
+    );
+
+    // Ping-pong turns around without crossing the marker at the end twice
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation("slide")
+        .unwrap();
+    assert_eq!(
+        animation_events(&mut app, root, 8),
+        [
+            LayoutAnimationEventKind::Started,
+            marker("start"),
+            marker("middle"),
+            marker("end"),
+            LayoutAnimationEventKind::Looped,
+            marker("middle"),
+        ]
+    );
+
+    let written: Value = serde_json::from_str(&to_json(&app, &handle)).unwrap();
+    assert_eq!(
+        written["animations"]["slide"]["@settings"]["events"],
+        serde_json::json!([
+            { "timestamp_ms": 0, "name": "start" },
+            { "timestamp_ms": 50, "name": "middle" },
+            { "timestamp_ms": 100, "name": "end" }
+        ])
+    );
+}
+
+#[test]
+fn long_frames_go_over_every_loop() {
+    let (mut app, root, _) = spawn_animated_layout(&with_markers(), 250);
+    let play = |app: &mut App, loop_mode| {
+        NodeEntityMut::new(&mut app.world, root)
+            .layout()
+            .play_animation_with(
+                "slide",
+                AnimationPlayOptions::default().with_loop_mode(loop_mode),
+            )
+            .unwrap();
+    };
+
+    play(&mut app, AnimationLoopMode::Loop);
+    assert_eq!(
+        animation_events(&mut app, root, 1),
+        [
+            LayoutAnimationEventKind::Started,
+            marker("start"),
+            marker("middle"),
+            marker("end"),
+            LayoutAnimationEventKind::Looped,
+            marker("start"),
+            marker("middle"),
+            marker("end"),
+            LayoutAnimationEventKind::Looped,
+            marker("start"),
+            marker("middle"),
+        ]
+    );
+    assert_box_position(&mut app, root, 50.0);
+
+    // Repeating stops at the last repeat, even when the frame goes past it
+    play(&mut app, AnimationLoopMode::Repeat(2));
+    assert_eq!(
+        animation_events(&mut app, root, 1),
+        [
+            LayoutAnimationEventKind::Started,
+            marker("start"),
+            marker("middle"),
+            marker("end"),
+            LayoutAnimationEventKind::Looped,
+            marker("start"),
+            marker("middle"),
+            marker("end"),
+            LayoutAnimationEventKind::Finished,
+        ]
+    );
+    assert_box_position(&mut app, root, 100.0);
+}
+
+/// Plays `slide` from `source` until it has ping-ponged back to 60, and returns where `node` is
+fn play_slide(source: &str, node: &str) -> (f32, Value) {
+    let (mut app, root, handle) = spawn_animated_layout(source, 20);
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation("slide")
+        .unwrap();
+    advance_frames(&mut app, 7);
+
+    let position = NodeEntityMut::new(&mut app.world, root)
+        .child(node)
+        .get::<yabuil::node::Node>()
+        .unwrap()
+        .position
+        .x;
+    let written = serde_json::from_str(&to_json(&app, &handle)).unwrap();
+    (position, written)
+}
+
+#[test]
+fn animations_can_animate_top_level_settings_nodes() {
+    let source = ANIMATION_JSON
+        .replace(r#""id": "box""#, r#""id": "settings""#)
+        .replace(r#""box": ["#, r#""settings": ["#);
+    let (position, written) = play_slide(&source, "settings");
+    assert!((position - 60.0).abs() < 1e-3, "settings is at {position}");
+    assert_eq!(
+        written["animations"]["slide"]["settings"][1]["targets"]["Position"],
+        serde_json::json!([100.0, 0.0])
+    );
+}
+
+#[test]
+fn animation_settings_load_from_the_old_key() {
+    let source = ANIMATION_JSON.replace(r#""@settings": {"#, r#""settings": {"#);
+    let (position, written) = play_slide(&source, "box");
+    assert!((position - 60.0).abs() < 1e-3, "box is at {position}");
+    assert_eq!(
+        written["animations"]["slide"]["@settings"]["loop_mode"],
+        "PingPong"
+    );
+    assert!(written["animations"]["slide"].get("settings").is_none());
+}
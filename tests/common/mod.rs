@@ -0,0 +1,309 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use std::{path::Path, sync::Arc};
+
+use bevy::{
+    asset::{
+        io::{
+            memory::{Dir, MemoryAssetReader},
+            AssetSource,
+        },
+        AssetLoadError, AssetLoadFailedEvent, LoadState,
+    },
+    prelude::*,
+    render::camera::ManualTextureViews,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use yabuil::{
+    animation::LayoutAnimation,
+    asset::Layout,
+    components::{spawning::spawn_layout, LayoutBundle},
+    views::NodeEntityMut,
+    AttributeLoadError, LayoutApp, LayoutAttribute, LayoutPlugin, LayoutRegistry,
+    RestrictedLoadContext,
+};
+
+/// Declares attributes that keep whatever data they were given, so that the layouts from the
+/// examples can be loaded without the example code
+macro_rules! passthrough_attributes {
+    ($($name:ident),*) => {
+        $(
+            #[derive(Serialize, Deserialize, TypePath)]
+            #[serde(transparent)]
+            pub struct $name(Value);
+
+            impl LayoutAttribute for $name {
+                const NAME: &'static str = stringify!($name);
+
+                fn apply(&self, _: NodeEntityMut) {}
+            }
+        )*
+    };
+}
+
+passthrough_attributes!(ControllerCursor, MainMenuButton, NearestNeighbor);
+
+/// Reads a list of words from a file next to the layout while the layout is loading
+#[derive(Serialize, Deserialize, TypePath)]
+pub struct WordList {
+    pub file: String,
+    #[serde(skip)]
+    pub words: Vec<String>,
+    #[serde(skip)]
+    pub image: Handle<Image>,
+}
+
+impl LayoutAttribute for WordList {
+    const NAME: &'static str = "WordList";
+
+    fn apply(&self, _: NodeEntityMut) {}
+
+    fn load_dependencies<'a>(
+        &'a mut self,
+        context: &'a mut RestrictedLoadContext,
+    ) -> BoxedFuture<'a, Result<(), AttributeLoadError>> {
+        Box::pin(async move {
+            let path = context.asset_path().resolve_embed(&self.file)?;
+            let bytes = context.read_asset_bytes(path).await?;
+            self.words = String::from_utf8(bytes)?
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            self.image = context.add_labeled_asset(&self.file, Image::default());
+            Ok(())
+        })
+    }
+}
+
+/// Counts how many nodes with this attribute have been spawned and despawned, in [`TrackedNodes`]
+#[derive(Serialize, Deserialize, TypePath)]
+pub struct Tracked {
+    /// Recorded in [`TrackedNodes::despawned_tags`] when the node is despawned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+#[derive(Resource, Default)]
+pub struct TrackedNodes {
+    pub applied: usize,
+    pub despawned: usize,
+    pub despawned_tags: Vec<String>,
+}
+
+impl LayoutAttribute for Tracked {
+    const NAME: &'static str = "Tracked";
+
+    fn apply(&self, mut world: NodeEntityMut) {
+        world.world_mut().resource_mut::<TrackedNodes>().applied += 1;
+    }
+
+    fn on_despawn(&self, mut world: NodeEntityMut) {
+        let mut tracked = world.world_mut().resource_mut::<TrackedNodes>();
+        tracked.despawned += 1;
+        tracked.despawned_tags.extend(self.tag.clone());
+    }
+}
+
+pub const ROUND_TRIP_SOURCE: &str = "round_trip";
+
+pub fn app(round_trip: Dir) -> App {
+    let mut app = App::new();
+    app.register_asset_source(
+        ROUND_TRIP_SOURCE,
+        AssetSource::build().with_reader(move || {
+            Box::new(MemoryAssetReader {
+                root: round_trip.clone(),
+            })
+        }),
+    );
+
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        HierarchyPlugin,
+        TransformPlugin,
+        bevy::input::InputPlugin,
+        ImagePlugin::default(),
+        bevy::window::WindowPlugin {
+            primary_window: None,
+            exit_condition: bevy::window::ExitCondition::DontExit,
+            close_when_requested: false,
+        },
+        bevy::text::TextPlugin,
+        LayoutPlugin::default(),
+    ))
+    .init_asset::<TextureAtlasLayout>()
+    .init_resource::<ManualTextureViews>()
+    .register_layout_attribute::<ControllerCursor>()
+    .register_layout_attribute::<MainMenuButton>()
+    .register_layout_attribute::<NearestNeighbor>()
+    .register_layout_attribute::<WordList>()
+    .register_layout_attribute::<Tracked>()
+    .init_resource::<TrackedNodes>();
+
+    app
+}
+
+pub fn load_layouts(app: &mut App, paths: &[String]) -> Vec<Handle<Layout>> {
+    let handles: Vec<Handle<Layout>> = paths
+        .iter()
+        .map(|path| app.world.resource::<AssetServer>().load(path.clone()))
+        .collect();
+
+    for _ in 0..1000 {
+        app.update();
+
+        let server = app.world.resource::<AssetServer>();
+        let states: Vec<_> = handles
+            .iter()
+            .map(|handle| server.load_state(handle))
+            .collect();
+
+        if let Some(idx) = states.iter().position(|state| *state == LoadState::Failed) {
+            panic!("Failed to load layout {}", paths[idx]);
+        }
+
+        if states.iter().all(|state| *state == LoadState::Loaded) {
+            return handles;
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+
+    panic!("Timed out waiting for layouts to load");
+}
+
+pub fn to_json(app: &App, handle: &Handle<Layout>) -> String {
+    app.world
+        .resource::<Assets<Layout>>()
+        .get(handle.id())
+        .unwrap()
+        .to_json(
+            app.world.resource::<LayoutRegistry>(),
+            app.world.resource::<Assets<LayoutAnimation>>(),
+        )
+        .unwrap()
+}
+
+/// Checks that everything in `original` was written back out in `written`
+///
+/// Fields that the layout file left out (and that were written out with their default value)
+/// are ignored
+pub fn assert_contains(original: &Value, written: &Value, path: &str) {
+    match (original, written) {
+        (Value::Number(a), Value::Number(b)) => {
+            assert_eq!(a.as_f64(), b.as_f64(), "number mismatch at {path}")
+        }
+        (Value::Object(original), Value::Object(written)) => {
+            for (key, value) in original.iter() {
+                let Some(written) = written.get(key) else {
+                    panic!("{path}/{key} was not written out");
+                };
+                assert_contains(value, written, &format!("{path}/{key}"));
+            }
+        }
+        (Value::Array(original), Value::Array(written)) => {
+            assert_eq!(original.len(), written.len(), "length mismatch at {path}");
+            for (idx, (original, written)) in original.iter().zip(written.iter()).enumerate() {
+                assert_contains(original, written, &format!("{path}[{idx}]"));
+            }
+        }
+        // Groups can be written as just their list of nodes
+        (Value::Array(_), Value::Object(written)) if written.contains_key("nodes") => {
+            assert_contains(original, &written["nodes"], &format!("{path}/nodes"))
+        }
+        (original, written) => assert_eq!(original, written, "value mismatch at {path}"),
+    }
+}
+
+/// The paths of every layout in the examples
+pub fn example_layouts() -> Vec<String> {
+    let mut paths: Vec<String> =
+        std::fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/layouts"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".layout.json"))
+            .map(|name| format!("layouts/{name}"))
+            .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    paths
+}
+
+/// Checks that the layout `source` fails to load, and returns the error of the loader
+pub fn assert_load_fails(source: &str) -> Arc<dyn std::error::Error + Send + Sync> {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/invalid.layout.json"), source);
+
+    let mut app = app(round_trip);
+    let handle: Handle<Layout> = app
+        .world
+        .resource::<AssetServer>()
+        .load(format!("{ROUND_TRIP_SOURCE}://layouts/invalid.layout.json"));
+
+    for _ in 0..1000 {
+        app.update();
+        match app.world.resource::<AssetServer>().load_state(&handle) {
+            LoadState::Failed => {
+                let events = app.world.resource::<Events<AssetLoadFailedEvent<Layout>>>();
+                let mut reader = events.get_reader();
+                let event = reader
+                    .read(events)
+                    .find(|event| event.id == handle.id())
+                    .expect("a failed layout should send an event");
+                match &event.error {
+                    AssetLoadError::AssetLoaderError { error, .. } => return error.clone(),
+                    error => panic!("layout failed outside of the loader: {error}"),
+                }
+            }
+            LoadState::Loaded => panic!("layout should not have loaded"),
+            _ => std::thread::sleep(std::time::Duration::from_millis(5)),
+        }
+    }
+
+    panic!("Timed out waiting for layout to fail");
+}
+
+/// Loads `source` as a layout and spawns it onto a new root entity
+pub fn spawn_source(source: &str) -> (App, Entity, Handle<Layout>) {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/spawned.layout.json"), source);
+
+    let mut app = app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/spawned.layout.json")],
+    );
+
+    let root = app.world.spawn_empty().id();
+    spawn_layout(&mut app.world, root, handles[0].clone(), |_, _, _| {}).unwrap();
+
+    (app, root, handles[0].clone())
+}
+
+/// Like [`spawn_source`], but spawns the layout through a [`LayoutBundle`] so that the root is
+/// handled like any other layout (attributes are applied, reloads and despawns are tracked)
+pub fn spawn_bundle(source: &str) -> (App, Entity, Handle<Layout>) {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/spawned.layout.json"), source);
+
+    let mut app = app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/spawned.layout.json")],
+    );
+
+    let root = app.world.spawn(LayoutBundle::new(handles[0].clone())).id();
+    app.update();
+
+    (app, root, handles[0].clone())
+}
+
+pub fn advance_frames(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
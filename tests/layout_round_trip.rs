@@ -0,0 +1,216 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{asset::io::memory::Dir, prelude::*};
+use serde_json::Value;
+use yabuil::{animation::LayoutAnimation, asset::Layout, LayoutRegistry};
+
+use common::{app, assert_contains, example_layouts, load_layouts, to_json, ROUND_TRIP_SOURCE};
+
+#[test]
+fn layouts_round_trip() {
+    let paths = example_layouts();
+
+    let round_trip = Dir::default();
+    let mut app = app(round_trip.clone());
+
+    let handles = load_layouts(&mut app, &paths);
+    let mut written = vec![];
+    for (path, handle) in paths.iter().zip(handles.iter()) {
+        let json = to_json(&app, handle);
+
+        let original: Value = serde_json::from_str(
+            &std::fs::read_to_string(
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("assets")
+                    .join(path),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_contains(&original, &serde_json::from_str(&json).unwrap(), path);
+
+        round_trip.insert_asset_text(Path::new(path), &json);
+        written.push(json);
+    }
+
+    // Loading the written layouts should give back the same layouts
+    let round_trip_paths: Vec<String> = paths
+        .iter()
+        .map(|path| format!("{ROUND_TRIP_SOURCE}://{path}"))
+        .collect();
+    let handles = load_layouts(&mut app, &round_trip_paths);
+    for ((path, handle), written) in paths.iter().zip(handles.iter()).zip(written.iter()) {
+        assert_eq!(&to_json(&app, handle), written, "{path} did not round trip");
+    }
+}
+
+/// Writes the example layouts in the cooked format and checks that they load back the same
+#[cfg(feature = "cooked")]
+#[test]
+fn layouts_round_trip_cooked() {
+    let paths = example_layouts();
+
+    let round_trip = Dir::default();
+    let mut app = app(round_trip.clone());
+
+    let handles = load_layouts(&mut app, &paths);
+    let mut written = vec![];
+    let mut cooked_paths = vec![];
+    for (path, handle) in paths.iter().zip(handles.iter()) {
+        let cooked = app
+            .world
+            .resource::<Assets<Layout>>()
+            .get(handle.id())
+            .unwrap()
+            .to_cooked(
+                app.world.resource::<LayoutRegistry>(),
+                app.world.resource::<Assets<LayoutAnimation>>(),
+            )
+            .unwrap();
+
+        let cooked_path = path.replace(".layout.json", ".layout.bin");
+        round_trip.insert_asset(Path::new(&cooked_path), cooked);
+        cooked_paths.push(format!("{ROUND_TRIP_SOURCE}://{cooked_path}"));
+        written.push(to_json(&app, handle));
+    }
+
+    let handles = load_layouts(&mut app, &cooked_paths);
+    for ((path, handle), written) in paths.iter().zip(handles.iter()).zip(written.iter()) {
+        assert_eq!(&to_json(&app, handle), written, "{path} did not round trip");
+    }
+}
+
+/// `button.layout.json`, written as RON
+#[cfg(feature = "ron")]
+const BUTTON_RON: &str = r#"
+// Comments and trailing commas are allowed
+(
+    resolution: (1920, 1080),
+    canvas_size: (640, 80),
+    nodes: [
+        (
+            id: "button_hit",
+            position: (0.0, 0.0),
+            size: (540.0, 80.0),
+            anchor: "TopLeft",
+            node_kind: "Null",
+            attributes: {
+                "InputDetection": {
+                    "use_camera_window": true,
+                },
+            },
+        ),
+        (
+            id: "button_content",
+            position: (0.0, 0.0),
+            size: (540.0, 80.0),
+            anchor: "TopLeft",
+            node_kind: "Group",
+            node_data: [
+                (
+                    id: "button_image",
+                    position: (0.0, 0.0),
+                    size: (540.0, 80.0),
+                    anchor: "TopLeft",
+                    node_kind: "Image",
+                    node_data: {
+                        "path": "images/button.png",
+                    },
+                ),
+                (
+                    id: "button_text",
+                    position: (30.0, 10.0),
+                    size: (500.0, 60.0),
+                    anchor: "TopLeft",
+                    node_kind: "Text",
+                    node_data: {
+                        "text": "placeholder",
+                        "size": 50.0,
+                        "color": (0.7, 0.7, 0.7, 1.0),
+                    },
+                ),
+            ],
+        ),
+    ],
+    animations: {
+        "select": {
+            "button_content": [
+                (
+                    timestamp_ms: 0,
+                    targets: {
+                        "Position": (0.0, 0.0),
+                    },
+                ),
+                (
+                    timestamp_ms: 100,
+                    targets: {
+                        "Position": (100.0, 0.0),
+                    },
+                ),
+            ],
+            "button_content/button_image": [
+                (
+                    timestamp_ms: 0,
+                    targets: {
+                        "Color": (0.5, 0.5, 0.5, 1.0),
+                    },
+                ),
+                (
+                    timestamp_ms: 100,
+                    targets: {
+                        "Color": (1.0, 1.0, 1.0, 1.0),
+                    },
+                ),
+            ],
+            "button_content/button_text": [
+                (
+                    timestamp_ms: 0,
+                    targets: {
+                        "Color": (0.5, 0.5, 0.5, 1.0),
+                    },
+                ),
+                (
+                    timestamp_ms: 100,
+                    targets: {
+                        "Color": (1.0, 1.0, 1.0, 1.0),
+                    },
+                ),
+            ],
+            "button_hit": [
+                (
+                    timestamp_ms: 0,
+                    targets: {
+                        "Size": (540.0, 80.0),
+                    },
+                ),
+                (
+                    timestamp_ms: 100,
+                    targets: {
+                        "Size": (640.0, 80.0),
+                    },
+                ),
+            ],
+        },
+    },
+)
+"#;
+
+#[cfg(feature = "ron")]
+#[test]
+fn ron_layout_matches_json() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/button.layout.ron"), BUTTON_RON);
+
+    let mut app = app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[
+            "layouts/button.layout.json".to_string(),
+            format!("{ROUND_TRIP_SOURCE}://layouts/button.layout.ron"),
+        ],
+    );
+
+    assert_eq!(to_json(&app, &handles[0]), to_json(&app, &handles[1]));
+}
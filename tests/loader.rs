@@ -0,0 +1,303 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{
+    asset::{io::memory::Dir, LoadState},
+    prelude::*,
+};
+use serde_json::Value;
+use yabuil::{
+    animation::LayoutAnimation,
+    asset::{Layout, LayoutError, LayoutErrorLocation, LayoutLoaderSettings, LayoutNodeInner},
+};
+
+use common::{app, assert_load_fails, load_layouts, to_json, WordList, ROUND_TRIP_SOURCE};
+
+const WORD_LIST_JSON: &str = r#"{
+    "canvas_size": [100, 100],
+    "nodes": [
+        {
+            "id": "words",
+            "position": [0.0, 0.0],
+            "size": [100.0, 100.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null",
+            "attributes": {
+                "WordList": { "file": "words.txt" }
+            }
+        }
+    ]
+}"#;
+
+#[test]
+fn attributes_load_sidecar_files() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/words.layout.json"), WORD_LIST_JSON);
+    round_trip.insert_asset_text(Path::new("layouts/words.txt"), "play settings\nexit");
+
+    let mut app = app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/words.layout.json")],
+    );
+
+    let layout = app
+        .world
+        .resource::<Assets<Layout>>()
+        .get(handles[0].id())
+        .unwrap();
+    let words = layout.child_by_id("words").unwrap().attributes[0]
+        .downcast_ref::<WordList>()
+        .unwrap();
+    assert_eq!(words.words, ["play", "settings", "exit"]);
+
+    let image_path = words.image.path().unwrap();
+    assert_eq!(image_path.label(), Some("attributes/words.txt"));
+    assert!(app
+        .world
+        .resource::<Assets<Image>>()
+        .contains(words.image.id()));
+}
+
+#[test]
+fn failed_attribute_dependencies_are_reported() {
+    assert_load_fails(&WORD_LIST_JSON.replace("words.txt", "missing.txt"));
+}
+
+const SETTINGS_JSON: &str = r#"{
+    "canvas_size": [400, 100],
+    "nodes": [
+        {
+            "id": "label",
+            "position": [0.0, 0.0],
+            "size": [400.0, 100.0],
+            "anchor": "TopLeft",
+            "node_kind": "Text",
+            "node_data": {
+                "text": "label",
+                "size": 40.0,
+                "color": [1.0, 1.0, 1.0, 1.0]
+            },
+            "attributes": {
+                "Unregistered": {}
+            }
+        }
+    ]
+}"#;
+
+#[test]
+fn load_settings_override_registry_and_layout() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/settings.layout.json"), SETTINGS_JSON);
+
+    let mut app = app(round_trip.clone());
+    let path = format!("{ROUND_TRIP_SOURCE}://layouts/settings.layout.json");
+    let handle: Handle<Layout> = app.world.resource::<AssetServer>().load_with_settings(
+        path.clone(),
+        |settings: &mut LayoutLoaderSettings| {
+            settings.ignore_unknown_registry_data = Some(true);
+            settings.default_font = Some("fonts/default.ttf".into());
+            settings.resolution = Some(UVec2::new(1920, 1080));
+        },
+    );
+
+    for _ in 0..1000 {
+        app.update();
+        match app.world.resource::<AssetServer>().load_state(&handle) {
+            LoadState::Loaded => break,
+            LoadState::Failed => panic!("layout should load with the unknown attribute ignored"),
+            _ => std::thread::sleep(std::time::Duration::from_millis(5)),
+        }
+    }
+
+    let layouts = app.world.resource::<Assets<Layout>>();
+    let layout = layouts.get(handle.id()).expect("layout should have loaded");
+    assert_eq!(layout.resolution, None);
+    assert_eq!(layout.get_resolution(), UVec2::new(1920, 1080));
+    assert!(layout.nodes[0].attributes.is_empty());
+
+    let LayoutNodeInner::Text(text) = &layout.nodes[0].inner else {
+        panic!("label should be a text node");
+    };
+    assert_eq!(text.font, None);
+    assert_eq!(
+        text.handle.path().unwrap().path(),
+        Path::new("fonts/default.ttf")
+    );
+
+    // The settings are not written back out with the layout
+    let written: Value = serde_json::from_str(&to_json(&app, &handle)).unwrap();
+    assert!(written.get("resolution").is_none());
+
+    // Without the settings, the unknown attribute still fails the layout
+    assert_load_fails(SETTINGS_JSON);
+}
+
+const LOCATION_JSON: &str = r#"{
+    "canvas_size": [100, 100],
+    "nodes": [
+        {
+            "id": "menu",
+            "position": [0.0, 0.0],
+            "size": [100.0, 100.0],
+            "anchor": "TopLeft",
+            "node_kind": "Group",
+            "node_data": [
+                {
+                    "id": "title",
+                    "position": [0.0, 0.0],
+                    "size": [100.0, 20.0],
+                    "colour": [1.0, 1.0, 1.0, 1.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Null"
+                }
+            ]
+        }
+    ]
+}"#;
+
+fn location(node_path: &str, line: usize, column: usize) -> LayoutErrorLocation {
+    LayoutErrorLocation {
+        node_path: Some(node_path.into()),
+        line,
+        column,
+    }
+}
+
+#[test]
+fn load_errors_report_where_they_happened() {
+    let error = assert_load_fails(LOCATION_JSON);
+    match error.downcast_ref::<LayoutError>() {
+        Some(LayoutError::UnknownField {
+            location: at,
+            field,
+        }) => {
+            assert_eq!(field, "colour");
+            assert_eq!(*at, location("menu/title", 15, 29));
+        }
+        _ => panic!("expected an unknown field, got {error:?}"),
+    }
+
+    // Syntax errors are reported at the position `serde_json` gives, in the node it was reading
+    let source = LOCATION_JSON
+        .replace(
+            "                    \"colour\": [1.0, 1.0, 1.0, 1.0],\n",
+            "",
+        )
+        .replace(r#""size": [100.0, 20.0],"#, r#""size": [100.0, 20.0]"#);
+    let error = assert_load_fails(&source);
+    match error.downcast_ref::<LayoutError>() {
+        Some(LayoutError::JSON { location: at, .. }) => {
+            assert_eq!(*at, location("menu/title", 15, 21));
+        }
+        _ => panic!("expected a syntax error, got {error:?}"),
+    }
+}
+
+const PROBLEMS_JSON: &str = r#"{
+    "canvas_size": [100, 100],
+    "nodes": [
+        {
+            "id": "good",
+            "position": [0.0, 0.0],
+            "size": [100.0, 20.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null"
+        },
+        {
+            "id": "unregistered",
+            "position": [0.0, 20.0],
+            "size": [100.0, 20.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null",
+            "attributes": { "Unregistered": {} }
+        },
+        {
+            "id": "invalid",
+            "position": "left",
+            "size": [100.0, 20.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null"
+        }
+    ],
+    "animations": {
+        "slide": {
+            "missing": [
+                { "timestamp_ms": 0, "targets": { "Position": [0.0, 0.0] } }
+            ],
+            "good": [
+                { "timestamp_ms": 0, "targets": { "Position": [0.0, 0.0] } }
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn every_problem_is_reported() {
+    let error = assert_load_fails(PROBLEMS_JSON);
+    let Some(LayoutError::Diagnostics(errors)) = error.downcast_ref::<LayoutError>() else {
+        panic!("expected every problem to be reported, got {error:?}");
+    };
+
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(matches!(
+        &errors[0],
+        LayoutError::UnknownAttribute { location: at, attribute }
+            if attribute == "Unregistered" && *at == location("unregistered", 17, 43)
+    ));
+    assert!(matches!(
+        &errors[1],
+        LayoutError::InvalidField { location: at, field, .. }
+            if field == "position" && *at == location("invalid", 21, 23)
+    ));
+    assert!(matches!(
+        &errors[2],
+        LayoutError::UnknownAnimationNode { location: at, animation }
+            if animation == "slide" && *at == location("missing", 29, 22)
+    ));
+}
+
+#[test]
+fn problems_are_skipped_in_partial_layouts() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/problems.layout.json"), PROBLEMS_JSON);
+
+    let mut app = app(round_trip);
+    let handle: Handle<Layout> = app.world.resource::<AssetServer>().load_with_settings(
+        format!("{ROUND_TRIP_SOURCE}://layouts/problems.layout.json"),
+        |settings: &mut LayoutLoaderSettings| {
+            settings.ignore_unknown_registry_data = Some(true);
+        },
+    );
+
+    for _ in 0..1000 {
+        app.update();
+        match app.world.resource::<AssetServer>().load_state(&handle) {
+            LoadState::Loaded => break,
+            LoadState::Failed => panic!("layout should load with its problems skipped"),
+            _ => std::thread::sleep(std::time::Duration::from_millis(5)),
+        }
+    }
+
+    let layout = app
+        .world
+        .resource::<Assets<Layout>>()
+        .get(handle.id())
+        .expect("layout should have loaded");
+    assert!(layout.child_by_id("good").is_some());
+    assert!(layout
+        .child_by_id("unregistered")
+        .unwrap()
+        .attributes
+        .is_empty());
+    assert!(layout.child_by_id("invalid").is_none());
+
+    let animation = app
+        .world
+        .resource::<Assets<LayoutAnimation>>()
+        .get(layout.animations[0].id())
+        .unwrap();
+    let nodes: Vec<&str> = animation.keys().map(|path| path.as_str()).collect();
+    assert_eq!(nodes, ["good"]);
+}
@@ -0,0 +1,386 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{
+    asset::io::memory::Dir, ecs::system::CommandQueue, prelude::*, render::view::RenderLayers,
+};
+use yabuil::{
+    animation::{LayoutAnimationPlaybackState, PlaybackState},
+    asset::Layout,
+    components::{spawning::spawn_layout, LayoutNodeId, SpawnLayoutError},
+    node::{LayoutInfo, ZIndex},
+    views::{
+        GroupNodeArgs, ImageNodeArgs, LayoutNodeArgs, NodeArgs, NodeEntityError, NodeEntityMut,
+        TextNodeArgs,
+    },
+    LayoutCommands, LayoutId,
+};
+
+use common::{app, load_layouts, spawn_bundle, spawn_source, TrackedNodes, ROUND_TRIP_SOURCE};
+
+const GROUP_JSON: &str = r#"{
+    "canvas_size": [400, 400],
+    "nodes": [
+        {
+            "id": "menu",
+            "position": [0.0, 0.0],
+            "size": [400.0, 400.0],
+            "anchor": "TopLeft",
+            "node_kind": "Group",
+            "node_data": [
+                {
+                    "id": "title",
+                    "position": [0.0, 0.0],
+                    "size": [400.0, 50.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Text",
+                    "node_data": { "text": "title", "size": 20.0, "color": [1.0, 1.0, 1.0, 1.0] },
+                    "attributes": { "Tracked": {} }
+                },
+                {
+                    "id": "row",
+                    "position": [0.0, 50.0],
+                    "size": [400.0, 50.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Group",
+                    "node_data": [
+                        {
+                            "id": "icon",
+                            "position": [0.0, 0.0],
+                            "size": [50.0, 50.0],
+                            "anchor": "TopLeft",
+                            "node_kind": "Null",
+                            "attributes": { "Tracked": {} }
+                        }
+                    ],
+                    "attributes": { "Tracked": {} }
+                }
+            ]
+        },
+        {
+            "id": "footer",
+            "position": [0.0, 350.0],
+            "size": [400.0, 50.0],
+            "anchor": "TopLeft",
+            "node_kind": "Group",
+            "node_data": [
+                {
+                    "id": "icon",
+                    "position": [0.0, 0.0],
+                    "size": [50.0, 50.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Null"
+                }
+            ]
+        }
+    ]
+}"#;
+
+fn node_args(id: &str) -> NodeArgs {
+    NodeArgs {
+        id: id.to_string(),
+        size: Vec2::new(50.0, 50.0),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn nodes_are_added_to_groups() {
+    let (mut app, root, _) = spawn_source(GROUP_JSON);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut menu = node.child("menu");
+    let mut menu = menu.group();
+
+    menu.add_image_node(ImageNodeArgs {
+        node: node_args("icon"),
+        image: Handle::default(),
+        tint: None,
+    })
+    .unwrap();
+    menu.add_text_node(TextNodeArgs {
+        node: node_args("label"),
+        text: "label".to_string(),
+        font_size: 20.0,
+        font: Handle::default(),
+        color: Color::WHITE,
+        alignment: JustifyText::Left,
+    })
+    .unwrap();
+    let mut list = menu
+        .add_group_node(GroupNodeArgs {
+            node: node_args("list"),
+            child_anchor: Default::default(),
+        })
+        .unwrap();
+    list.add_image_node(ImageNodeArgs {
+        node: node_args("icon"),
+        image: Handle::default(),
+        tint: None,
+    })
+    .unwrap();
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    for path in [
+        "menu/title",
+        "menu/icon",
+        "menu/label",
+        "menu/list",
+        "menu/list/icon",
+    ] {
+        let child = node.child(path);
+        assert_eq!(
+            child.get::<LayoutNodeId>().unwrap().qualified(),
+            Path::new(path),
+            "{path} was not spawned"
+        );
+    }
+}
+
+const CARD_JSON: &str = r#"{
+    "canvas_size": [200, 200],
+    "nodes": [
+        {
+            "id": "dot",
+            "position": [0.0, 0.0],
+            "size": [10.0, 10.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null"
+        }
+    ],
+    "animations": {
+        "pulse": {
+            "dot": [
+                { "timestamp_ms": 0, "targets": { "Position": [0.0, 0.0] } }
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn added_nodes_get_the_components_of_their_kind() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/spawned.layout.json"), GROUP_JSON);
+    round_trip.insert_asset_text(Path::new("layouts/card.layout.json"), CARD_JSON);
+
+    let mut app = app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[
+            format!("{ROUND_TRIP_SOURCE}://layouts/spawned.layout.json"),
+            format!("{ROUND_TRIP_SOURCE}://layouts/card.layout.json"),
+        ],
+    );
+    let root = app.world.spawn(RenderLayers::layer(3)).id();
+    spawn_layout(&mut app.world, root, handles[0].clone(), |_, _, _| {}).unwrap();
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut menu = node.child("menu");
+    let mut menu = menu.group();
+    let icon = menu
+        .add_image_node(ImageNodeArgs {
+            node: node_args("icon"),
+            image: Handle::default(),
+            tint: None,
+        })
+        .unwrap()
+        .id();
+    let list = menu
+        .add_group_node(GroupNodeArgs {
+            node: node_args("list"),
+            child_anchor: Default::default(),
+        })
+        .unwrap()
+        .into_node()
+        .id();
+    let card = menu
+        .add_layout_node(LayoutNodeArgs {
+            node: node_args("card"),
+            layout: handles[1].clone(),
+        })
+        .unwrap()
+        .id();
+
+    for entity in [icon, list, card] {
+        let entity = app.world.entity(entity);
+        assert_eq!(entity.get::<LayoutId>().unwrap().0, root);
+        assert_eq!(entity.get::<RenderLayers>(), Some(&RenderLayers::layer(3)));
+        assert!(entity.contains::<ZIndex>());
+    }
+
+    // Containers lay out their children, and sublayouts also play their own animations
+    assert!(!app.world.entity(icon).contains::<LayoutInfo>());
+    assert!(app.world.entity(list).contains::<LayoutInfo>());
+    assert!(app.world.entity(card).contains::<LayoutInfo>());
+    assert!(app
+        .world
+        .entity(card)
+        .contains::<LayoutAnimationPlaybackState>());
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut card = node.child("menu/card");
+    assert!(matches!(
+        card.layout().animation_state("pulse"),
+        Some(PlaybackState::Stopped)
+    ));
+    let dot = card.child("dot").id();
+    assert_eq!(node_path(&app.world, dot), Path::new("menu/card/dot"));
+}
+
+#[test]
+fn nodes_cannot_be_added_with_a_taken_id() {
+    let (mut app, root, _) = spawn_source(GROUP_JSON);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut menu = node.child("menu");
+    let mut menu = menu.group();
+    assert!(matches!(
+        menu.add_group_node(GroupNodeArgs {
+            node: node_args("row"),
+            child_anchor: Default::default(),
+        }),
+        Err(SpawnLayoutError::DuplicateChild(_, name)) if name == "row"
+    ));
+
+    // Ids only have to be unique among siblings
+    menu.add_image_node(ImageNodeArgs {
+        node: node_args("icon"),
+        image: Handle::default(),
+        tint: None,
+    })
+    .unwrap();
+
+    let menu = NodeEntityMut::new(&mut app.world, root).child("menu").id();
+    assert_eq!(app.world.get::<Children>(menu).unwrap().len(), 3);
+}
+
+#[test]
+fn adding_nodes_fails_once_the_layout_is_unloaded() {
+    let (mut app, root, handle) = spawn_source(GROUP_JSON);
+    app.world
+        .resource_mut::<Assets<Layout>>()
+        .remove(handle.id());
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut menu = node.child("menu");
+    let mut menu = menu.group();
+
+    assert!(matches!(
+        menu.add_image_node(ImageNodeArgs {
+            node: node_args("icon"),
+            image: Handle::default(),
+            tint: None,
+        }),
+        Err(SpawnLayoutError::NotLoaded)
+    ));
+    assert!(matches!(
+        menu.add_text_node(TextNodeArgs {
+            node: node_args("label"),
+            text: "label".to_string(),
+            font_size: 20.0,
+            font: Handle::default(),
+            color: Color::WHITE,
+            alignment: JustifyText::Left,
+        }),
+        Err(SpawnLayoutError::NotLoaded)
+    ));
+    assert!(matches!(
+        menu.add_group_node(GroupNodeArgs {
+            node: node_args("list"),
+            child_anchor: Default::default(),
+        }),
+        Err(SpawnLayoutError::NotLoaded)
+    ));
+}
+
+fn node_path(world: &World, entity: Entity) -> &Path {
+    world.get::<LayoutNodeId>(entity).unwrap().qualified()
+}
+
+#[test]
+fn despawned_nodes_are_torn_down() {
+    let (mut app, root, _) = spawn_bundle(GROUP_JSON);
+    assert_eq!(app.world.resource::<TrackedNodes>().applied, 3);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let row = node.child("menu/row").id();
+    let icon = node.child("menu/row/icon").id();
+    node.child("menu/row").despawn_node().unwrap();
+
+    assert!(app.world.get_entity(row).is_none());
+    assert!(app.world.get_entity(icon).is_none());
+    assert_eq!(app.world.resource::<TrackedNodes>().despawned, 2);
+
+    let node = NodeEntityMut::new(&mut app.world, root);
+    assert!(matches!(
+        node.despawn_node(),
+        Err(NodeEntityError::IsRoot(_))
+    ));
+}
+
+#[test]
+fn detached_nodes_are_left_at_the_top_level() {
+    let (mut app, root, _) = spawn_bundle(GROUP_JSON);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut row = node.child("menu/row");
+    row.detach().unwrap();
+    let row = row.id();
+
+    assert!(app.world.get::<Parent>(row).is_none());
+    assert_eq!(app.world.get::<Visibility>(row), Some(&Visibility::Hidden));
+    assert!(NodeEntityMut::new(&mut app.world, root)
+        .get_child("menu/row")
+        .is_err());
+
+    // Despawning the layout does not reach nodes that are no longer in its tree
+    let mut queue = CommandQueue::default();
+    Commands::new(&mut queue, &app.world).despawn_layout(root);
+    queue.apply(&mut app.world);
+
+    assert!(app.world.get_entity(root).is_none());
+    assert!(app.world.get_entity(row).is_some());
+    assert_eq!(app.world.resource::<TrackedNodes>().despawned, 1);
+}
+
+#[test]
+fn nodes_are_moved_between_groups() {
+    let (mut app, root, _) = spawn_bundle(GROUP_JSON);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut row = node.child("menu/row");
+    row.detach().unwrap();
+    row.move_to("footer", 0).unwrap();
+    let row = row.id();
+    let icon = node.child("footer/row/icon").id();
+    let footer = node.child("footer").id();
+
+    assert_eq!(node_path(&app.world, row), Path::new("footer/row"));
+    assert_eq!(node_path(&app.world, icon), Path::new("footer/row/icon"));
+    assert_eq!(app.world.get::<Parent>(row).unwrap().get(), footer);
+    assert_eq!(app.world.get::<Children>(footer).unwrap()[0], row);
+    assert_eq!(
+        app.world.get::<Visibility>(row),
+        Some(&Visibility::Inherited)
+    );
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    assert!(matches!(
+        node.child("footer").move_to("footer/row", 0),
+        Err(NodeEntityError::MoveIntoDescendant(_))
+    ));
+    assert!(matches!(
+        node.child("menu/title").move_to("footer/icon", 0),
+        Err(NodeEntityError::NotAContainer(_))
+    ));
+    assert!(matches!(
+        node.child("footer/row/icon").move_to("footer", 0),
+        Err(NodeEntityError::DuplicateChild(_, _))
+    ));
+    assert!(matches!(node.child("footer").move_to("menu", 0), Ok(())));
+    assert_eq!(
+        node_path(&app.world, icon),
+        Path::new("menu/footer/row/icon")
+    );
+}
@@ -0,0 +1,303 @@
+mod common;
+
+use std::{
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bevy::{asset::io::memory::Dir, prelude::*};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use yabuil::{
+    animation::{LayoutAnimation, LayoutAnimationTarget, ResourceRestrictedWorld},
+    asset::Layout,
+    builtin::PositionAnimation,
+    views::{NodeEntityMut, NodeMut},
+    LayoutApp, LayoutAttribute, LayoutRegistry, LayoutRegistryError,
+};
+
+use common::{app, example_layouts, load_layouts, MainMenuButton, ROUND_TRIP_SOURCE};
+
+/// Follows `schema` to the definition it refers to, if it is a reference
+fn resolve_ref<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema["$ref"].as_str() {
+        Some(path) => resolve_ref(root, root.pointer(path.trim_start_matches('#')).unwrap()),
+        None => schema,
+    }
+}
+
+#[test]
+fn json_schema_covers_registered_data() {
+    let app = app(Dir::default());
+    let schema = Layout::json_schema(
+        app.world.resource::<LayoutRegistry>(),
+        &app.world.resource::<AppTypeRegistry>().read(),
+    );
+    let definitions = &schema["definitions"];
+
+    let node = &definitions["Node"];
+    let kinds: Vec<&str> = node["properties"]["node_kind"]["enum"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|kind| kind.as_str().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        ["Null", "Image", "Text", "Layout", "Group", "Prefab", "Repeat"]
+    );
+
+    let anchor = resolve_ref(&schema, &node["properties"]["anchor"]["anyOf"][0]);
+    assert_eq!(anchor["oneOf"].as_array().unwrap().len(), 9);
+    assert_eq!(
+        anchor["oneOf"][0],
+        serde_json::json!({ "const": "TopLeft" })
+    );
+
+    // Reflected payloads are described by their fields, other payloads accept anything
+    let attributes = &definitions["Attributes"]["properties"];
+    let input_detection = resolve_ref(&schema, &attributes["InputDetection"]["anyOf"][0]);
+    assert_eq!(
+        input_detection["properties"]["use_camera_window"],
+        serde_json::json!({ "type": "boolean" })
+    );
+    assert!(attributes["MainMenuButton"]["anyOf"][0]
+        .get("type")
+        .is_none());
+
+    let targets = &definitions["AnimationTargets"]["properties"];
+    assert_eq!(
+        resolve_ref(&schema, &targets["Position"]["anyOf"][0]),
+        &definitions["Vec2"]
+    );
+    assert_eq!(
+        resolve_ref(&schema, &targets["Color"]["anyOf"][0]),
+        &definitions["Color"]
+    );
+    assert_eq!(
+        resolve_ref(&schema, &targets["Rotation"]["anyOf"][0]),
+        &serde_json::json!({ "type": "number", "title": "yabuil::builtin::RotationAnimation" })
+    );
+
+    // Every attribute and animation target used by the examples is in the schema
+    fn check_names(value: &Value, attributes: &Value, targets: &Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter() {
+                    let names = match key.as_str() {
+                        "attributes" => attributes,
+                        "targets" => targets,
+                        _ => {
+                            check_names(value, attributes, targets);
+                            continue;
+                        }
+                    };
+
+                    for name in value.as_object().unwrap().keys() {
+                        assert!(names.get(name).is_some(), "{name} is not in the schema");
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values.iter() {
+                    check_names(value, attributes, targets);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for path in example_layouts() {
+        let layout: Value = serde_json::from_str(
+            &std::fs::read_to_string(
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("assets")
+                    .join(path),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        check_names(&layout, attributes, targets);
+    }
+}
+
+/// Claims the name of the `MainMenuButton` attribute that the examples use
+#[derive(Serialize, Deserialize, TypePath, Clone)]
+struct OtherMainMenuButton(Value);
+
+impl LayoutAttribute for OtherMainMenuButton {
+    const NAME: &'static str = "MainMenuButton";
+
+    fn apply(&self, _: NodeEntityMut) {}
+}
+
+#[test]
+fn registry_detects_name_collisions() {
+    let app = app(Dir::default());
+    let registry = app.world.resource::<LayoutRegistry>();
+
+    let registered_path = |registry: &LayoutRegistry| {
+        registry
+            .attributes()
+            .into_iter()
+            .find(|registered| registered.name == "MainMenuButton")
+            .map(|registered| registered.type_path)
+    };
+
+    let main_menu_button = registered_path(registry).unwrap();
+    assert!(main_menu_button.ends_with("::MainMenuButton"));
+    assert!(registry
+        .animations()
+        .iter()
+        .any(|registered| registered.name == "Position"));
+
+    assert!(registry.try_register_attribute::<MainMenuButton>().is_ok());
+    assert!(matches!(
+        registry.try_register_attribute::<OtherMainMenuButton>(),
+        Err(LayoutRegistryError::AttributeCollision { registered, .. }) if registered == main_menu_button
+    ));
+    assert!(matches!(
+        registry.try_register_cloneable_attribute::<OtherMainMenuButton>(),
+        Err(LayoutRegistryError::AttributeCollision { registered, .. }) if registered == main_menu_button
+    ));
+    assert_eq!(registered_path(registry), Some(main_menu_button));
+    assert!(registry
+        .try_register_cloneable_animation::<PositionAnimation>()
+        .is_ok());
+
+    // Only the type that owns the name can unregister it
+    assert!(!registry.unregister_attribute::<OtherMainMenuButton>());
+    registry.register_attribute::<OtherMainMenuButton>();
+    assert!(registered_path(registry)
+        .unwrap()
+        .ends_with("::OtherMainMenuButton"));
+
+    assert!(!registry.unregister_attribute::<MainMenuButton>());
+    assert!(registry.unregister_attribute::<OtherMainMenuButton>());
+    assert_eq!(registered_path(registry), None);
+
+    assert!(registry
+        .try_register_cloneable_attribute::<OtherMainMenuButton>()
+        .is_ok());
+    assert!(registered_path(registry)
+        .unwrap()
+        .ends_with("::OtherMainMenuButton"));
+}
+
+static DROPPED_ATTRIBUTES: AtomicUsize = AtomicUsize::new(0);
+static DROPPED_TARGETS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts how many times it has been dropped, to check that layouts free their attributes
+#[derive(Serialize, Deserialize, TypePath, Clone)]
+struct DropCounter(u32);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        DROPPED_ATTRIBUTES.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl LayoutAttribute for DropCounter {
+    const NAME: &'static str = "DropCounter";
+
+    fn apply(&self, _: NodeEntityMut) {}
+}
+
+/// Counts how many times it has been dropped, to check that animations free their targets
+#[derive(Serialize, Deserialize, TypePath)]
+struct DropCounterTarget(f32);
+
+impl Drop for DropCounterTarget {
+    fn drop(&mut self) {
+        DROPPED_TARGETS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl LayoutAnimationTarget for DropCounterTarget {
+    const NAME: &'static str = "DropCounter";
+
+    fn interpolate(&self, _: Option<&Self>, _: NodeMut, _: ResourceRestrictedWorld, _: f32) {}
+}
+
+const DROP_COUNTER_JSON: &str = r#"{
+    "canvas_size": [400, 100],
+    "nodes": [
+        {
+            "id": "first",
+            "position": [0.0, 0.0],
+            "size": [200.0, 100.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null",
+            "attributes": {
+                "DropCounter": 1
+            }
+        },
+        {
+            "id": "second",
+            "position": [200.0, 0.0],
+            "size": [200.0, 100.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null",
+            "attributes": {
+                "DropCounter": 2
+            }
+        }
+    ],
+    "animations": {
+        "fade": {
+            "first": [
+                {
+                    "timestamp_ms": 0,
+                    "targets": {
+                        "DropCounter": 0.0
+                    }
+                },
+                {
+                    "timestamp_ms": 100,
+                    "targets": {
+                        "DropCounter": 1.0
+                    }
+                }
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn dropped_layouts_free_their_attributes_and_targets() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/drops.layout.json"), DROP_COUNTER_JSON);
+
+    let mut app = app(round_trip);
+    app.register_layout_animation::<DropCounterTarget>();
+    app.world
+        .resource::<LayoutRegistry>()
+        .register_cloneable_attribute::<DropCounter>();
+
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/drops.layout.json")],
+    );
+    assert_eq!(DROPPED_ATTRIBUTES.load(Ordering::SeqCst), 0);
+    assert_eq!(DROPPED_TARGETS.load(Ordering::SeqCst), 0);
+
+    let layout = app
+        .world
+        .resource_mut::<Assets<Layout>>()
+        .remove(handles[0].id())
+        .unwrap();
+
+    let copy = layout.nodes[0].attributes[0].try_clone().unwrap();
+    assert_eq!(copy.downcast_ref::<DropCounter>().unwrap().0, 1);
+    drop(copy);
+    assert_eq!(DROPPED_ATTRIBUTES.load(Ordering::SeqCst), 1);
+
+    let animations = layout.animations.clone();
+    drop(layout);
+    assert_eq!(DROPPED_ATTRIBUTES.load(Ordering::SeqCst), 3);
+
+    let mut assets = app.world.resource_mut::<Assets<LayoutAnimation>>();
+    let animation = assets.remove(animations[0].id()).unwrap();
+    drop(animation);
+    assert_eq!(DROPPED_TARGETS.load(Ordering::SeqCst), 2);
+}
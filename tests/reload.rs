@@ -0,0 +1,261 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{asset::io::memory::Dir, ecs::system::CommandQueue, prelude::*};
+use yabuil::{
+    animation::{LayoutAnimationEvent, LayoutAnimationEventKind},
+    asset::Layout,
+    views::NodeEntityMut,
+    LayoutBundle, LayoutCommands,
+};
+
+use common::{advance_frames, app, load_layouts, spawn_bundle, TrackedNodes, ROUND_TRIP_SOURCE};
+
+const RELOAD_JSON: &str = r#"{
+    "canvas_size": [400, 400],
+    "nodes": [
+        {
+            "id": "menu",
+            "position": [0.0, 0.0],
+            "size": [400.0, 400.0],
+            "anchor": "TopLeft",
+            "node_kind": "Group",
+            "node_data": [
+                {
+                    "id": "title",
+                    "position": [0.0, 0.0],
+                    "size": [400.0, 50.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Null",
+                    "attributes": { "Tracked": {} }
+                }
+            ],
+            "attributes": { "Tracked": {} }
+        }
+    ],
+    "animations": {
+        "slide": {
+            "menu/title": [
+                { "timestamp_ms": 0, "targets": { "Position": [0.0, 0.0] } },
+                { "timestamp_ms": 1000, "targets": { "Position": [100.0, 0.0] } }
+            ]
+        }
+    }
+}"#;
+
+/// Tags the `Tracked` attributes of [`RELOAD_JSON`] with `version`
+fn tagged(source: &str, version: &str) -> String {
+    source
+        .replacen(
+            r#""Tracked": {}"#,
+            &format!(r#""Tracked": {{ "tag": "{version} title" }}"#),
+            1,
+        )
+        .replacen(
+            r#""Tracked": {}"#,
+            &format!(r#""Tracked": {{ "tag": "{version} menu" }}"#),
+            1,
+        )
+}
+
+/// Spawns a tagged [`RELOAD_JSON`], and loads a version of it where the title is renamed to
+/// `heading` and the attributes have other tags
+fn spawn_versions() -> (App, Entity, Handle<Layout>, Handle<Layout>) {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(
+        Path::new("layouts/spawned.layout.json"),
+        &tagged(RELOAD_JSON, "old"),
+    );
+    round_trip.insert_asset_text(
+        Path::new("layouts/edited.layout.json"),
+        &tagged(&RELOAD_JSON.replace(r#"title""#, r#"heading""#), "new"),
+    );
+
+    let mut app = app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[
+            format!("{ROUND_TRIP_SOURCE}://layouts/spawned.layout.json"),
+            format!("{ROUND_TRIP_SOURCE}://layouts/edited.layout.json"),
+        ],
+    );
+    let root = app.world.spawn(LayoutBundle::new(handles[0].clone())).id();
+    app.update();
+
+    (app, root, handles[0].clone(), handles[1].clone())
+}
+
+/// Replaces the layout behind `handle` with the one behind `edited`, like editing the file would
+fn replace(app: &mut App, handle: &Handle<Layout>, edited: &Handle<Layout>) {
+    let mut layouts = app.world.resource_mut::<Assets<Layout>>();
+    let edited = layouts.remove(edited.id()).unwrap();
+    *layouts.get_mut(handle.id()).unwrap() = edited;
+}
+
+fn child(app: &mut App, root: Entity, path: &str) -> Entity {
+    NodeEntityMut::new(&mut app.world, root).child(path).id()
+}
+
+/// Marks the layout as modified, the same way that hot reloading does
+fn modify(app: &mut App, handle: &Handle<Layout>) {
+    app.world
+        .resource_mut::<Assets<Layout>>()
+        .get_mut(handle.id())
+        .unwrap();
+}
+
+fn play_slide(app: &mut App, root: Entity) {
+    NodeEntityMut::new(&mut app.world, root)
+        .layout()
+        .play_animation("slide")
+        .unwrap();
+    app.update();
+}
+
+fn interrupted(app: &mut App) -> Vec<(Entity, String)> {
+    app.world
+        .resource_mut::<Events<LayoutAnimationEvent>>()
+        .drain()
+        .filter(|event| event.kind == LayoutAnimationEventKind::Interrupted)
+        .map(|event| (event.entity, event.animation))
+        .collect()
+}
+
+#[test]
+fn reloads_tear_down_the_old_nodes() {
+    let (mut app, root, handle) = spawn_bundle(RELOAD_JSON);
+    assert_eq!(app.world.resource::<TrackedNodes>().applied, 2);
+
+    modify(&mut app, &handle);
+    advance_frames(&mut app, 2);
+
+    let tracked = app.world.resource::<TrackedNodes>();
+    assert_eq!(tracked.despawned, 2);
+    assert_eq!(tracked.applied, 4);
+    NodeEntityMut::new(&mut app.world, root).child("menu/title");
+}
+
+#[test]
+fn reloads_tear_down_the_attributes_that_were_applied() {
+    let (mut app, root, handle, edited) = spawn_versions();
+
+    replace(&mut app, &handle, &edited);
+    advance_frames(&mut app, 2);
+
+    // The title is gone from the edited layout, and the menu has another tag
+    assert_eq!(
+        app.world.resource::<TrackedNodes>().despawned_tags,
+        ["old menu", "old title"]
+    );
+    NodeEntityMut::new(&mut app.world, root).child("menu/heading");
+}
+
+#[test]
+fn reloads_interrupt_removed_animations() {
+    let (mut app, root, handle) = spawn_bundle(RELOAD_JSON);
+    play_slide(&mut app, root);
+
+    // Animations that are still in the layout keep playing
+    modify(&mut app, &handle);
+    advance_frames(&mut app, 2);
+    assert!(interrupted(&mut app).is_empty());
+
+    app.world
+        .resource_mut::<Assets<Layout>>()
+        .get_mut(handle.id())
+        .unwrap()
+        .animations
+        .clear();
+    advance_frames(&mut app, 2);
+    assert_eq!(interrupted(&mut app), [(root, "slide".to_string())]);
+}
+
+#[test]
+fn despawned_layouts_interrupt_their_animations() {
+    let (mut app, root, _) = spawn_bundle(RELOAD_JSON);
+    play_slide(&mut app, root);
+
+    let mut queue = CommandQueue::default();
+    Commands::new(&mut queue, &app.world).despawn_layout(root);
+    queue.apply(&mut app.world);
+
+    assert_eq!(interrupted(&mut app), [(root, "slide".to_string())]);
+    assert_eq!(app.world.resource::<TrackedNodes>().despawned, 2);
+}
+
+#[test]
+fn despawned_layouts_are_torn_down_with_the_attributes_that_were_applied() {
+    // The layout is despawned after it was modified, but before it was reloaded
+    let (mut app, root, handle, edited) = spawn_versions();
+    replace(&mut app, &handle, &edited);
+
+    let mut queue = CommandQueue::default();
+    Commands::new(&mut queue, &app.world).despawn_layout(root);
+    queue.apply(&mut app.world);
+    assert_eq!(
+        app.world.resource::<TrackedNodes>().despawned_tags,
+        ["old menu", "old title"]
+    );
+
+    // The layout is no longer loaded at all
+    let (mut app, root, handle, _) = spawn_versions();
+    app.world
+        .resource_mut::<Assets<Layout>>()
+        .remove(handle.id())
+        .unwrap();
+
+    let mut queue = CommandQueue::default();
+    Commands::new(&mut queue, &app.world).despawn_layout(root);
+    queue.apply(&mut app.world);
+    assert_eq!(
+        app.world.resource::<TrackedNodes>().despawned_tags,
+        ["old menu", "old title"]
+    );
+}
+
+#[test]
+fn failed_reloads_keep_the_old_tree() {
+    let (mut app, root, handle) = spawn_bundle(RELOAD_JSON);
+    let title = child(&mut app, root, "menu/title");
+
+    // The layout is modified, but is no longer loaded by the time it would be respawned
+    modify(&mut app, &handle);
+    let removed = app
+        .world
+        .resource_mut::<Assets<Layout>>()
+        .remove(handle.id())
+        .unwrap();
+    advance_frames(&mut app, 2);
+    assert_eq!(child(&mut app, root, "menu/title"), title);
+
+    // The failed reload is not retried once the layout is back
+    app.world
+        .resource_mut::<Assets<Layout>>()
+        .insert(handle.id(), removed);
+    advance_frames(&mut app, 2);
+    assert_eq!(child(&mut app, root, "menu/title"), title);
+
+    // But the next modification reloads the layout as usual
+    modify(&mut app, &handle);
+    advance_frames(&mut app, 2);
+    assert_ne!(child(&mut app, root, "menu/title"), title);
+}
+
+#[test]
+fn reloads_with_unknown_animation_nodes_keep_the_old_tree() {
+    let (mut app, root, handle) = spawn_bundle(RELOAD_JSON);
+    let title = child(&mut app, root, "menu/title");
+
+    // `slide` still animates `menu/title`, which is no longer in the layout
+    app.world
+        .resource_mut::<Assets<Layout>>()
+        .get_mut(handle.id())
+        .unwrap()
+        .nodes[0]
+        .id = "panel".to_string();
+    advance_frames(&mut app, 2);
+
+    assert_eq!(child(&mut app, root, "menu/title"), title);
+    assert_eq!(app.world.resource::<TrackedNodes>().despawned, 0);
+}
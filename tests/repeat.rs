@@ -0,0 +1,184 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{asset::io::memory::Dir, prelude::*};
+use serde_json::Value;
+use yabuil::{
+    asset::{Layout, LayoutNodeInner},
+    components::spawning::spawn_layout,
+    views::{NodeEntityMut, RepeatNodeMut},
+};
+
+use common::{
+    app, assert_contains, load_layouts, spawn_bundle, to_json, TrackedNodes, ROUND_TRIP_SOURCE,
+};
+
+const REPEAT_JSON: &str = r#"{
+    "canvas_size": [400, 400],
+    "nodes": [
+        {
+            "id": "list",
+            "position": [10.0, 10.0],
+            "size": [300.0, 200.0],
+            "anchor": "TopLeft",
+            "node_kind": "Repeat",
+            "node_data": {
+                "count": 5,
+                "stride": [100.0, 0.0],
+                "columns": 2,
+                "row_stride": [0.0, 50.0],
+                "template": {
+                    "id": "row",
+                    "position": [5.0, 5.0],
+                    "size": [90.0, 40.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Group",
+                    "node_data": [
+                        {
+                            "id": "label",
+                            "position": [0.0, 0.0],
+                            "size": [90.0, 40.0],
+                            "anchor": "TopLeft",
+                            "node_kind": "Text",
+                            "node_data": { "text": "row", "size": 20.0, "color": [1.0, 1.0, 1.0, 1.0] }
+                        }
+                    ]
+                },
+                "overrides": {
+                    "row_1/label": { "text": "second" },
+                    "row_4": { "position": [0.0, 150.0] }
+                }
+            }
+        }
+    ]
+}"#;
+
+#[test]
+fn repeated_nodes_are_spawned_and_written_back() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/repeat.layout.json"), REPEAT_JSON);
+
+    let mut app = app(round_trip.clone());
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/repeat.layout.json")],
+    );
+
+    {
+        let layouts = app.world.resource::<Assets<Layout>>();
+        let layout = layouts.get(handles[0].id()).unwrap();
+        let template = layout.child_by_id("list/row_2/label").unwrap();
+        assert!(matches!(template.inner, LayoutNodeInner::Text(_)));
+        assert!(layout.child_by_id("list/row_5").is_some());
+        assert!(layout.child_by_id("list/row_02").is_none());
+    }
+
+    let root = app.world.spawn_empty().id();
+    spawn_layout(&mut app.world, root, handles[0].clone(), |_, _, _| {}).unwrap();
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut list = node.child("list");
+    let mut repeat = list.repeat();
+    assert_eq!(repeat.count(), 5);
+
+    let positions: Vec<Vec2> = (0..5)
+        .map(|index| {
+            repeat
+                .item(index)
+                .get::<yabuil::node::Node>()
+                .unwrap()
+                .position
+        })
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            Vec2::new(5.0, 5.0),
+            Vec2::new(105.0, 5.0),
+            Vec2::new(5.0, 55.0),
+            Vec2::new(105.0, 55.0),
+            Vec2::new(0.0, 150.0),
+        ]
+    );
+
+    let label = |repeat: &mut RepeatNodeMut, index: usize| {
+        repeat
+            .item(index)
+            .child("label")
+            .get::<Text>()
+            .unwrap()
+            .sections[0]
+            .value
+            .clone()
+    };
+    assert_eq!(label(&mut repeat, 0), "row");
+    assert_eq!(label(&mut repeat, 1), "second");
+
+    repeat.set_count(2).unwrap();
+    assert_eq!(repeat.count(), 2);
+    assert!(repeat.get_item(2).is_none());
+
+    repeat.set_count(7).unwrap();
+    assert_eq!(repeat.count(), 7);
+    assert_eq!(
+        repeat.item(6).get::<yabuil::node::Node>().unwrap().position,
+        Vec2::new(5.0, 155.0)
+    );
+
+    let json = to_json(&app, &handles[0]);
+    let written: Value = serde_json::from_str(&json).unwrap();
+    assert_contains(
+        &serde_json::from_str(REPEAT_JSON).unwrap(),
+        &written,
+        "repeat",
+    );
+
+    round_trip.insert_asset_text(Path::new("layouts/written.layout.json"), &json);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/written.layout.json")],
+    );
+    assert_eq!(to_json(&app, &handles[0]), json);
+}
+
+const TRACKED_REPEAT_JSON: &str = r#"{
+    "canvas_size": [400, 400],
+    "nodes": [
+        {
+            "id": "list",
+            "position": [0.0, 0.0],
+            "size": [400.0, 400.0],
+            "anchor": "TopLeft",
+            "node_kind": "Repeat",
+            "node_data": {
+                "count": 2,
+                "stride": [0.0, 50.0],
+                "template": {
+                    "id": "row",
+                    "position": [0.0, 0.0],
+                    "size": [400.0, 50.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Null",
+                    "attributes": { "Tracked": {} }
+                }
+            }
+        }
+    ]
+}"#;
+
+#[test]
+fn items_added_at_runtime_are_torn_down() {
+    let (mut app, root, _) = spawn_bundle(TRACKED_REPEAT_JSON);
+    assert_eq!(app.world.resource::<TrackedNodes>().applied, 2);
+
+    let mut node = NodeEntityMut::new(&mut app.world, root);
+    let mut list = node.child("list");
+    let mut repeat = list.repeat();
+    repeat.set_count(4).unwrap();
+    repeat.set_count(1).unwrap();
+
+    let tracked = app.world.resource::<TrackedNodes>();
+    assert_eq!(tracked.applied, 4);
+    assert_eq!(tracked.despawned, 3);
+}
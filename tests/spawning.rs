@@ -0,0 +1,336 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{
+    asset::{io::memory::Dir, io::Reader, AssetLoader, AsyncReadExt, LoadContext, UntypedAssetId},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use yabuil::{
+    asset::{Layout, LayoutNode, LayoutNodeInner, NodeOverride},
+    components::{
+        spawning::spawn_layout, LayoutBundle, LayoutLoadError, LayoutLoadFailed, LayoutNodeId,
+        LoadedLayout, SpawnLayoutError,
+    },
+    views::NodeEntityMut,
+    LayoutApp, LayoutAttribute, RestrictedLoadContext,
+};
+
+use common::{advance_frames, app, load_layouts, TrackedNodes, ROUND_TRIP_SOURCE};
+
+const LAYOUT_JSON: &str = r#"{
+    "canvas_size": [400, 400],
+    "nodes": [
+        {
+            "id": "title",
+            "position": [0.0, 0.0],
+            "size": [400.0, 50.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null"
+        }
+    ]
+}"#;
+
+/// The load events sent for layout roots, in the order they were sent
+#[derive(Resource, Default)]
+struct RecordedEvents {
+    loaded: Vec<(Entity, AssetId<Layout>)>,
+    failed: Vec<(Entity, AssetId<Layout>, String)>,
+}
+
+fn record_events(
+    mut loaded: EventReader<LoadedLayout>,
+    mut failed: EventReader<LayoutLoadFailed>,
+    mut recorded: ResMut<RecordedEvents>,
+) {
+    recorded
+        .loaded
+        .extend(loaded.read().map(|event| (event.id.0, event.handle.id())));
+    recorded.failed.extend(
+        failed
+            .read()
+            .map(|event| (event.entity, event.handle.id(), event.reason.to_string())),
+    );
+}
+
+/// Spawns a root for `layouts/spawned.layout.json` from the round trip source
+fn spawn_root(mut app: App) -> (App, Entity, Handle<Layout>) {
+    app.init_resource::<RecordedEvents>()
+        .add_systems(Last, record_events);
+
+    let handle: Handle<Layout> = app
+        .world
+        .resource::<AssetServer>()
+        .load(format!("{ROUND_TRIP_SOURCE}://layouts/spawned.layout.json"));
+    let root = app.world.spawn(LayoutBundle::new(handle.clone())).id();
+
+    (app, root, handle)
+}
+
+/// Updates the app until a load event has been sent since `seen` events
+fn wait_for_events(app: &mut App, seen: usize) {
+    for _ in 0..1000 {
+        app.update();
+
+        let recorded = app.world.resource::<RecordedEvents>();
+        if recorded.loaded.len() + recorded.failed.len() > seen {
+            return;
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+
+    panic!("Timed out waiting for a load event");
+}
+
+#[test]
+fn spawned_layouts_send_loaded_layout() {
+    let dir = Dir::default();
+    dir.insert_asset_text(Path::new("layouts/spawned.layout.json"), LAYOUT_JSON);
+
+    let (mut app, root, handle) = spawn_root(app(dir));
+    wait_for_events(&mut app, 0);
+    advance_frames(&mut app, 5);
+
+    let recorded = app.world.resource::<RecordedEvents>();
+    assert_eq!(recorded.loaded, [(root, handle.id())]);
+    assert!(recorded.failed.is_empty());
+}
+
+#[test]
+fn failed_layouts_send_layout_load_failed() {
+    let dir = Dir::default();
+    dir.insert_asset_text(Path::new("layouts/spawned.layout.json"), "{ \"nodes\": ");
+
+    let (mut app, root, handle) = spawn_root(app(dir.clone()));
+    wait_for_events(&mut app, 0);
+
+    let recorded = app.world.resource::<RecordedEvents>();
+    assert_eq!(
+        recorded.failed,
+        [(root, handle.id(), LayoutLoadError::AssetFailed.to_string())]
+    );
+    assert!(recorded.loaded.is_empty());
+
+    // Once the layout is fixed, retrying the root spawns it
+    dir.insert_asset_text(Path::new("layouts/spawned.layout.json"), LAYOUT_JSON);
+    assert!(LayoutBundle::retry(app.world.entity_mut(root)));
+    wait_for_events(&mut app, 1);
+
+    let recorded = app.world.resource::<RecordedEvents>();
+    assert_eq!(recorded.loaded, [(root, handle.id())]);
+    assert_eq!(recorded.failed.len(), 1);
+}
+
+/// An asset type that yabuil does not know about, which fails to load when it is empty
+#[derive(Asset, TypePath)]
+struct Phrase(#[allow(dead_code)] String);
+
+#[derive(Default)]
+struct PhraseLoader;
+
+impl AssetLoader for PhraseLoader {
+    type Asset = Phrase;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _: &'a (),
+        _: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Phrase, Self::Error>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            if text.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "the phrase is empty",
+                ));
+            }
+
+            Ok(Phrase(text))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["phrase"]
+    }
+}
+
+/// Depends on a [`Phrase`] next to the layout
+#[derive(Serialize, Deserialize, TypePath)]
+struct Caption {
+    file: String,
+    #[serde(skip)]
+    phrase: Handle<Phrase>,
+}
+
+impl LayoutAttribute for Caption {
+    const NAME: &'static str = "Caption";
+
+    fn apply(&self, _: NodeEntityMut) {}
+
+    fn initialize_dependencies(&mut self, context: &mut RestrictedLoadContext) {
+        self.phrase = context.load(format!("{ROUND_TRIP_SOURCE}://{}", self.file));
+    }
+
+    fn visit_dependencies(&self, visit_fn: &mut dyn FnMut(UntypedAssetId)) {
+        visit_fn(self.phrase.id().untyped());
+    }
+}
+
+const CAPTION_JSON: &str = r#"{
+    "canvas_size": [400, 400],
+    "nodes": [
+        {
+            "id": "title",
+            "position": [0.0, 0.0],
+            "size": [400.0, 50.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null",
+            "attributes": { "Caption": { "file": "layouts/title.phrase" } }
+        }
+    ]
+}"#;
+
+#[test]
+fn failed_layouts_are_retried_when_any_dependency_loads() {
+    let dir = Dir::default();
+    dir.insert_asset_text(Path::new("layouts/spawned.layout.json"), CAPTION_JSON);
+    dir.insert_asset_text(Path::new("layouts/title.phrase"), "");
+
+    let mut app = app(dir.clone());
+    app.init_asset::<Phrase>()
+        .register_asset_loader(PhraseLoader)
+        .register_layout_attribute::<Caption>();
+
+    let (mut app, root, handle) = spawn_root(app);
+    wait_for_events(&mut app, 0);
+
+    let recorded = app.world.resource::<RecordedEvents>();
+    assert_eq!(
+        recorded.failed,
+        [(
+            root,
+            handle.id(),
+            LayoutLoadError::DependencyFailed.to_string()
+        )]
+    );
+
+    // Fixing the phrase is enough for the root to be spawned, without retrying it
+    dir.insert_asset_text(Path::new("layouts/title.phrase"), "Main Menu");
+    app.world
+        .resource::<AssetServer>()
+        .reload(format!("{ROUND_TRIP_SOURCE}://layouts/title.phrase"));
+    wait_for_events(&mut app, 1);
+
+    let recorded = app.world.resource::<RecordedEvents>();
+    assert_eq!(recorded.loaded, [(root, handle.id())]);
+}
+
+const PARTIAL_JSON: &str = r#"{
+    "canvas_size": [400, 400],
+    "nodes": [
+        {
+            "id": "first",
+            "position": [0.0, 0.0],
+            "size": [400.0, 50.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null",
+            "attributes": { "Tracked": {} }
+        },
+        {
+            "id": "row",
+            "position": [0.0, 50.0],
+            "size": [400.0, 50.0],
+            "anchor": "TopLeft",
+            "node_kind": "Group",
+            "node_data": [
+                {
+                    "id": "icon",
+                    "position": [0.0, 0.0],
+                    "size": [50.0, 50.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Null",
+                    "attributes": { "Tracked": {} }
+                },
+                {
+                    "id": "card",
+                    "position": [50.0, 0.0],
+                    "size": [50.0, 50.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Layout",
+                    "node_data": { "path": "layouts/card.layout.json" }
+                }
+            ]
+        }
+    ]
+}"#;
+
+fn count_nodes(app: &mut App, name: &str) -> usize {
+    app.world
+        .query::<&LayoutNodeId>()
+        .iter(&app.world)
+        .filter(|id| id.name() == name)
+        .count()
+}
+
+#[test]
+fn failed_spawns_leave_no_nodes_behind() {
+    let dir = Dir::default();
+    dir.insert_asset_text(Path::new("layouts/spawned.layout.json"), PARTIAL_JSON);
+    dir.insert_asset_text(Path::new("layouts/card.layout.json"), LAYOUT_JSON);
+
+    let mut app = app(dir);
+    let handles = load_layouts(
+        &mut app,
+        &[
+            format!("{ROUND_TRIP_SOURCE}://layouts/spawned.layout.json"),
+            format!("{ROUND_TRIP_SOURCE}://layouts/card.layout.json"),
+        ],
+    );
+
+    // Sublayouts are loaded from the default asset source, so point the card at the round trip
+    // source, and then unload it so that spawning fails once `first` and `row/icon` are spawned
+    let mut layouts = app.world.resource_mut::<Assets<Layout>>();
+    let LayoutNodeInner::Group(row) = &mut layouts.get_mut(handles[0].id()).unwrap().nodes[1].inner
+    else {
+        panic!("row should be a group node");
+    };
+    let LayoutNodeInner::Layout(card) = &mut row.nodes[1].inner else {
+        panic!("card should be a layout node");
+    };
+    card.handle = handles[1].clone();
+    let card = layouts.remove(handles[1].id()).unwrap();
+
+    let apply = |node: &LayoutNode, _: &[&NodeOverride], mut child: NodeEntityMut| {
+        for attribute in node.attributes.iter() {
+            attribute.apply(child.reborrow());
+        }
+    };
+    let root = app.world.spawn_empty().id();
+    assert!(matches!(
+        spawn_layout(&mut app.world, root, handles[0].clone(), apply),
+        Err(SpawnLayoutError::NotLoaded)
+    ));
+    let children = app.world.get::<Children>(root);
+    assert_eq!(children.map_or(0, |children| children.len()), 0);
+    assert_eq!(count_nodes(&mut app, "first"), 0);
+    assert_eq!(count_nodes(&mut app, "icon"), 0);
+
+    // Retrying spawns every node once
+    app.world
+        .resource_mut::<Assets<Layout>>()
+        .insert(handles[1].id(), card);
+    spawn_layout(&mut app.world, root, handles[0].clone(), apply).unwrap();
+    assert_eq!(app.world.get::<Children>(root).unwrap().len(), 2);
+    let row = NodeEntityMut::new(&mut app.world, root).child("row").id();
+    assert_eq!(app.world.get::<Children>(row).unwrap().len(), 2);
+    assert_eq!(count_nodes(&mut app, "first"), 1);
+    assert_eq!(count_nodes(&mut app, "icon"), 1);
+    assert_eq!(app.world.resource::<TrackedNodes>().applied, 2);
+}
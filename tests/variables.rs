@@ -0,0 +1,367 @@
+mod common;
+
+use std::path::Path;
+
+use bevy::{asset::io::memory::Dir, prelude::*};
+use serde_json::Value;
+use yabuil::{
+    asset::{BoundField, Layout, LayoutNodeInner},
+    components::spawning::spawn_layout,
+    views::NodeEntityMut,
+};
+
+use common::{
+    advance_frames, app, assert_contains, assert_load_fails, load_layouts, to_json,
+    ROUND_TRIP_SOURCE,
+};
+
+const VARIABLES_JSON: &str = r#"{
+    "canvas_size": [200, 100],
+    "variables": {
+        "$accent": [1.0, 0.4, 0.0, 1.0],
+        "$title_size": 40,
+        "$title_offset": [10.0, 20.0],
+        "$price": "$$5",
+        "$highlight": "$accent"
+    },
+    "nodes": [
+        {
+            "id": "title",
+            "position": "$title_offset",
+            "size": [180.0, 60.0],
+            "anchor": "TopLeft",
+            "node_kind": "Text",
+            "node_data": {
+                "text": "$price",
+                "size": "$title_size",
+                "color": "$highlight"
+            }
+        }
+    ],
+    "animations": {
+        "flash": {
+            "title": [
+                {
+                    "timestamp_ms": 0,
+                    "targets": {
+                        "Color": "$accent"
+                    }
+                }
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn variables_are_substituted_and_written_back() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/variables.layout.json"), VARIABLES_JSON);
+
+    let mut app = app(round_trip.clone());
+    let handles = load_layouts(
+        &mut app,
+        &[format!(
+            "{ROUND_TRIP_SOURCE}://layouts/variables.layout.json"
+        )],
+    );
+
+    {
+        let layouts = app.world.resource::<Assets<Layout>>();
+        let layout = layouts.get(handles[0].id()).unwrap();
+        let title = &layout.nodes[0];
+        assert_eq!(title.position, Vec2::new(10.0, 20.0));
+        assert_eq!(title.binding(BoundField::Position), Some("$title_offset"));
+        assert_eq!(title.binding(BoundField::Size), None);
+        assert_eq!(title.binding(BoundField::Color), Some("$highlight"));
+
+        let LayoutNodeInner::Text(text) = &title.inner else {
+            panic!("title should be a text node");
+        };
+        assert_eq!(text.text, "$5");
+        assert_eq!(text.size, 40.0);
+        assert_eq!(text.color, Color::rgba(1.0, 0.4, 0.0, 1.0));
+    }
+
+    let json = to_json(&app, &handles[0]);
+    let written: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(written["variables"]["$price"], "$$5");
+    assert_eq!(
+        written["variables"]["$highlight"],
+        serde_json::json!([1.0, 0.4, 0.0, 1.0])
+    );
+    assert_eq!(written["nodes"][0]["position"], "$title_offset");
+    assert_eq!(written["nodes"][0]["node_data"]["text"], "$price");
+    assert_eq!(written["nodes"][0]["node_data"]["color"], "$highlight");
+    // Animation targets keep the value the variable had when the layout was loaded
+    assert_eq!(
+        written["animations"]["flash"]["title"][0]["targets"]["Color"],
+        serde_json::json!([1.0, 0.4, 0.0, 1.0])
+    );
+
+    round_trip.insert_asset_text(Path::new("layouts/written.layout.json"), &json);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/written.layout.json")],
+    );
+    assert_eq!(to_json(&app, &handles[0]), json);
+}
+
+#[test]
+fn undeclared_variables_are_reported() {
+    assert_load_fails(&VARIABLES_JSON.replace(r#""$title_size": 40,"#, ""));
+}
+
+#[test]
+fn strings_that_are_not_declared_variables_are_kept() {
+    let json = VARIABLES_JSON
+        .replace(r#""text": "$price""#, r#""text": "$5""#)
+        .replace(r#""$price": "$$5","#, r#""$price": "$$accent","#);
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/literal.layout.json"), &json);
+
+    let mut app = app(round_trip);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/literal.layout.json")],
+    );
+
+    let layouts = app.world.resource::<Assets<Layout>>();
+    let layout = layouts.get(handles[0].id()).unwrap();
+    let title = &layout.nodes[0];
+    assert_eq!(title.binding(BoundField::Text), None);
+    let LayoutNodeInner::Text(text) = &title.inner else {
+        panic!("title should be a text node");
+    };
+    assert_eq!(text.text, "$5");
+
+    let written: Value = serde_json::from_str(&to_json(&app, &handles[0])).unwrap();
+    assert_eq!(written["variables"]["$price"], "$$accent");
+    assert_eq!(written["nodes"][0]["node_data"]["text"], "$$5");
+}
+
+const PREFABS_JSON: &str = r#"{
+    "canvas_size": [400, 100],
+    "variables": {
+        "$label": "placeholder",
+        "$label_color": [1.0, 1.0, 1.0, 1.0]
+    },
+    "prefabs": {
+        "tag": {
+            "child_anchor": "TopLeft",
+            "nodes": [
+                {
+                    "id": "background",
+                    "position": [0.0, 0.0],
+                    "size": [100.0, 40.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Null"
+                },
+                {
+                    "id": "label",
+                    "position": [4.0, 4.0],
+                    "size": [92.0, 32.0],
+                    "anchor": "TopLeft",
+                    "node_kind": "Text",
+                    "node_data": {
+                        "text": "$label",
+                        "size": 20.0,
+                        "color": "$label_color"
+                    }
+                }
+            ]
+        },
+        "tag_pair": [
+            {
+                "id": "first",
+                "position": [0.0, 0.0],
+                "size": [100.0, 40.0],
+                "anchor": "TopLeft",
+                "node_kind": "Prefab",
+                "node_data": { "prefab": "tag", "variables": { "$label": "first" } }
+            },
+            {
+                "id": "second",
+                "position": [0.0, 50.0],
+                "size": [100.0, 40.0],
+                "anchor": "TopLeft",
+                "node_kind": "Prefab",
+                "node_data": { "prefab": "tag" }
+            }
+        ]
+    },
+    "nodes": [
+        {
+            "id": "new",
+            "position": [0.0, 0.0],
+            "size": [100.0, 40.0],
+            "anchor": "TopLeft",
+            "node_kind": "Prefab",
+            "node_data": { "prefab": "tag", "variables": { "$label": "NEW" } }
+        },
+        {
+            "id": "pair",
+            "position": [200.0, 0.0],
+            "size": [100.0, 90.0],
+            "anchor": "TopLeft",
+            "node_kind": "Prefab",
+            "node_data": { "prefab": "tag_pair" }
+        }
+    ],
+    "animations": {
+        "blink": {
+            "pair/second/label": [
+                {
+                    "timestamp_ms": 0,
+                    "targets": {
+                        "Color": [1.0, 0.0, 0.0, 1.0]
+                    }
+                }
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn prefabs_are_copied_into_groups() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/prefabs.layout.json"), PREFABS_JSON);
+
+    let mut app = app(round_trip.clone());
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/prefabs.layout.json")],
+    );
+
+    {
+        let layouts = app.world.resource::<Assets<Layout>>();
+        let layout = layouts.get(handles[0].id()).unwrap();
+        assert_eq!(layout.prefabs.len(), 2);
+
+        let label_text = |path: &str| {
+            let node = layout.child_by_id(path).unwrap();
+            let LayoutNodeInner::Text(text) = &node.inner else {
+                panic!("{path} should be a text node");
+            };
+            (text.text.clone(), node.binding(BoundField::Text))
+        };
+
+        // Variables set by the prefab node are fixed for that copy of the prefab
+        assert_eq!(label_text("new/label"), ("NEW".to_string(), None));
+        assert_eq!(label_text("pair/first/label"), ("first".to_string(), None));
+        assert_eq!(
+            label_text("pair/second/label"),
+            ("placeholder".to_string(), Some("$label"))
+        );
+    }
+
+    let json = to_json(&app, &handles[0]);
+    let written: Value = serde_json::from_str(&json).unwrap();
+    assert_contains(
+        &serde_json::from_str(PREFABS_JSON).unwrap(),
+        &written,
+        "prefabs",
+    );
+
+    round_trip.insert_asset_text(Path::new("layouts/written.layout.json"), &json);
+    let handles = load_layouts(
+        &mut app,
+        &[format!("{ROUND_TRIP_SOURCE}://layouts/written.layout.json")],
+    );
+    assert_eq!(to_json(&app, &handles[0]), json);
+}
+
+#[test]
+fn undeclared_prefabs_are_reported() {
+    assert_load_fails(&PREFABS_JSON.replace(r#""prefab": "tag_pair""#, r#""prefab": "tags""#));
+}
+
+const CARD_JSON: &str = r#"{
+    "canvas_size": [200, 100],
+    "variables": {
+        "$offset": [10.0, 0.0]
+    },
+    "nodes": [
+        {
+            "id": "box",
+            "position": "$offset",
+            "size": [20.0, 20.0],
+            "anchor": "TopLeft",
+            "node_kind": "Null"
+        }
+    ],
+    "animations": {
+        "settle": {
+            "box": [
+                { "timestamp_ms": 0, "targets": { "Position": "$offset" } }
+            ]
+        }
+    }
+}"#;
+
+const CARD_PARENT_JSON: &str = r#"{
+    "canvas_size": [200, 100],
+    "variables": {
+        "$offset": [50.0, 0.0]
+    },
+    "nodes": [
+        {
+            "id": "card",
+            "position": [0.0, 0.0],
+            "size": [200.0, 100.0],
+            "anchor": "TopLeft",
+            "node_kind": "Layout",
+            "node_data": { "path": "layouts/card.layout.json" }
+        }
+    ]
+}"#;
+
+fn box_position(app: &mut App, root: Entity) -> f32 {
+    NodeEntityMut::new(&mut app.world, root)
+        .child("card/box")
+        .get::<yabuil::node::Node>()
+        .unwrap()
+        .position
+        .x
+}
+
+#[test]
+fn inherited_variables_only_rebind_node_fields() {
+    let round_trip = Dir::default();
+    round_trip.insert_asset_text(Path::new("layouts/card.layout.json"), CARD_JSON);
+    round_trip.insert_asset_text(Path::new("layouts/parent.layout.json"), CARD_PARENT_JSON);
+
+    let mut app = app(round_trip);
+    app.insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+        std::time::Duration::from_millis(20),
+    ));
+    let handles = load_layouts(
+        &mut app,
+        &[
+            format!("{ROUND_TRIP_SOURCE}://layouts/parent.layout.json"),
+            format!("{ROUND_TRIP_SOURCE}://layouts/card.layout.json"),
+        ],
+    );
+
+    // Sublayouts are loaded from the default asset source, so point the parent at the card
+    let mut layouts = app.world.resource_mut::<Assets<Layout>>();
+    let parent = layouts.get_mut(handles[0].id()).unwrap();
+    let LayoutNodeInner::Layout(card) = &mut parent.nodes[0].inner else {
+        panic!("card should be a layout node");
+    };
+    card.handle = handles[1].clone();
+
+    let root = app.world.spawn_empty().id();
+    spawn_layout(&mut app.world, root, handles[0].clone(), |_, _, _| {}).unwrap();
+
+    // The position of the box follows the value of the parent
+    assert_eq!(box_position(&mut app, root), 50.0);
+
+    // But the keyframe keeps the value that the card declared the variable with
+    NodeEntityMut::new(&mut app.world, root)
+        .child("card")
+        .layout()
+        .play_animation("settle")
+        .unwrap();
+    advance_frames(&mut app, 2);
+    assert_eq!(box_position(&mut app, root), 10.0);
+}